@@ -0,0 +1,368 @@
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, Command};
+use serde::Deserialize;
+
+use super::create;
+use super::forge;
+use super::fs;
+use super::install;
+use super::manifest::{self, Launch, ServerManifest, ServerType};
+use super::quilt;
+
+pub(super) fn build_command() -> Command {
+	Command::new("adopt")
+		.about("Write a manifest for an existing server folder")
+		.arg(
+			Arg::new("server")
+				.help("Server folder name (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("type")
+				.long("type")
+				.short('t')
+				.help("Server type (detected if omitted)")
+				.value_parser(ServerType::names())
+				.required(false),
+		)
+		.arg(
+			Arg::new("version")
+				.long("version")
+				.short('v')
+				.help("Minecraft version (detected if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("ram_mb")
+				.long("ram-mb")
+				.help("RAM in MB used by `minecraft` start")
+				.value_parser(clap::value_parser!(u32))
+				.required(false),
+		)
+		.arg(
+			Arg::new("force")
+				.long("force")
+				.help("Overwrite an existing manifest")
+				.action(clap::ArgAction::SetTrue),
+		)
+}
+
+pub(super) fn run_adopt(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server to adopt",
+	)?;
+
+	if server.manifest.is_some() && !matches.get_flag("force") {
+		anyhow::bail!(
+			"{} already has a {} (use --force)",
+			server.name(),
+			manifest::MANIFEST_FILE
+		);
+	}
+
+	let detected = detect(&server.path);
+	let existing = server.manifest.clone();
+
+	let server_type = matches
+		.get_one::<String>("type")
+		.map(|s| ServerType::parse(s))
+		.transpose()?
+		.or(detected.server_type)
+		.or(existing.as_ref().map(|m| m.server_type))
+		.unwrap_or_else(create::select_server_type);
+
+	let version = match matches.get_one::<String>("version") {
+		Some(v) => install::resolve_version(server_type, v)?,
+		None => detected
+			.version
+			.clone()
+			.or(existing.as_ref().map(|m| m.version.clone()))
+			.unwrap_or_else(create::prompt_version),
+	};
+
+	let props = fs::read_properties(&server.path)?;
+	let mut adopted = merge(existing, server_type, version, &detected);
+	if let Some(port) = props.get("server-port").and_then(|p| p.parse().ok()) {
+		adopted.port = port;
+	}
+	if let Some(motd) = props.get("motd") {
+		adopted.motd = motd.clone();
+	}
+	if let Some(ram) = matches.get_one::<u32>("ram_mb") {
+		adopted.ram_mb = Some(*ram);
+	}
+	if let Some(launch) = loader_launch(&server.path, &mut adopted)? {
+		adopted.launch = launch;
+	}
+
+	manifest::save(&server.path, &adopted)?;
+	println!("Adopted {}: {}", server.name(), adopted.describe());
+	Ok(())
+}
+
+/// The existing manifest with the detected or flagged fields replaced, so
+/// `--force` keeps launch, JVM, Java and backup settings. A different type
+/// or version drops what described the old install.
+fn merge(
+	existing: Option<ServerManifest>,
+	server_type: ServerType,
+	version: String,
+	detected: &Detected,
+) -> ServerManifest {
+	let mut m = match existing {
+		Some(m) if m.server_type == server_type && m.version == version => m,
+		Some(m) => ServerManifest {
+			server_type,
+			version,
+			build: None,
+			loader: None,
+			installer: None,
+			launch: Launch::default(),
+			java_major: None,
+			..m
+		},
+		None => ServerManifest::new(server_type, version, 25565),
+	};
+	if server_type.has_builds() && detected.build.is_some() {
+		m.build = detected.build;
+	}
+	m
+}
+
+/// Quilt, Forge and NeoForge start from something other than `server.jar`;
+/// records that, and the loader version found on disk.
+fn loader_launch(
+	server_dir: &Path,
+	m: &mut ServerManifest,
+) -> anyhow::Result<Option<Launch>> {
+	let flavor = match m.server_type {
+		ServerType::Quilt => return Ok(Some(quilt::launch())),
+		ServerType::Forge => forge::Flavor::Forge,
+		ServerType::NeoForge => forge::Flavor::NeoForge,
+		_ => return Ok(None),
+	};
+	let name = m.server_type.as_str();
+	let installs = forge::detect_installs(flavor, server_dir);
+	let Some((_, loader)) =
+		installs.into_iter().rfind(|(v, _)| *v == m.version)
+	else {
+		anyhow::bail!(
+			"No {name} install for {} found in {}",
+			m.version,
+			server_dir.display()
+		);
+	};
+	let launch = forge::detect_launch(flavor, &m.version, &loader, server_dir)
+		.ok_or_else(|| {
+			anyhow::anyhow!(
+				"{name} {loader} in {} has no launch target for {}",
+				server_dir.display(),
+				m.version
+			)
+		})?;
+	m.loader = Some(loader);
+	Ok(Some(launch))
+}
+
+#[derive(Debug, Default)]
+struct Detected {
+	server_type: Option<ServerType>,
+	version: Option<String>,
+	build: Option<u64>,
+}
+
+/// Paper keeps the running version in `version_history.json`.
+#[derive(Debug, Deserialize)]
+struct PaperVersionHistory {
+	#[serde(rename = "currentVersion")]
+	current_version: String,
+}
+
+fn detect(server_dir: &Path) -> Detected {
+	let history = std::fs::read(server_dir.join("version_history.json"))
+		.ok()
+		.and_then(|b| serde_json::from_slice::<PaperVersionHistory>(&b).ok());
+
+	if let Some(history) = history {
+		let current = &history.current_version;
+		let (version, build) = parse_paper_version(current);
+		let server_type = if current.contains("Purpur") {
+			ServerType::Purpur
+		} else if current.contains("Folia") {
+			ServerType::Folia
+		} else {
+			ServerType::Paper
+		};
+		return Detected {
+			server_type: Some(server_type),
+			version,
+			build,
+		};
+	}
+
+	let marker_types = [
+		("velocity.toml", ServerType::Velocity),
+		("waterfall.yml", ServerType::Waterfall),
+		("purpur.yml", ServerType::Purpur),
+	];
+	for (marker, server_type) in marker_types {
+		if server_dir.join(marker).exists() {
+			return Detected {
+				server_type: Some(server_type),
+				..Detected::default()
+			};
+		}
+	}
+
+	let is_paper = server_dir.join("config").join("paper-global.yml").exists()
+		|| server_dir.join("paper.yml").exists();
+	if is_paper {
+		return Detected {
+			server_type: Some(ServerType::Paper),
+			..Detected::default()
+		};
+	}
+
+	for (flavor, server_type) in [
+		(forge::Flavor::Forge, ServerType::Forge),
+		(forge::Flavor::NeoForge, ServerType::NeoForge),
+	] {
+		if let Some((version, _)) =
+			forge::detect_installs(flavor, server_dir).pop()
+		{
+			return Detected {
+				server_type: Some(server_type),
+				version: Some(version),
+				..Detected::default()
+			};
+		}
+	}
+	if server_dir.join(quilt::LAUNCH_JAR).exists() {
+		return Detected {
+			server_type: Some(ServerType::Quilt),
+			..Detected::default()
+		};
+	}
+
+	let is_fabric = server_dir.join(".fabric").is_dir()
+		|| server_dir
+			.join("fabric-server-launcher.properties")
+			.exists();
+	if is_fabric {
+		return Detected {
+			server_type: Some(ServerType::Fabric),
+			..Detected::default()
+		};
+	}
+
+	Detected::default()
+}
+
+/// Parses e.g. `1.21.4-130-a1b2c3d (MC: 1.21.4)` or
+/// `git-Paper-130 (MC: 1.21.4)` into version and build. Purpur and Folia use
+/// the same format.
+fn parse_paper_version(s: &str) -> (Option<String>, Option<u64>) {
+	let version = s
+		.split_once("(MC: ")
+		.and_then(|(_, rest)| rest.split_once(')'))
+		.map(|(v, _)| v.trim().to_string());
+
+	let head = s.split_once(" (").map(|(h, _)| h).unwrap_or(s);
+	let build = head
+		.split('-')
+		.filter_map(|p| p.parse::<u64>().ok())
+		.next_back();
+
+	(version, build)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_legacy_paper_version() {
+		assert_eq!(
+			parse_paper_version("git-Paper-130 (MC: 1.21.4)"),
+			(Some("1.21.4".to_string()), Some(130))
+		);
+	}
+
+	#[test]
+	fn parses_modern_paper_version() {
+		assert_eq!(
+			parse_paper_version("1.21.4-232-main@a1b2c3d (MC: 1.21.4)"),
+			(Some("1.21.4".to_string()), Some(232))
+		);
+	}
+
+	#[test]
+	fn parses_garbage_as_none() {
+		assert_eq!(parse_paper_version("whatever"), (None, None));
+	}
+
+	#[test]
+	fn merge_keeps_settings_and_drops_stale_install_fields() {
+		let mut existing =
+			ServerManifest::new(ServerType::Fabric, "1.20.1", 25570);
+		existing.loader = Some("0.15.0".into());
+		existing.jvm_args = vec!["-Dfoo=bar".into()];
+		existing.java = Some("/opt/jdk".into());
+		existing.java_major = Some(17);
+		let none = Detected {
+			server_type: None,
+			version: None,
+			build: None,
+		};
+
+		let same = merge(
+			Some(existing.clone()),
+			ServerType::Fabric,
+			"1.20.1".into(),
+			&none,
+		);
+		assert_eq!(same.loader.as_deref(), Some("0.15.0"));
+		assert_eq!(same.java_major, Some(17));
+		assert_eq!(same.port, 25570);
+
+		let moved =
+			merge(Some(existing), ServerType::Fabric, "1.21".into(), &none);
+		assert_eq!(moved.version, "1.21");
+		assert_eq!(moved.loader, None);
+		assert_eq!(moved.java_major, None);
+		assert_eq!(moved.jvm_args, vec!["-Dfoo=bar".to_string()]);
+		assert_eq!(moved.java.as_deref(), Some("/opt/jdk"));
+	}
+
+	#[test]
+	fn detects_forge_installs_and_their_launch() {
+		let dir = fs::TestDir::new("adopt");
+		let lib = dir.join("libraries/net/minecraftforge/forge/1.20.1-47.3.0");
+		std::fs::create_dir_all(&lib).unwrap();
+		std::fs::write(lib.join("unix_args.txt"), b"").unwrap();
+		assert!(fs::has_launch_files(&dir));
+
+		let detected = detect(&dir);
+		assert_eq!(detected.server_type, Some(ServerType::Forge));
+		assert_eq!(detected.version.as_deref(), Some("1.20.1"));
+
+		let mut m = ServerManifest::new(ServerType::Forge, "1.20.1", 25565);
+		let launch = loader_launch(&dir, &mut m).unwrap().unwrap();
+		let lib = "libraries/net/minecraftforge/forge/1.20.1-47.3.0";
+		assert_eq!(
+			launch,
+			Launch::ArgsFile {
+				unix: format!("{lib}/unix_args.txt"),
+				windows: format!("{lib}/win_args.txt"),
+			}
+		);
+		assert_eq!(m.loader.as_deref(), Some("47.3.0"));
+
+		m.version = "1.21.4".to_string();
+		assert!(loader_launch(&dir, &mut m).is_err());
+	}
+}
@@ -0,0 +1,226 @@
+//! World archives: zip (opens anywhere) or tar.zst (smaller, faster).
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub(super) enum Format {
+	#[default]
+	#[serde(rename = "zip")]
+	Zip,
+	#[serde(rename = "tar.zst")]
+	TarZst,
+}
+
+impl Format {
+	pub(super) const ALL: [Self; 2] = [Self::Zip, Self::TarZst];
+
+	/// Also the file extension.
+	pub(super) fn as_str(self) -> &'static str {
+		match self {
+			Self::Zip => "zip",
+			Self::TarZst => "tar.zst",
+		}
+	}
+
+	pub(super) fn parse(s: &str) -> anyhow::Result<Self> {
+		Self::ALL
+			.into_iter()
+			.find(|f| f.as_str() == s.trim())
+			.ok_or_else(|| anyhow::anyhow!("Invalid archive format: {s}"))
+	}
+
+	pub(super) fn names() -> Vec<&'static str> {
+		Self::ALL.iter().map(|f| f.as_str()).collect()
+	}
+
+	pub(super) fn from_path(path: &Path) -> Option<Self> {
+		let name = path.file_name()?.to_str()?;
+		Self::ALL
+			.into_iter()
+			.find(|f| name.ends_with(&format!(".{}", f.as_str())))
+	}
+}
+
+/// Files the server holds open and recreates on start.
+const SKIPPED: [&str; 1] = ["session.lock"];
+
+/// Archives each of `dirs` (relative to `base`) into `dest`, writing through a
+/// `.part` file so a failed backup never looks complete.
+pub(super) fn create(
+	format: Format,
+	base: &Path,
+	dirs: &[PathBuf],
+	dest: &Path,
+) -> anyhow::Result<()> {
+	let part = crate::net::part_path(dest);
+	let result = match format {
+		Format::Zip => create_zip(base, dirs, &part),
+		Format::TarZst => create_tar_zst(base, dirs, &part),
+	};
+	if let Err(e) = result {
+		let _ = std::fs::remove_file(&part);
+		return Err(e);
+	}
+	std::fs::rename(&part, dest)?;
+	Ok(())
+}
+
+/// Zips `files` (path on disk, name in the archive) into `dest`, for
+/// layouts that differ from the folders on disk.
+pub(super) fn create_zip_from(
+	files: &[(PathBuf, String)],
+	dest: &Path,
+) -> anyhow::Result<()> {
+	let part = crate::net::part_path(dest);
+	let result = (|| {
+		let mut zip = zip::ZipWriter::new(std::fs::File::create(&part)?);
+		for (file, name) in files {
+			add_to_zip(&mut zip, file, name)?;
+		}
+		zip.finish()?.flush()?;
+		anyhow::Ok(())
+	})();
+	if let Err(e) = result {
+		let _ = std::fs::remove_file(&part);
+		return Err(e);
+	}
+	std::fs::rename(&part, dest)?;
+	Ok(())
+}
+
+fn create_zip(
+	base: &Path,
+	dirs: &[PathBuf],
+	path: &Path,
+) -> anyhow::Result<()> {
+	let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+	for dir in dirs {
+		for file in walk(dir)? {
+			add_to_zip(&mut zip, &file, &entry_name(base, &file)?)?;
+		}
+	}
+	zip.finish()?.flush()?;
+	Ok(())
+}
+
+fn add_to_zip(
+	zip: &mut zip::ZipWriter<std::fs::File>,
+	file: &Path,
+	name: &str,
+) -> anyhow::Result<()> {
+	let large = file.metadata()?.len() >= u64::from(u32::MAX);
+	let options = zip::write::SimpleFileOptions::default()
+		.compression_method(zip::CompressionMethod::Deflated)
+		.large_file(large);
+	zip.start_file(name, options)?;
+	std::io::copy(&mut std::fs::File::open(file)?, zip)?;
+	Ok(())
+}
+
+fn create_tar_zst(
+	base: &Path,
+	dirs: &[PathBuf],
+	path: &Path,
+) -> anyhow::Result<()> {
+	let encoder = zstd::Encoder::new(std::fs::File::create(path)?, 3)?;
+	let mut tar = tar::Builder::new(encoder);
+	for dir in dirs {
+		for file in walk(dir)? {
+			tar.append_path_with_name(&file, entry_name(base, &file)?)?;
+		}
+	}
+	tar.into_inner()?.finish()?.flush()?;
+	Ok(())
+}
+
+/// Unpacks `archive` into `dest`; both formats refuse entries escaping it.
+pub(super) fn extract(archive: &Path, dest: &Path) -> anyhow::Result<()> {
+	let format = Format::from_path(archive).ok_or_else(|| {
+		anyhow::anyhow!("Unsupported archive: {}", archive.display())
+	})?;
+	let file = std::fs::File::open(archive)?;
+	std::fs::create_dir_all(dest)?;
+
+	match format {
+		Format::Zip => zip::ZipArchive::new(file)?.extract(dest)?,
+		Format::TarZst => {
+			tar::Archive::new(zstd::Decoder::new(file)?).unpack(dest)?
+		}
+	}
+	Ok(())
+}
+
+/// Every regular file below `dir`, skipping [`SKIPPED`] names.
+pub(super) fn walk(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+	let mut out = Vec::new();
+	let mut pending = vec![dir.to_path_buf()];
+	while let Some(dir) = pending.pop() {
+		for entry in std::fs::read_dir(&dir)? {
+			let entry = entry?;
+			let path = entry.path();
+			if entry.file_type()?.is_dir() {
+				pending.push(path);
+			} else if !SKIPPED.iter().any(|s| entry.file_name() == *s) {
+				out.push(path);
+			}
+		}
+	}
+	out.sort();
+	Ok(out)
+}
+
+/// Archive entry name with forward slashes, e.g. `world/region/r.0.0.mca`.
+pub(super) fn entry_name(base: &Path, file: &Path) -> anyhow::Result<String> {
+	let rel = file.strip_prefix(base)?;
+	let parts: Vec<String> = rel
+		.components()
+		.map(|c| c.as_os_str().to_string_lossy().into_owned())
+		.collect();
+	Ok(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn format_from_extension() {
+		assert_eq!(
+			Format::from_path(Path::new("a/s-2025-01-01_00-00-00.zip")),
+			Some(Format::Zip)
+		);
+		assert_eq!(
+			Format::from_path(Path::new("s.tar.zst")),
+			Some(Format::TarZst)
+		);
+		assert_eq!(Format::from_path(Path::new("s.tar.gz")), None);
+	}
+
+	#[test]
+	fn round_trips_both_formats() {
+		let tmp = crate::commands::minecraft::fs::TestDir::new("archive");
+		let world = tmp.join("src").join("world");
+		std::fs::create_dir_all(world.join("region")).unwrap();
+		std::fs::write(world.join("level.dat"), b"level").unwrap();
+		std::fs::write(world.join("region").join("r.0.0.mca"), b"r").unwrap();
+		std::fs::write(world.join("session.lock"), b"").unwrap();
+
+		for format in Format::ALL {
+			let dest = tmp.join(format!("backup.{}", format.as_str()));
+			let dirs = std::slice::from_ref(&world);
+			create(format, &tmp.join("src"), dirs, &dest).unwrap();
+
+			let out = tmp.join(format!("out-{}", format.as_str()));
+			extract(&dest, &out).unwrap();
+			let level = std::fs::read(out.join("world").join("level.dat"));
+			assert_eq!(level.unwrap(), b"level");
+			assert!(out.join("world/region/r.0.0.mca").exists());
+			assert!(!out.join("world/session.lock").exists());
+		}
+	}
+}
@@ -0,0 +1,694 @@
+//! `backup` / `restore`: timestamped world archives under
+//! `<servers root>/.backups/<server>/` with keep-last/daily/weekly pruning.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgMatches, Command};
+use dialoguer::Select;
+
+use super::archive::{self, Format};
+use super::console;
+use super::fs;
+use crate::net;
+
+const RESTORE_TMP: &str = ".eagle-restore.part";
+/// How long to wait for `save-all flush` to report back.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub(super) fn build_command() -> Command {
+	Command::new("backup")
+		.about("Archive a server's world folders")
+		.args_conflicts_with_subcommands(true)
+		.arg(
+			Arg::new("server")
+				.help("Server folder name (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("format")
+				.long("format")
+				.help("Archive format (default: manifest setting, else zip)")
+				.value_parser(Format::names()),
+		)
+		.arg(
+			Arg::new("keep")
+				.long("keep")
+				.help("Keep the newest N backups")
+				.value_parser(clap::value_parser!(usize)),
+		)
+		.arg(
+			Arg::new("keep_daily")
+				.long("keep-daily")
+				.help("Keep one backup per day for the last N days")
+				.value_parser(clap::value_parser!(usize)),
+		)
+		.arg(
+			Arg::new("keep_weekly")
+				.long("keep-weekly")
+				.help("Keep one backup per week for the last N weeks")
+				.value_parser(clap::value_parser!(usize)),
+		)
+		.arg(
+			Arg::new("force")
+				.long("force")
+				.help("Copy the world even if the server seems to be running")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.subcommand(
+			Command::new("list").about("List backups").arg(
+				Arg::new("server")
+					.help("Server folder name (default: all servers)")
+					.required(false),
+			),
+		)
+}
+
+pub(super) fn build_restore_command() -> Command {
+	Command::new("restore")
+		.about("Replace a server's worlds with a backup")
+		.arg(
+			Arg::new("server")
+				.help("Server folder name (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("backup")
+				.help("Backup file name, path or `latest` (prompts if omitted)")
+				.required(false),
+		)
+}
+
+/// A backup archive on disk, named `<server>-<stamp>.<format>`.
+#[derive(Debug, Clone)]
+pub(super) struct Backup {
+	pub(super) path: PathBuf,
+	pub(super) stamp: String,
+	pub(super) size: u64,
+}
+
+impl Backup {
+	fn file_name(&self) -> String {
+		self.path
+			.file_name()
+			.map(|n| n.to_string_lossy().into_owned())
+			.unwrap_or_default()
+	}
+}
+
+/// Which backups survive pruning; all `None` keeps everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Retention {
+	pub(super) keep: Option<usize>,
+	pub(super) daily: Option<usize>,
+	pub(super) weekly: Option<usize>,
+}
+
+impl Retention {
+	fn is_unbounded(&self) -> bool {
+		self.keep.is_none() && self.daily.is_none() && self.weekly.is_none()
+	}
+
+	/// For stamps sorted newest first, whether each one is kept.
+	fn kept(&self, stamps: &[&str]) -> Vec<bool> {
+		if self.is_unbounded() {
+			return vec![true; stamps.len()];
+		}
+
+		let mut days = HashSet::new();
+		let mut weeks = HashSet::new();
+		stamps
+			.iter()
+			.enumerate()
+			.map(|(i, stamp)| {
+				let by_count = self.keep.is_some_and(|n| i < n);
+				let by_day = self.daily.is_some_and(|n| {
+					days.len() < n && days.insert(stamp.get(..10))
+				});
+				let by_week = self.weekly.is_some_and(|n| {
+					weeks.len() < n && weeks.insert(iso_week(stamp))
+				});
+				by_count || by_day || by_week
+			})
+			.collect()
+	}
+}
+
+/// Everything a live backup needs, resolved once when the server starts.
+#[derive(Debug, Clone)]
+pub(super) struct BackupJob {
+	pub(super) server: fs::Server,
+	pub(super) format: Format,
+	pub(super) retention: Retention,
+	pub(super) interval: Option<Duration>,
+}
+
+enum LiveState {
+	Idle,
+	/// `save-off` and `save-all flush` sent; waiting for the save message.
+	Flushing(Instant),
+	Archiving(JoinHandle<anyhow::Result<PathBuf>>),
+}
+
+/// Drives `save-off` -> `save-all flush` -> archive -> `save-on` against a
+/// supervised server. Methods return the console commands to send.
+pub(super) struct LiveBackup {
+	job: BackupJob,
+	state: LiveState,
+	next_due: Option<Instant>,
+}
+
+impl LiveBackup {
+	pub(super) fn new(job: BackupJob) -> Self {
+		let next_due = job.interval.map(|i| Instant::now() + i);
+		Self {
+			job,
+			state: LiveState::Idle,
+			next_due,
+		}
+	}
+
+	pub(super) fn is_busy(&self) -> bool {
+		!matches!(self.state, LiveState::Idle)
+	}
+
+	/// Starts a backup unless one is already running.
+	pub(super) fn request(&mut self) -> Vec<&'static str> {
+		if self.is_busy() {
+			println!("[eagle] A backup is already in progress");
+			return Vec::new();
+		}
+		println!("[eagle] Live backup: flushing the world to disk");
+		self.state = LiveState::Flushing(Instant::now());
+		vec!["save-off", "save-all flush"]
+	}
+
+	/// Feeds a console line; the save confirmation starts the archive.
+	pub(super) fn on_line(&mut self, line: &str) {
+		if !matches!(self.state, LiveState::Flushing(_))
+			|| !is_save_confirmation(line)
+		{
+			return;
+		}
+
+		let job = self.job.clone();
+		self.state = LiveState::Archiving(std::thread::spawn(move || {
+			backup_now(&job.server, job.format, job.retention)
+		}));
+	}
+
+	/// Handles the interval, flush timeouts and archive completion. `ready`
+	/// gates interval backups until the server has finished starting.
+	pub(super) fn poll(&mut self, ready: bool) -> Vec<&'static str> {
+		let now = Instant::now();
+		match &self.state {
+			LiveState::Idle => {
+				if ready && self.next_due.is_some_and(|d| now >= d) {
+					self.next_due = self.job.interval.map(|i| now + i);
+					return self.request();
+				}
+				Vec::new()
+			}
+			LiveState::Flushing(since) => {
+				if now.duration_since(*since) < FLUSH_TIMEOUT {
+					return Vec::new();
+				}
+				println!("[eagle] Live backup aborted: no save confirmation");
+				self.state = LiveState::Idle;
+				vec!["save-on"]
+			}
+			LiveState::Archiving(handle) => {
+				if !handle.is_finished() {
+					return Vec::new();
+				}
+				let LiveState::Archiving(handle) =
+					std::mem::replace(&mut self.state, LiveState::Idle)
+				else {
+					unreachable!();
+				};
+				match handle.join() {
+					Ok(Ok(path)) => {
+						println!("[eagle] Live backup done: {}", path.display())
+					}
+					Ok(Err(e)) => println!("[eagle] Live backup failed: {e}"),
+					Err(_) => println!("[eagle] Live backup thread panicked"),
+				}
+				vec!["save-on"]
+			}
+		}
+	}
+}
+
+/// `Saved the game` (1.13+) or `Saved the world` (older) after `save-all`.
+fn is_save_confirmation(line: &str) -> bool {
+	line.contains("Saved the game") || line.contains("Saved the world")
+}
+
+pub(super) fn run_backup(matches: &ArgMatches) -> anyhow::Result<()> {
+	if let Some(("list", sub)) = matches.subcommand() {
+		return list(sub.get_one::<String>("server").map(|s| s.as_str()));
+	}
+
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server to back up",
+	)?;
+
+	if !matches.get_flag("force") && fs::is_running(&server)? {
+		anyhow::bail!(
+			"{} appears to be running (port {} is open); stop it first or pass --force",
+			server.name(),
+			fs::server_port(&server)?
+		);
+	}
+
+	let job = job_for(&server, matches)?;
+	backup_now(&server, job.format, job.retention)?;
+	Ok(())
+}
+
+/// Manifest `backup` settings, overridden by any flags present in `matches`.
+pub(super) fn job_for(
+	server: &fs::Server,
+	matches: &ArgMatches,
+) -> anyhow::Result<BackupJob> {
+	let settings = server
+		.manifest
+		.as_ref()
+		.map(|m| m.backup.clone())
+		.unwrap_or_default();
+	let flag = |id: &str| matches.try_get_one::<usize>(id).ok().flatten();
+
+	let format = match matches.try_get_one::<String>("format").ok().flatten() {
+		Some(f) => Format::parse(f)?,
+		None => settings.format.unwrap_or_default(),
+	};
+	let interval = matches
+		.try_get_one::<u64>("backup_every")
+		.ok()
+		.flatten()
+		.copied()
+		.or(settings.interval_minutes)
+		.filter(|m| *m > 0)
+		.map(|m| Duration::from_secs(m * 60));
+
+	Ok(BackupJob {
+		server: server.clone(),
+		format,
+		retention: Retention {
+			keep: flag("keep").copied().or(settings.keep),
+			daily: flag("keep_daily").copied().or(settings.keep_daily),
+			weekly: flag("keep_weekly").copied().or(settings.keep_weekly),
+		},
+		interval,
+	})
+}
+
+/// Archives the server's worlds and prunes old backups. The caller is
+/// responsible for the world being quiescent.
+pub(super) fn backup_now(
+	server: &fs::Server,
+	format: Format,
+	retention: Retention,
+) -> anyhow::Result<PathBuf> {
+	let worlds = fs::world_dirs(&server.path)?;
+	if worlds.is_empty() {
+		anyhow::bail!("No world folders found in {}", server.path.display());
+	}
+
+	let dir = fs::backups_dir(&server.path);
+	std::fs::create_dir_all(&dir)?;
+	let dest = dir.join(format!(
+		"{}-{}.{}",
+		server.name(),
+		console::stamp(),
+		format.as_str()
+	));
+
+	println!("Backing up {} to {}...", server.name(), dest.display());
+	archive::create(format, &server.path, &worlds, &dest)?;
+	let size = std::fs::metadata(&dest)?.len();
+	println!("Backup written ({})", net::format_bytes(size));
+
+	prune(&dir, retention)?;
+	Ok(dest)
+}
+
+fn prune(dir: &Path, retention: Retention) -> anyhow::Result<()> {
+	let backups = list_backups(dir)?;
+	let stamps: Vec<&str> = backups.iter().map(|b| b.stamp.as_str()).collect();
+	for (backup, keep) in backups.iter().zip(retention.kept(&stamps)) {
+		if !keep {
+			println!("Pruning {}", backup.file_name());
+			std::fs::remove_file(&backup.path)?;
+		}
+	}
+	Ok(())
+}
+
+/// Backups in `dir`, newest first. Unrelated files are ignored.
+pub(super) fn list_backups(dir: &Path) -> anyhow::Result<Vec<Backup>> {
+	if !dir.is_dir() {
+		return Ok(Vec::new());
+	}
+
+	let mut out = Vec::new();
+	for entry in std::fs::read_dir(dir)? {
+		let entry = entry?;
+		let path = entry.path();
+		if !entry.file_type()?.is_file() {
+			continue;
+		}
+		let Some(stamp) = path
+			.file_name()
+			.and_then(|n| n.to_str())
+			.and_then(parse_stamp)
+		else {
+			continue;
+		};
+		out.push(Backup {
+			stamp: stamp.to_string(),
+			size: entry.metadata()?.len(),
+			path,
+		});
+	}
+
+	out.sort_by(|a, b| b.stamp.cmp(&a.stamp));
+	Ok(out)
+}
+
+/// `survival-2025-01-31_18-04-09.zip` -> `2025-01-31_18-04-09`.
+fn parse_stamp(file_name: &str) -> Option<&str> {
+	let format = Format::from_path(Path::new(file_name))?;
+	let stem = file_name.strip_suffix(&format!(".{}", format.as_str()))?;
+	let stamp = stem.get(stem.len().checked_sub(19)?..)?;
+	let shape_ok = stamp.char_indices().all(|(i, c)| match i {
+		4 | 7 | 13 | 16 => c == '-',
+		10 => c == '_',
+		_ => c.is_ascii_digit(),
+	});
+	(shape_ok && stem[..stem.len() - 19].ends_with('-')).then_some(stamp)
+}
+
+fn iso_week(stamp: &str) -> Option<(i32, u8)> {
+	let year = stamp.get(..4)?.parse().ok()?;
+	let month: u8 = stamp.get(5..7)?.parse().ok()?;
+	let day = stamp.get(8..10)?.parse().ok()?;
+	let month = time::Month::try_from(month).ok()?;
+	let date = time::Date::from_calendar_date(year, month, day).ok()?;
+	let (year, week, _) = date.to_iso_week_date();
+	Some((year, week))
+}
+
+fn list(name: Option<&str>) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let servers = match name {
+		Some(name) => vec![fs::select_server(&roots, Some(name), "")?],
+		None => fs::find_servers(&roots)?,
+	};
+
+	let mut any = false;
+	for server in servers {
+		let name = server.name();
+		let backups = list_backups(&fs::backups_dir(&server.path))?;
+		if backups.is_empty() {
+			continue;
+		}
+		any = true;
+		println!("{name}:");
+		for b in backups {
+			println!(
+				"  {:<48} {:>10}",
+				b.file_name(),
+				net::format_bytes(b.size)
+			);
+		}
+	}
+
+	if !any {
+		println!("No backups found.");
+	}
+	Ok(())
+}
+
+pub(super) fn run_restore(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server to restore",
+	)?;
+
+	if fs::is_running(&server)? {
+		anyhow::bail!(
+			"{} appears to be running (port {} is open); stop it before restoring",
+			server.name(),
+			fs::server_port(&server)?
+		);
+	}
+
+	let dir = fs::backups_dir(&server.path);
+	let backup = resolve_backup(
+		&dir,
+		matches.get_one::<String>("backup").map(|s| s.as_str()),
+	)?;
+
+	let tmp = server.path.join(RESTORE_TMP);
+	if tmp.exists() {
+		std::fs::remove_dir_all(&tmp)?;
+	}
+	let mut guard = fs::DirGuard::new(tmp.clone());
+
+	println!("Extracting {}...", backup.display());
+	archive::extract(&backup, &tmp)?;
+
+	let mut names = Vec::new();
+	for entry in std::fs::read_dir(&tmp)? {
+		let entry = entry?;
+		if entry.file_type()?.is_dir() {
+			names.push(entry.file_name());
+		}
+	}
+	if names.is_empty() {
+		anyhow::bail!("{} contains no world folders", backup.display());
+	}
+
+	let safety = dir.join(format!("safety-{}", console::stamp()));
+	swap_worlds(&server.path, &tmp, &names, &safety)?;
+
+	std::fs::remove_dir_all(&tmp)?;
+	guard.commit();
+
+	println!("Restored {} from {}", server.name(), backup.display());
+	if safety.exists() {
+		println!("Previous worlds kept in {}", safety.display());
+	}
+	Ok(())
+}
+
+/// `latest`, a file name from the server's backups, or a path; prompts with
+/// the server's backups when omitted.
+fn resolve_backup(dir: &Path, wanted: Option<&str>) -> anyhow::Result<PathBuf> {
+	let backups = list_backups(dir)?;
+
+	let Some(wanted) = wanted else {
+		if backups.is_empty() {
+			anyhow::bail!("No backups in {}", dir.display());
+		}
+		let items: Vec<String> =
+			backups.iter().map(Backup::file_name).collect();
+		let selection = Select::new()
+			.with_prompt("Select a backup")
+			.items(&items)
+			.default(0)
+			.interact()?;
+		return Ok(backups[selection].path.clone());
+	};
+
+	if wanted == "latest" {
+		return backups
+			.first()
+			.map(|b| b.path.clone())
+			.ok_or_else(|| anyhow::anyhow!("No backups in {}", dir.display()));
+	}
+	if let Some(b) = backups.iter().find(|b| b.file_name() == wanted) {
+		return Ok(b.path.clone());
+	}
+
+	let path = PathBuf::from(wanted);
+	if path.is_file() {
+		return Ok(path);
+	}
+	anyhow::bail!("Backup not found: {wanted}")
+}
+
+/// Rename, falling back to copy + delete across filesystems.
+/// Moves every current world folder (and anything the backup would
+/// overwrite) into `safety`, then the restored folders from `restored` into
+/// the server. On failure everything moved so far goes back.
+fn swap_worlds(
+	server_dir: &Path,
+	restored: &Path,
+	names: &[std::ffi::OsString],
+	safety: &Path,
+) -> anyhow::Result<()> {
+	let mut current = fs::world_dirs(server_dir)?;
+	for name in names {
+		let path = server_dir.join(name);
+		if path.exists() && !current.contains(&path) {
+			current.push(path);
+		}
+	}
+
+	let mut moved = Vec::new();
+	let result = (|| {
+		for path in &current {
+			std::fs::create_dir_all(safety)?;
+			let to = safety.join(path.file_name().unwrap_or_default());
+			move_dir(path, &to)?;
+			moved.push((path.clone(), to));
+		}
+		for name in names {
+			let to = server_dir.join(name);
+			move_dir(&restored.join(name), &to)?;
+			moved.push((restored.join(name), to));
+		}
+		Ok(())
+	})();
+
+	if let Err(e) = result {
+		for (from, to) in moved.iter().rev() {
+			if let Err(undo) = move_dir(to, from) {
+				eprintln!(
+					"Could not move {} back to {}: {undo}",
+					to.display(),
+					from.display()
+				);
+			}
+		}
+		if std::fs::read_dir(safety).is_ok_and(|mut d| d.next().is_none()) {
+			let _ = std::fs::remove_dir(safety);
+		}
+		return Err(e);
+	}
+	Ok(())
+}
+
+fn move_dir(from: &Path, to: &Path) -> anyhow::Result<()> {
+	if std::fs::rename(from, to).is_ok() {
+		return Ok(());
+	}
+	fs::copy_dir(from, to)?;
+	std::fs::remove_dir_all(from)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_backup_file_names() {
+		assert_eq!(
+			parse_stamp("my-server-2025-01-31_18-04-09.zip"),
+			Some("2025-01-31_18-04-09")
+		);
+		assert_eq!(
+			parse_stamp("s-2025-01-31_18-04-09.tar.zst"),
+			Some("2025-01-31_18-04-09")
+		);
+		assert_eq!(parse_stamp("2025-01-31_18-04-09.zip"), None);
+		assert_eq!(parse_stamp("s-2025-01-31.zip"), None);
+		assert_eq!(parse_stamp("s-2025-01-31_18-04-09.zip.part"), None);
+	}
+
+	#[test]
+	fn recognises_save_confirmations() {
+		assert!(is_save_confirmation(
+			"[12:00:00] [Server thread/INFO]: Saved the game"
+		));
+		assert!(is_save_confirmation("[INFO] Saved the world"));
+		assert!(!is_save_confirmation(
+			"[INFO]: Saving the game (this may take a moment!)"
+		));
+	}
+
+	#[test]
+	fn keep_last_n() {
+		let stamps = [
+			"2025-01-03_00-00-00",
+			"2025-01-02_00-00-00",
+			"2025-01-01_00-00-00",
+		];
+		let r = Retention {
+			keep: Some(2),
+			..Retention::default()
+		};
+		assert_eq!(r.kept(&stamps), [true, true, false]);
+		assert_eq!(Retention::default().kept(&stamps), [true, true, true]);
+	}
+
+	#[test]
+	fn daily_and_weekly_keep_newest_per_bucket() {
+		// 2025-01-06 is a Monday, so the 5th is the previous ISO week.
+		let stamps = [
+			"2025-01-07_12-00-00",
+			"2025-01-07_06-00-00",
+			"2025-01-06_12-00-00",
+			"2025-01-05_12-00-00",
+			"2024-12-29_12-00-00",
+			"2024-12-20_12-00-00",
+		];
+		let daily = Retention {
+			daily: Some(2),
+			..Retention::default()
+		};
+		assert_eq!(
+			daily.kept(&stamps),
+			[true, false, true, false, false, false]
+		);
+
+		let weekly = Retention {
+			weekly: Some(3),
+			..Retention::default()
+		};
+		assert_eq!(
+			weekly.kept(&stamps),
+			[true, false, false, true, true, false]
+		);
+	}
+
+	#[test]
+	fn swap_worlds_saves_every_world_and_rolls_back() {
+		let dir = fs::TestDir::new("restore");
+		let server = dir.join("s");
+		let restored = dir.join("restored");
+		for world in ["world", "world_nether"] {
+			std::fs::create_dir_all(server.join(world)).unwrap();
+			std::fs::write(server.join(world).join("level.dat"), "old")
+				.unwrap();
+		}
+		std::fs::create_dir_all(restored.join("world")).unwrap();
+		std::fs::write(restored.join("world/level.dat"), "new").unwrap();
+
+		let names = ["world".into(), "missing".into()];
+		let safety = dir.join("safety-1");
+		assert!(swap_worlds(&server, &restored, &names, &safety).is_err());
+		let read = |p: &str| std::fs::read_to_string(server.join(p)).unwrap();
+		assert_eq!(read("world/level.dat"), "old");
+		assert_eq!(read("world_nether/level.dat"), "old");
+		assert!(restored.join("world/level.dat").exists());
+		assert!(!safety.exists());
+
+		let safety = dir.join("safety-2");
+		swap_worlds(&server, &restored, &names[..1], &safety).unwrap();
+		assert_eq!(read("world/level.dat"), "new");
+		assert!(!server.join("world_nether").exists());
+		assert!(safety.join("world_nether/level.dat").exists());
+		assert!(safety.join("world/level.dat").exists());
+	}
+}
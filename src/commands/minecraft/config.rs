@@ -0,0 +1,197 @@
+//! `minecraft config`: read and edit `server.properties` with validation.
+
+use clap::{Arg, ArgMatches, Command};
+
+use super::fs;
+use super::properties::{self, PROPERTIES_FILE, Properties};
+
+pub(super) fn build_command() -> Command {
+	let key = || {
+		Arg::new("key")
+			.help("Property name, e.g. difficulty")
+			.required(true)
+	};
+
+	Command::new("config")
+		.about("Get or change server.properties values")
+		.arg(Arg::new("server").help("Server folder name").required(true))
+		.subcommand_required(true)
+		.subcommand(Command::new("get").about("Print one value").arg(key()))
+		.subcommand(
+			Command::new("set")
+				.about("Set a value (validated for known keys)")
+				.arg(key())
+				.arg(
+					Arg::new("value")
+						.help("New value")
+						.required(true)
+						.allow_hyphen_values(true),
+				)
+				.arg(
+					Arg::new("force")
+						.long("force")
+						.help("Allow unknown keys and invalid values")
+						.action(clap::ArgAction::SetTrue),
+				),
+		)
+		.subcommand(Command::new("unset").about("Remove a key").arg(key()))
+		.subcommand(
+			Command::new("list").about("Print every value").arg(
+				Arg::new("json")
+					.long("json")
+					.help("Print the values as a JSON object")
+					.action(clap::ArgAction::SetTrue),
+			),
+		)
+		.subcommand(
+			Command::new("diff-defaults")
+				.about("Show values that differ from vanilla defaults"),
+		)
+}
+
+pub(super) fn run_config(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server",
+	)?;
+	if server
+		.manifest
+		.as_ref()
+		.is_some_and(|m| m.server_type.is_proxy())
+	{
+		anyhow::bail!(
+			"{} is a proxy; edit its velocity.toml / config.yml instead",
+			server.name()
+		);
+	}
+	if !Properties::path(&server.path).exists() {
+		anyhow::bail!(
+			"{} has no {PROPERTIES_FILE} yet (start it once first)",
+			server.name()
+		);
+	}
+
+	let mut props = Properties::load(&server.path)?;
+	match matches.subcommand() {
+		Some(("get", sub)) => {
+			let key = sub.get_one::<String>("key").expect("required");
+			match props.get(key) {
+				Some(value) => println!("{value}"),
+				None => anyhow::bail!(
+					"{key} is not set{}",
+					properties::key_hint(key)
+				),
+			}
+		}
+		Some(("set", sub)) => {
+			let key = sub.get_one::<String>("key").expect("required");
+			let value = sub.get_one::<String>("value").expect("required");
+			let value = validate(key, value, sub.get_flag("force"))?;
+			props.set(key, &value);
+			props.save(&server.path)?;
+			println!("{key}={value}");
+			restart_note(&server)?;
+		}
+		Some(("unset", sub)) => {
+			let key = sub.get_one::<String>("key").expect("required");
+			if !props.unset(key) {
+				anyhow::bail!("{key} is not set{}", properties::key_hint(key));
+			}
+			props.save(&server.path)?;
+			println!("Removed {key}");
+			restart_note(&server)?;
+		}
+		Some(("list", sub)) => {
+			if sub.get_flag("json") {
+				let map: serde_json::Map<String, serde_json::Value> = props
+					.entries()
+					.map(|(k, v)| (k.to_string(), v.into()))
+					.collect();
+				println!("{}", serde_json::to_string_pretty(&map)?);
+				return Ok(());
+			}
+			for (key, value) in props.entries() {
+				println!("{key}={value}{}", problem(key, value));
+			}
+		}
+		Some(("diff-defaults", _)) => diff_defaults(&props),
+		_ => unreachable!("subcommand_required"),
+	}
+	Ok(())
+}
+
+/// Checks `value` against the key's type; returns it normalized.
+fn validate(key: &str, value: &str, force: bool) -> anyhow::Result<String> {
+	let Some(spec) = properties::lookup(key) else {
+		if force {
+			return Ok(value.to_string());
+		}
+		anyhow::bail!(
+			"Unknown property {key}{} (use --force to set it anyway)",
+			properties::key_hint(key)
+		);
+	};
+	match spec.parse(value) {
+		Ok(typed) => Ok(typed.to_string()),
+		Err(_) if force => Ok(value.to_string()),
+		Err(e) => Err(e),
+	}
+}
+
+/// Trailing warning for `list`.
+fn problem(key: &str, value: &str) -> String {
+	match properties::lookup(key) {
+		Some(spec) => match spec.parse(value) {
+			Ok(_) => String::new(),
+			Err(e) => format!("  <- {e}"),
+		},
+		None => match properties::key_hint(key) {
+			hint if hint.is_empty() => String::new(),
+			hint => format!("  <- unknown key{hint}"),
+		},
+	}
+}
+
+fn diff_defaults(props: &Properties) {
+	let mut same = true;
+	for spec in properties::KNOWN {
+		let Some(value) = props.get(spec.key) else {
+			continue;
+		};
+		let differs = match (spec.parse(value), spec.parse(spec.default)) {
+			(Ok(a), Ok(b)) => a != b,
+			_ => value != spec.default,
+		};
+		if differs {
+			same = false;
+			println!(
+				"{}={value}  (default: {}, {})",
+				spec.key,
+				display_default(spec.default),
+				spec.describe()
+			);
+		}
+	}
+	for (key, value) in props.entries() {
+		if properties::lookup(key).is_none() {
+			same = false;
+			println!("{key}={value}  (not a vanilla key)");
+		}
+	}
+	if same {
+		println!("All values match the vanilla defaults");
+	}
+}
+
+fn display_default(default: &str) -> &str {
+	if default.is_empty() { "empty" } else { default }
+}
+
+fn restart_note(server: &fs::Server) -> anyhow::Result<()> {
+	if fs::is_running(server)? {
+		println!("{} is running; restart it to apply.", server.name());
+	}
+	Ok(())
+}
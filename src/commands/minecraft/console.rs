@@ -0,0 +1,325 @@
+//! Supervised mode: eagle owns the server's stdio instead of handing the
+//! terminal to `java`, so it can log, watch for startup and stop cleanly.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Mutex, Once, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::backup::{BackupJob, LiveBackup};
+use super::start::LaunchPlan;
+
+pub(super) const LOG_DIR: &str = "eagle-logs";
+const TICK: Duration = Duration::from_millis(100);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub(super) struct Options {
+	/// Console command that shuts the server down (`stop`, or `end` on
+	/// proxies).
+	pub(super) stop_command: &'static str,
+	/// How long to wait after sending it before killing the process.
+	pub(super) stop_timeout: Duration,
+	/// Enables live backups (`:backup`, and the interval if set).
+	pub(super) backup: Option<BackupJob>,
+}
+
+/// Typed into a supervised console, handled by eagle instead of the server.
+const BACKUP_COMMAND: &str = ":backup";
+
+/// How a supervised run ended.
+pub(super) struct Outcome {
+	pub(super) status: ExitStatus,
+	/// The user asked for the stop (Ctrl+C), as opposed to a crash or an
+	/// in-game `/stop`.
+	pub(super) requested: bool,
+	pub(super) ran_for: Duration,
+	pub(super) log: PathBuf,
+}
+
+/// Runs the server with piped stdio until it exits.
+pub(super) fn supervise(
+	plan: &LaunchPlan,
+	opts: &Options,
+) -> anyhow::Result<Outcome> {
+	install_interrupt_handler()?;
+	INTERRUPTED.store(false, Ordering::SeqCst);
+
+	let log_path = plan.dir.join(LOG_DIR).join(format!("{}.log", stamp()));
+	let mut log = open_log(&log_path)?;
+	println!("[eagle] Logging console to {}", log_path.display());
+
+	let started = Instant::now();
+	let mut child = spawn(plan)?;
+	let mut stdin = child.stdin.take();
+
+	let (tx, rx) = mpsc::channel();
+	forward_lines(child.stdout.take(), tx.clone());
+	forward_lines(child.stderr.take(), tx);
+	let input = stdin_lines();
+
+	let mut live = opts.backup.clone().map(LiveBackup::new);
+	let mut ready = false;
+	let mut stop_pending = false;
+	let mut stop_deadline: Option<Instant> = None;
+	let mut killed = false;
+	loop {
+		match rx.recv_timeout(TICK) {
+			Ok(line) => {
+				println!("{line}");
+				writeln!(log, "[{}] {line}", clock())?;
+				if let Some(took) = parse_done(&line) {
+					ready = true;
+					println!(
+						"[eagle] Server ready in {took} ({:.1}s since launch)",
+						started.elapsed().as_secs_f64()
+					);
+				}
+				if let Some(live) = live.as_mut() {
+					live.on_line(&line);
+				}
+			}
+			Err(RecvTimeoutError::Disconnected) => break,
+			Err(RecvTimeoutError::Timeout) => {}
+		}
+
+		let mut stop_now = false;
+		if INTERRUPTED.swap(false, Ordering::SeqCst) {
+			let busy = live.as_ref().is_some_and(|l| l.is_busy());
+			if stop_deadline.is_some() {
+				println!("[eagle] Killing server");
+				child.kill()?;
+				killed = true;
+			} else if busy && !stop_pending {
+				println!(
+					"[eagle] Stopping after the live backup (Ctrl+C again to stop now)"
+				);
+				stop_pending = true;
+			} else {
+				stop_now = true;
+			}
+		}
+
+		while let Ok(line) = input.lock().map_err(poisoned)?.try_recv() {
+			if line != BACKUP_COMMAND {
+				send(&mut stdin, &mut log, &line)?;
+			} else if let Some(live) = live.as_mut() {
+				for command in live.request() {
+					send(&mut stdin, &mut log, command)?;
+				}
+			} else {
+				println!(
+					"[eagle] Live backups are not available for this server"
+				);
+			}
+		}
+
+		if let Some(live) = live.as_mut() {
+			for command in live.poll(ready && stop_deadline.is_none()) {
+				send(&mut stdin, &mut log, command)?;
+			}
+		}
+
+		let busy = live.as_ref().is_some_and(|l| l.is_busy());
+		if stop_deadline.is_none() && (stop_now || stop_pending && !busy) {
+			println!(
+				"[eagle] Sending `{}` (Ctrl+C again to kill)",
+				opts.stop_command
+			);
+			send(&mut stdin, &mut log, opts.stop_command)?;
+			stop_deadline = Some(Instant::now() + opts.stop_timeout);
+		}
+
+		if !killed && stop_deadline.is_some_and(|d| Instant::now() >= d) {
+			println!(
+				"[eagle] Server did not stop within {}s; killing",
+				opts.stop_timeout.as_secs()
+			);
+			child.kill()?;
+			killed = true;
+		}
+	}
+
+	let status = child.wait()?;
+	writeln!(log, "[{}] [eagle] Server exited: {status}", clock())?;
+	Ok(Outcome {
+		status,
+		requested: stop_deadline.is_some(),
+		ran_for: started.elapsed(),
+		log: log_path,
+	})
+}
+
+fn spawn(plan: &LaunchPlan) -> anyhow::Result<Child> {
+	let mut cmd = plan.command();
+	cmd.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped());
+
+	// Keep the terminal's Ctrl+C away from java; eagle sends `stop` instead.
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		cmd.process_group(0);
+	}
+	#[cfg(windows)]
+	{
+		use std::os::windows::process::CommandExt;
+		const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+		cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+	}
+
+	Ok(cmd.spawn()?)
+}
+
+fn open_log(path: &Path) -> anyhow::Result<std::fs::File> {
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+	Ok(std::fs::OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path)?)
+}
+
+/// Writes a console command to the server, echoing it into the log.
+fn send(
+	stdin: &mut Option<std::process::ChildStdin>,
+	log: &mut std::fs::File,
+	command: &str,
+) -> anyhow::Result<()> {
+	writeln!(log, "[{}] > {command}", clock())?;
+	let Some(pipe) = stdin.as_mut() else {
+		return Ok(());
+	};
+	// A closed pipe means the server is already going down.
+	if writeln!(pipe, "{command}")
+		.and_then(|_| pipe.flush())
+		.is_err()
+	{
+		*stdin = None;
+	}
+	Ok(())
+}
+
+fn forward_lines<R>(pipe: Option<R>, tx: Sender<String>)
+where
+	R: std::io::Read + Send + 'static,
+{
+	let Some(pipe) = pipe else {
+		return;
+	};
+	std::thread::spawn(move || {
+		for line in BufReader::new(pipe).lines() {
+			let Ok(line) = line else {
+				break;
+			};
+			if tx.send(line).is_err() {
+				break;
+			}
+		}
+	});
+}
+
+/// Terminal input, read by one thread for the whole process so restarts
+/// don't leave a second reader racing the first.
+fn stdin_lines() -> &'static Mutex<Receiver<String>> {
+	static LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+	LINES.get_or_init(|| {
+		let (tx, rx) = mpsc::channel();
+		std::thread::spawn(move || {
+			let stdin = std::io::stdin();
+			for line in stdin.lock().lines() {
+				let Ok(line) = line else {
+					break;
+				};
+				let line = line.trim().to_string();
+				if !line.is_empty() && tx.send(line).is_err() {
+					break;
+				}
+			}
+		});
+		Mutex::new(rx)
+	})
+}
+
+/// Sleeps for `duration`; returns `true` early if Ctrl+C was pressed.
+pub(super) fn sleep_interruptible(duration: Duration) -> anyhow::Result<bool> {
+	install_interrupt_handler()?;
+	INTERRUPTED.store(false, Ordering::SeqCst);
+
+	let deadline = Instant::now() + duration;
+	while Instant::now() < deadline {
+		if INTERRUPTED.swap(false, Ordering::SeqCst) {
+			return Ok(true);
+		}
+		std::thread::sleep(TICK);
+	}
+	Ok(false)
+}
+
+fn install_interrupt_handler() -> anyhow::Result<()> {
+	static ONCE: Once = Once::new();
+	let mut result = Ok(());
+	ONCE.call_once(|| {
+		result = ctrlc::set_handler(|| {
+			INTERRUPTED.store(true, Ordering::SeqCst);
+		});
+	});
+	result.map_err(|e| anyhow::anyhow!("Could not install Ctrl+C handler: {e}"))
+}
+
+fn poisoned<T>(_: std::sync::PoisonError<T>) -> anyhow::Error {
+	anyhow::anyhow!("stdin reader lock poisoned")
+}
+
+/// `Done (3.456s)! For help, type "help"` -> `3.456s`.
+fn parse_done(line: &str) -> Option<&str> {
+	let rest = &line[line.find("Done (")? + "Done (".len()..];
+	let end = rest.find(")!")?;
+	rest[end..].starts_with(")! For help").then(|| &rest[..end])
+}
+
+fn now() -> time::OffsetDateTime {
+	time::OffsetDateTime::now_local()
+		.unwrap_or_else(|_| time::OffsetDateTime::now_utc())
+}
+
+/// File-name-safe local timestamp, e.g. `2025-01-31_18-04-09`.
+pub(super) fn stamp() -> String {
+	let t = now();
+	format!(
+		"{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+		t.year(),
+		u8::from(t.month()),
+		t.day(),
+		t.hour(),
+		t.minute(),
+		t.second()
+	)
+}
+
+fn clock() -> String {
+	let t = now();
+	format!("{:02}:{:02}:{:02}", t.hour(), t.minute(), t.second())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn detects_startup_line() {
+		let line = r#"[18:04:12 INFO]: Done (3.456s)! For help, type "help""#;
+		assert_eq!(parse_done(line), Some("3.456s"));
+
+		let vanilla = r#"[18:04:12] [Server thread/INFO]: Done (12.001s)! For help, type "help""#;
+		assert_eq!(parse_done(vanilla), Some("12.001s"));
+
+		assert_eq!(parse_done("[INFO]: Done (1s)! Not the startup line"), None);
+		assert_eq!(parse_done("Preparing spawn area: 84%"), None);
+	}
+}
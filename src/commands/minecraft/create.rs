@@ -1,11 +1,39 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use clap::parser::ValueSource;
 use clap::{Arg, ArgMatches, Command};
 use dialoguer::{Input, Select};
 
-use super::fabric;
 use super::fs;
-use super::paper;
+use super::install::{self, Pin};
+use super::java;
+use super::manifest::{self, ServerManifest, ServerType};
+use super::mrpack;
+use super::ports;
+use super::properties::{self, Properties};
+use super::rcon;
+use super::wizard;
+use super::world;
+
+/// Flags that set one `server.properties` key: (arg id, key).
+const PROPERTY_FLAGS: [(&str, &str); 7] = [
+	("gamemode", "gamemode"),
+	("difficulty", "difficulty"),
+	("max_players", "max-players"),
+	("seed", "level-seed"),
+	("level_type", "level-type"),
+	("view_distance", "view-distance"),
+	("simulation_distance", "simulation-distance"),
+];
+
+/// Switches that set a key to a fixed value: (arg id, key, value).
+const PROPERTY_SWITCHES: [(&str, &str, &str); 5] = [
+	("offline", "online-mode", "false"),
+	("whitelist", "white-list", "true"),
+	("hardcore", "hardcore", "true"),
+	("no_pvp", "pvp", "false"),
+	("no_structures", "generate-structures", "false"),
+];
 
 pub(super) fn build_command() -> Command {
 	Command::new("create")
@@ -21,15 +49,20 @@ pub(super) fn build_command() -> Command {
 			Arg::new("type")
 				.long("type")
 				.short('t')
-				.help("Server type: paper | fabric")
-				.value_parser(["paper", "fabric"])
+				.help(format!(
+					"Server type: {}",
+					ServerType::names().join(" | ")
+				))
+				.value_parser(ServerType::names())
 				.required(false),
 		)
 		.arg(
 			Arg::new("version")
 				.long("version")
 				.short('v')
-				.help("Minecraft version (e.g. 1.21.11 or 1.21)")
+				.help(
+					"Minecraft (or proxy) version, e.g. 1.21.11, 1.21 or latest",
+				)
 				.required(false),
 		)
 		.arg(
@@ -45,12 +78,60 @@ pub(super) fn build_command() -> Command {
 				.help("Server motd")
 				.default_value("eagle minecraft server"),
 		)
+		.arg(
+			Arg::new("ram_mb")
+				.long("ram-mb")
+				.help(
+					"RAM in MB used by `minecraft` start (stored in manifest)",
+				)
+				.value_parser(clap::value_parser!(u32))
+				.required(false),
+		)
 		.arg(
 			Arg::new("force")
 				.long("force")
 				.help("Overwrite if the folder already exists")
 				.action(clap::ArgAction::SetTrue),
 		)
+		.arg(
+			Arg::new("rcon")
+				.long("rcon")
+				.help("Enable RCON with a random port and password")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("query")
+				.long("query")
+				.help("Enable the Query protocol on the server port (UDP)")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.args(property_args())
+		.arg(
+			Arg::new("world")
+				.long("world")
+				.value_name("PATH")
+				.help(
+					"Import a world folder, .zip or .tar.zst (e.g. a singleplayer save)",
+				)
+				.value_parser(clap::value_parser!(PathBuf)),
+		)
+		.arg(
+			Arg::new("from_mrpack")
+				.long("from-mrpack")
+				.value_name("FILE")
+				.help("Build the server from a Modrinth .mrpack modpack")
+				.value_parser(clap::value_parser!(PathBuf))
+				.conflicts_with_all(["type", "version", "wizard"]),
+		)
+		.arg(
+			Arg::new("wizard")
+				.long("wizard")
+				.short('i')
+				.help(
+					"Walk through every option (default when --name is omitted)",
+				)
+				.action(clap::ArgAction::SetTrue),
+		)
 		.arg(
 			Arg::new("skip_download")
 				.long("skip-download")
@@ -59,59 +140,265 @@ pub(super) fn build_command() -> Command {
 		)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ServerType {
-	Paper,
-	Fabric,
+/// Everything `create` needs, from flags and/or the wizard.
+pub(super) struct Options {
+	pub(super) name: String,
+	pub(super) server_type: ServerType,
+	pub(super) version_input: String,
+	pub(super) port: u16,
+	/// The port came from the default, so `create` may move it off a port
+	/// that is already taken.
+	pub(super) auto_port: bool,
+	pub(super) motd: String,
+	pub(super) ram_mb: Option<u32>,
+	pub(super) rcon: bool,
+	pub(super) query: bool,
+	/// `server.properties` values written over the defaults, in order.
+	pub(super) properties: Vec<(String, String)>,
+	/// `--from-mrpack`: decides type, version and loader.
+	pub(super) pack: Option<mrpack::Pack>,
+	/// `--world`: save to import as `level-name`.
+	pub(super) world: Option<PathBuf>,
+}
+
+impl Options {
+	/// Flags only; prompts just for a missing type or version.
+	fn from_matches(
+		matches: &ArgMatches,
+		name: String,
+		pack: Option<mrpack::Pack>,
+	) -> anyhow::Result<Self> {
+		let (server_type, version_input) = match &pack {
+			Some(pack) => {
+				(pack.target.server_type, pack.target.minecraft.clone())
+			}
+			None => (
+				matches
+					.get_one::<String>("type")
+					.map(|s| s.as_str())
+					.map(ServerType::parse)
+					.transpose()?
+					.unwrap_or_else(select_server_type),
+				matches
+					.get_one::<String>("version")
+					.map(|s| s.to_string())
+					.unwrap_or_else(prompt_version),
+			),
+		};
+
+		Ok(Self {
+			name,
+			server_type,
+			version_input,
+			port: *matches.get_one::<u16>("port").unwrap_or(&22222),
+			auto_port: matches.value_source("port")
+				!= Some(ValueSource::CommandLine),
+			motd: matches
+				.get_one::<String>("motd")
+				.map(|s| s.to_string())
+				.unwrap_or_else(|| "eagle minecraft server".to_string()),
+			ram_mb: matches.get_one::<u32>("ram_mb").copied(),
+			rcon: matches.get_flag("rcon"),
+			query: matches.get_flag("query"),
+			properties: property_overrides(matches)?,
+			pack,
+			world: matches.get_one::<PathBuf>("world").cloned(),
+		}
+		.with_dedicated_properties())
+	}
+
+	/// `--set motd=...` and `--set server-port=...` fill the dedicated
+	/// fields, which the manifest and proxy configs also use.
+	pub(super) fn with_dedicated_properties(mut self) -> Self {
+		for (key, value) in std::mem::take(&mut self.properties) {
+			match key.as_str() {
+				"motd" => self.motd = value,
+				"server-port" => match value.parse() {
+					Ok(port) => {
+						self.port = port;
+						self.auto_port = false;
+					}
+					Err(_) => self.properties.push((key, value)),
+				},
+				_ => self.properties.push((key, value)),
+			}
+		}
+		self
+	}
+
+	/// Proxies have no RCON, world or `server.properties`, so options for
+	/// those are mistakes rather than something to ignore.
+	pub(super) fn check_proxy_options(&self) -> anyhow::Result<()> {
+		let kind = self.server_type.as_str();
+		if !self.server_type.is_proxy() {
+			return Ok(());
+		}
+		if self.rcon {
+			anyhow::bail!(
+				"{kind} has no built-in RCON; --rcon only applies to game servers"
+			);
+		}
+		if self.world.is_some() {
+			anyhow::bail!(
+				"{kind} has no world; --world only applies to game servers"
+			);
+		}
+		if !self.properties.is_empty() {
+			anyhow::bail!(
+				"{kind} has no server.properties; property options only apply to game servers"
+			);
+		}
+		Ok(())
+	}
+
+	/// Replaces `key` if present, else appends it.
+	pub(super) fn set_property(&mut self, key: &str, value: String) {
+		match self.properties.iter_mut().find(|(k, _)| k == key) {
+			Some((_, v)) => *v = value,
+			None => self.properties.push((key.to_string(), value)),
+		}
+	}
+
+	pub(super) fn property(&self, key: &str) -> Option<&str> {
+		self.properties
+			.iter()
+			.find(|(k, _)| k == key)
+			.map(|(_, v)| v.as_str())
+	}
 }
 
-impl ServerType {
-	fn as_str(self) -> &'static str {
-		match self {
-			Self::Paper => "paper",
-			Self::Fabric => "fabric",
+/// `server.properties` values from `--preset`, the dedicated flags, then
+/// `--set`; later sources replace earlier ones.
+pub(super) fn property_overrides(
+	matches: &ArgMatches,
+) -> anyhow::Result<Vec<(String, String)>> {
+	let mut out: Vec<(String, String)> = Vec::new();
+	let mut put = |key: &str, value: String| {
+		out.retain(|(k, _)| k != key);
+		out.push((key.to_string(), value));
+	};
+	if let Some(name) = matches.get_one::<String>("preset") {
+		for (key, value) in world::preset(name)?.properties {
+			put(key, value.to_string());
+		}
+	}
+	if let Some(code) = matches.get_one::<String>("superflat") {
+		put("level-type", "minecraft:flat".to_string());
+		put("generator-settings", world::superflat_settings(code)?);
+	}
+	for (id, key) in PROPERTY_FLAGS {
+		if let Some(value) = matches.get_one::<String>(id) {
+			put(key, value.clone());
+		}
+	}
+	for (id, key, value) in PROPERTY_SWITCHES {
+		if matches.get_flag(id) {
+			put(key, value.to_string());
 		}
 	}
+	if matches.get_flag("whitelist") {
+		put("enforce-whitelist", "true".to_string());
+	}
+	for pair in matches.get_many::<String>("set").into_iter().flatten() {
+		let Some((key, value)) = pair.split_once('=') else {
+			anyhow::bail!("Invalid --set {pair} (expected key=value)");
+		};
+		put(key.trim(), value.to_string());
+	}
+
+	out.into_iter()
+		.map(|(key, value)| {
+			let value = properties::normalize(&key, &value)?;
+			Ok((key, value))
+		})
+		.collect()
 }
 
 pub(super) fn run_create(matches: &ArgMatches) -> anyhow::Result<()> {
+	let pack = matches
+		.get_one::<PathBuf>("from_mrpack")
+		.map(|path| mrpack::Pack::open(path))
+		.transpose()?;
 	let name = matches
 		.get_one::<String>("name")
-		.map(|s| s.to_string())
-		.unwrap_or_else(prompt_server_name);
-
-	validate_server_name(&name)?;
-
-	let server_type = matches
-		.get_one::<String>("type")
-		.map(|s| s.as_str())
-		.map(parse_server_type)
-		.transpose()?
-		.unwrap_or_else(select_server_type);
-
-	let version_input = matches
-		.get_one::<String>("version")
-		.map(|s| s.to_string())
-		.unwrap_or_else(prompt_version);
-
-	let version = match server_type {
-		ServerType::Paper => paper::resolve_paper_version(&version_input)?,
-		ServerType::Fabric => version_input.clone(),
+		.cloned()
+		.or_else(|| pack.as_ref().map(|p| p.folder_name()));
+
+	let options = match name {
+		Some(name) if !matches.get_flag("wizard") => {
+			Options::from_matches(matches, name, pack)?
+		}
+		_ => match wizard::run(matches)? {
+			Some(options) => options,
+			None => {
+				println!("Aborted; nothing was written.");
+				return Ok(());
+			}
+		},
 	};
+	create(
+		options,
+		matches.get_flag("force"),
+		matches.get_flag("skip_download"),
+	)
+}
 
-	let port = *matches.get_one::<u16>("port").unwrap_or(&22222);
-	let motd = matches
-		.get_one::<String>("motd")
-		.map(|s| s.to_string())
-		.unwrap_or_else(|| "eagle minecraft server".to_string());
+fn create(
+	mut options: Options,
+	force: bool,
+	skip_download: bool,
+) -> anyhow::Result<()> {
+	validate_server_name(&options.name)?;
+	let roots = fs::servers_roots()?;
+	let root = fs::servers_root()?;
+	let server_dir = root.join(&options.name);
+	let elsewhere = fs::folders_named(&roots, &options.name)
+		.into_iter()
+		.find(|p| *p != server_dir);
+	if let Some(other) = elsewhere {
+		anyhow::bail!(
+			"A server named {} already exists in {}; names must be unique \
+			 across roots",
+			options.name,
+			other.display()
+		);
+	}
+	let allocations = ports::allocations(&roots)?;
+	options.port = ports::for_new_server(
+		&allocations,
+		&server_dir,
+		options.port,
+		options.auto_port,
+	)?;
 
-	let force = matches.get_flag("force");
-	let skip_download = matches.get_flag("skip_download");
+	let Options {
+		server_type,
+		version_input,
+		port,
+		motd,
+		..
+	} = &options;
+	let (server_type, port) = (*server_type, *port);
+
+	options.check_proxy_options()?;
+
+	// Packs name an exact version; there is no alias to resolve.
+	let version = match &options.pack {
+		Some(pack) => pack.target.minecraft.clone(),
+		None => install::resolve_version(server_type, version_input)?,
+	};
+	let rcon = match options.rcon {
+		true => {
+			let mut claimed: Vec<u16> =
+				allocations.iter().map(|a| a.port).collect();
+			claimed.push(port);
+			Some(rcon::Config::generate(&claimed)?)
+		}
+		false => None,
+	};
 
-	let root = fs::servers_root()?;
 	std::fs::create_dir_all(&root)?;
 
-	let server_dir = root.join(&name);
 	if server_dir.exists() {
 		if !force {
 			anyhow::bail!(
@@ -125,34 +412,138 @@ pub(super) fn run_create(matches: &ArgMatches) -> anyhow::Result<()> {
 	std::fs::create_dir_all(&server_dir)?;
 	let mut guard = fs::DirGuard::new(server_dir.clone());
 
-	write_eula(&server_dir)?;
-	write_server_properties(&server_dir, port, &motd)?;
+	let pack_files = match &options.pack {
+		Some(pack) => Some(pack.apply(&server_dir, !skip_download)?),
+		None => None,
+	};
 
-	if !skip_download {
-		let jar_path = server_dir.join("server.jar");
-		match server_type {
-			ServerType::Paper => {
-				paper::download_paper_server(&version, &jar_path)?
-			}
-			ServerType::Fabric => {
-				fabric::download_fabric_server(&version, &jar_path)?
+	let motd = match server_type {
+		ServerType::Velocity => {
+			write_velocity_config(&server_dir, port, motd, options.query)?;
+			motd.clone()
+		}
+		ServerType::Waterfall => {
+			write_waterfall_config(&server_dir, port, motd, options.query)?;
+			motd.clone()
+		}
+		_ => {
+			write_eula(&server_dir)?;
+			write_server_properties(&server_dir, &options, rcon.as_ref())?;
+			// A modpack may have brought its own.
+			let props = Properties::load(&server_dir)?;
+			if let Some(source) = &options.world {
+				let level = props.get("level-name").unwrap_or("world");
+				let split = world::splits_dimensions(server_type);
+				world::import(source, &server_dir, level, split)?;
 			}
+			props.get("motd").unwrap_or(motd).to_string()
 		}
+	};
+
+	let mut server_manifest = ServerManifest::new(server_type, &version, port);
+	server_manifest.motd = motd.clone();
+	server_manifest.ram_mb = options.ram_mb;
+	server_manifest.java_major =
+		Some(java::required_major(server_type, &version));
+
+	if !skip_download {
+		let pin = Pin {
+			loader: options.pack.as_ref().and_then(|p| p.target.loader.clone()),
+			..Pin::default()
+		};
+		let build = install::resolve_build(server_type, &version, &pin)?;
+		server_manifest.launch =
+			install::install(server_type, &version, &build, &server_dir)?;
+		build.apply(&mut server_manifest);
 	}
 
+	manifest::save(&server_dir, &server_manifest)?;
+
 	println!(
 		"Created server: {} ({}, {})",
 		server_dir.display(),
 		server_type.as_str(),
-		format_version_label(&version_input, &version),
+		format_version_label(version_input, &version),
 	);
+	if let (Some(pack), Some(files)) = (&options.pack, pack_files) {
+		println!(
+			"Modpack: {} {} ({files} files)",
+			pack.index.name, pack.index.version_id
+		);
+	}
 	println!("Port: {port}");
 	println!("Motd: {motd}");
+	if options.query {
+		println!("Query: enabled on UDP port {port}");
+	}
+	if let Some(rcon) = &rcon {
+		println!("RCON: port {} (password in server.properties)", rcon.port);
+	}
 
 	guard.commit();
 	Ok(())
 }
 
+fn property_args() -> Vec<Arg> {
+	let switch = |id: &'static str, long: &'static str, help: &'static str| {
+		Arg::new(id)
+			.long(long)
+			.help(help)
+			.action(clap::ArgAction::SetTrue)
+	};
+	vec![
+		Arg::new("gamemode")
+			.long("gamemode")
+			.help("Default game mode")
+			.value_parser(properties::GAMEMODES.to_vec()),
+		Arg::new("difficulty")
+			.long("difficulty")
+			.help("World difficulty")
+			.value_parser(properties::DIFFICULTIES.to_vec()),
+		Arg::new("max_players")
+			.long("max-players")
+			.help("Player limit"),
+		Arg::new("seed").long("seed").help("World seed"),
+		Arg::new("level_type")
+			.long("level-type")
+			.help("World type, e.g. minecraft:flat or minecraft:amplified"),
+		Arg::new("view_distance")
+			.long("view-distance")
+			.help("View distance in chunks (3-32)"),
+		Arg::new("simulation_distance")
+			.long("simulation-distance")
+			.help("Simulation distance in chunks (3-32)"),
+		switch(
+			"offline",
+			"offline",
+			"Set online-mode=false (no Mojang auth)",
+		),
+		switch("whitelist", "whitelist", "Enable and enforce the whitelist"),
+		switch("hardcore", "hardcore", "Hardcore mode"),
+		switch("no_pvp", "no-pvp", "Disable PvP"),
+		Arg::new("preset")
+			.long("preset")
+			.help("World generation preset (flags and --set override it)")
+			.value_parser(world::preset_names()),
+		Arg::new("superflat")
+			.long("superflat")
+			.value_name("LAYERS")
+			.help(
+				"Superflat world, bottom layer first: bedrock,2*dirt,grass_block;plains",
+			),
+		switch(
+			"no_structures",
+			"no-structures",
+			"Do not generate villages, temples and other structures",
+		),
+		Arg::new("set")
+			.long("set")
+			.value_name("KEY=VALUE")
+			.help("Any other server.properties value (repeatable)")
+			.action(clap::ArgAction::Append),
+	]
+}
+
 fn format_version_label(input: &str, resolved: &str) -> String {
 	if input == resolved {
 		input.to_string()
@@ -161,22 +552,15 @@ fn format_version_label(input: &str, resolved: &str) -> String {
 	}
 }
 
-fn prompt_server_name() -> String {
-	Input::<String>::new()
-		.with_prompt("Server name")
-		.interact_text()
-		.unwrap_or_else(|_| "mc-server".to_string())
-}
-
-fn prompt_version() -> String {
+pub(super) fn prompt_version() -> String {
 	Input::<String>::new()
-		.with_prompt("Minecraft version (e.g. 1.21.11 or 1.21)")
+		.with_prompt("Version (e.g. 1.21.11, 1.21 or latest)")
 		.interact_text()
 		.unwrap_or_else(|_| "1.21.11".to_string())
 }
 
-fn select_server_type() -> ServerType {
-	let options = ["paper", "fabric"];
+pub(super) fn select_server_type() -> ServerType {
+	let options = ServerType::names();
 	let selection = Select::new()
 		.with_prompt("Server type")
 		.items(&options)
@@ -184,22 +568,10 @@ fn select_server_type() -> ServerType {
 		.interact()
 		.unwrap_or(0);
 
-	if options[selection] == "fabric" {
-		ServerType::Fabric
-	} else {
-		ServerType::Paper
-	}
-}
-
-fn parse_server_type(s: &str) -> anyhow::Result<ServerType> {
-	match s.to_lowercase().as_str() {
-		"paper" => Ok(ServerType::Paper),
-		"fabric" => Ok(ServerType::Fabric),
-		_ => anyhow::bail!("Invalid type: {s} (expected: paper | fabric)"),
-	}
+	ServerType::ALL[selection]
 }
 
-fn validate_server_name(name: &str) -> anyhow::Result<()> {
+pub(super) fn validate_server_name(name: &str) -> anyhow::Result<()> {
 	if name.trim().is_empty() {
 		anyhow::bail!("Name must not be empty");
 	}
@@ -215,6 +587,15 @@ fn validate_server_name(name: &str) -> anyhow::Result<()> {
 		anyhow::bail!("Invalid name: '..' not allowed");
 	}
 
+	// Windows drops trailing dots and spaces, so "." and ". " both name the
+	// servers root itself.
+	if name
+		.trim_matches(|c: char| c == '.' || c.is_whitespace())
+		.is_empty()
+	{
+		anyhow::bail!("Invalid name: must contain more than dots");
+	}
+
 	Ok(())
 }
 
@@ -227,44 +608,220 @@ fn write_eula(server_dir: &Path) -> anyhow::Result<()> {
 	Ok(())
 }
 
+/// Keys `create` always decides itself.
+const MANAGED_PROPERTIES: [&str; 6] = [
+	"server-port",
+	"enable-rcon",
+	"rcon.port",
+	"rcon.password",
+	"enable-query",
+	"query.port",
+];
+
 fn write_server_properties(
+	server_dir: &Path,
+	options: &Options,
+	rcon: Option<&rcon::Config>,
+) -> anyhow::Result<()> {
+	let port = options.port.to_string();
+	let motd = options.motd.as_str();
+	let mut props = Properties::default();
+	let mut set_all = |entries: &[(&str, &str)]| {
+		for (key, value) in entries {
+			props.set(key, value);
+		}
+	};
+	set_all(&[
+		("enable-jmx-monitoring", "false"),
+		("server-port", &port),
+		("server-ip", ""),
+		("motd", motd),
+		("enable-command-block", "false"),
+		("online-mode", "true"),
+		("level-name", "world"),
+		("gamemode", "survival"),
+		("difficulty", "easy"),
+		("max-players", "20"),
+		("view-distance", "10"),
+		("simulation-distance", "10"),
+		("spawn-protection", "16"),
+		("sync-chunk-writes", "true"),
+	]);
+	match rcon {
+		Some(rcon) => set_all(&[
+			("enable-rcon", "true"),
+			("rcon.port", &rcon.port.to_string()),
+			("rcon.password", &rcon.password),
+			("broadcast-rcon-to-ops", "true"),
+		]),
+		None => set_all(&[("enable-rcon", "false")]),
+	}
+	match options.query {
+		true => set_all(&[("enable-query", "true"), ("query.port", &port)]),
+		false => set_all(&[("enable-query", "false")]),
+	}
+	set_all(&[
+		("enforce-secure-profile", "true"),
+		("white-list", "false"),
+		("pvp", "true"),
+		("allow-flight", "false"),
+		("generate-structures", "true"),
+		("level-seed", ""),
+		("allow-nether", "true"),
+		("spawn-animals", "true"),
+		("spawn-monsters", "true"),
+		("spawn-npcs", "true"),
+		("use-native-transport", "true"),
+	]);
+	// A modpack's own server.properties (already copied from its overrides)
+	// beats the defaults, but not the port, RCON and Query settings.
+	for (key, value) in Properties::load(server_dir)?.entries() {
+		if !MANAGED_PROPERTIES.contains(&key) {
+			props.set(key, value);
+		}
+	}
+	for (key, value) in &options.properties {
+		props.set(key, value);
+	}
+	props.save(server_dir)
+}
+
+/// Minimal `velocity.toml`; Velocity adds every missing key on first start.
+fn write_velocity_config(
 	server_dir: &Path,
 	port: u16,
 	motd: &str,
+	query: bool,
 ) -> anyhow::Result<()> {
-	let mut lines = Vec::new();
-	lines.push("enable-jmx-monitoring=false".to_string());
-	lines.push(format!("server-port={port}"));
-	lines.push("server-ip=".to_string());
-	lines.push(format!("motd={motd}"));
-	lines.push("enable-command-block=false".to_string());
-	lines.push("online-mode=true".to_string());
-	lines.push("level-name=world".to_string());
-	lines.push("gamemode=survival".to_string());
-	lines.push("difficulty=easy".to_string());
-	lines.push("max-players=20".to_string());
-	lines.push("view-distance=10".to_string());
-	lines.push("simulation-distance=10".to_string());
-	lines.push("spawn-protection=16".to_string());
-	lines.push("sync-chunk-writes=true".to_string());
-	lines.push("enable-rcon=false".to_string());
-	lines.push("enable-query=false".to_string());
-	lines.push("enforce-secure-profile=true".to_string());
-	lines.push("white-list=false".to_string());
-	lines.push("pvp=true".to_string());
-	lines.push("allow-flight=false".to_string());
-	lines.push("generate-structures=true".to_string());
-	lines.push("level-seed=".to_string());
-	lines.push("allow-nether=true".to_string());
-	lines.push("spawn-animals=true".to_string());
-	lines.push("spawn-monsters=true".to_string());
-	lines.push("spawn-npcs=true".to_string());
-	lines.push("use-native-transport=true".to_string());
-
-	std::fs::write(
-		server_dir.join("server.properties"),
-		format!("{}\n", lines.join("\n")),
-	)?;
+	let motd = motd.replace('\\', "\\\\").replace('"', "\\\"");
+	let content = format!(
+		r#"config-version = "2.7"
+bind = "0.0.0.0:{port}"
+motd = "{motd}"
+show-max-players = 500
+online-mode = true
+force-key-authentication = true
+player-info-forwarding-mode = "modern"
+forwarding-secret-file = "forwarding.secret"
+
+[servers]
+lobby = "127.0.0.1:25565"
+try = ["lobby"]
+
+[forced-hosts]
+
+[query]
+enabled = {query}
+port = {port}
+"#
+	);
+
+	std::fs::write(server_dir.join("velocity.toml"), content)?;
+	Ok(())
+}
+
+/// Minimal Waterfall `config.yml`; Waterfall adds every missing key on first
+/// start.
+fn write_waterfall_config(
+	server_dir: &Path,
+	port: u16,
+	motd: &str,
+	query: bool,
+) -> anyhow::Result<()> {
+	let motd = motd.replace('\'', "''");
+	let content = format!(
+		r#"online_mode: true
+ip_forward: true
+listeners:
+- host: 0.0.0.0:{port}
+  query_port: {port}
+  motd: '{motd}'
+  max_players: 500
+  priorities:
+  - lobby
+  force_default_server: false
+  forced_hosts: {{}}
+  tab_list: GLOBAL_PING
+  query_enabled: {query}
+  ping_passthrough: false
+servers:
+  lobby:
+    motd: '{motd}'
+    address: localhost:25565
+    restricted: false
+"#
+	);
 
+	std::fs::write(server_dir.join("config.yml"), content)?;
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn options(args: &[&str]) -> anyhow::Result<Options> {
+		let mut argv = vec!["create", "-n", "s", "-t", "paper", "-v", "1.21.4"];
+		argv.extend_from_slice(args);
+		let matches = build_command().try_get_matches_from(argv)?;
+		Options::from_matches(&matches, "s".to_string(), None)
+	}
+
+	#[test]
+	fn set_replaces_flags_and_presets() {
+		let o = options(&[
+			"--preset",
+			"amplified",
+			"--level-type",
+			"minecraft:flat",
+			"--gamemode",
+			"creative",
+			"--set",
+			"gamemode=adventure",
+		])
+		.unwrap();
+		assert_eq!(o.property("level-type"), Some("minecraft:flat"));
+		assert_eq!(o.property("gamemode"), Some("adventure"));
+		assert_eq!(o.properties.len(), 2);
+
+		let o = options(&["--whitelist"]).unwrap();
+		assert_eq!(o.property("white-list"), Some("true"));
+		assert_eq!(o.property("enforce-whitelist"), Some("true"));
+
+		assert!(options(&["--set", "gamemode"]).is_err());
+		assert!(options(&["--set", "max-players=lots"]).is_err());
+	}
+
+	#[test]
+	fn motd_and_port_go_to_their_own_fields() {
+		let o = options(&["--motd", "flag", "--set", "motd=set"]).unwrap();
+		assert_eq!(o.motd, "set");
+		assert!(o.property("motd").is_none());
+
+		let o = options(&[]).unwrap();
+		assert!(o.auto_port);
+		let o = options(&["--port", "25565"]).unwrap();
+		assert!(!o.auto_port);
+		let o = options(&["--set", "server-port=25570"]).unwrap();
+		assert_eq!((o.port, o.auto_port), (25570, false));
+		assert!(o.property("server-port").is_none());
+	}
+
+	#[test]
+	fn proxies_reject_game_server_options() {
+		let mut o = options(&["--gamemode", "creative"]).unwrap();
+		assert!(o.check_proxy_options().is_ok());
+		o.server_type = ServerType::Velocity;
+		assert!(o.check_proxy_options().is_err());
+		o.properties.clear();
+		assert!(o.check_proxy_options().is_ok());
+	}
+
+	#[test]
+	fn rejects_names_made_of_dots() {
+		assert!(validate_server_name("my.server").is_ok());
+		assert!(validate_server_name(".").is_err());
+		assert!(validate_server_name(". ").is_err());
+		assert!(validate_server_name("..").is_err());
+	}
+}
@@ -25,24 +25,51 @@ struct InstallerPart {
 	stable: Option<bool>,
 }
 
-pub(super) fn download_fabric_server(
-	version: &str,
-	jar_path: &Path,
-) -> anyhow::Result<()> {
-	println!("Downloading Fabric {version}...");
+/// Loader/installer pair a Fabric server jar was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FabricBuild {
+	pub(super) loader: String,
+	pub(super) installer: String,
+}
 
+/// Picks the loader/installer pair for `version`: the requested loader, or
+/// the newest stable one when `loader` is `None`.
+pub(super) fn resolve_fabric_build(
+	version: &str,
+	loader: Option<&str>,
+) -> anyhow::Result<FabricBuild> {
 	let url = format!("https://meta.fabricmc.net/v2/versions/loader/{version}");
 	let combos = net::get_json::<Vec<LoaderCombo>>(&url)?;
 	if combos.is_empty() {
 		anyhow::bail!("No Fabric loader versions found for {version}");
 	}
 
-	let best = pick_best_combo(&combos)
-		.ok_or_else(|| anyhow::anyhow!("No Fabric loader versions found"))?;
+	let best = match loader {
+		Some(loader) => combos
+			.iter()
+			.find(|c| c.loader.version == loader)
+			.ok_or_else(|| {
+				anyhow::anyhow!("Unknown Fabric loader {loader} for {version}")
+			})?,
+		None => pick_best_combo(&combos).ok_or_else(|| {
+			anyhow::anyhow!("No Fabric loader versions found")
+		})?,
+	};
+
+	Ok(FabricBuild {
+		loader: best.loader.version.clone(),
+		installer: best.installer.version.clone(),
+	})
+}
 
-	let loader = &best.loader.version;
-	let installer = &best.installer.version;
+pub(super) fn download_fabric_build(
+	version: &str,
+	build: &FabricBuild,
+	jar_path: &Path,
+) -> anyhow::Result<()> {
+	println!("Downloading Fabric {version}...");
 
+	let FabricBuild { loader, installer } = build;
 	let url = format!(
 		"https://meta.fabricmc.net/v2/versions/loader/{version}/{loader}/{installer}/server/jar"
 	);
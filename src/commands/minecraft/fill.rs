@@ -0,0 +1,282 @@
+//! Client for PaperMC's Fill API (`fill.papermc.io/v3`), shared by every
+//! project it hosts.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::manifest::ServerType;
+use crate::net;
+
+const FILL_API: &str = "https://fill.papermc.io/v3/projects";
+
+/// A project hosted on Fill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FillProject {
+	Paper,
+	Folia,
+	Velocity,
+	Waterfall,
+}
+
+impl FillProject {
+	pub(super) fn for_type(server_type: ServerType) -> Option<Self> {
+		match server_type {
+			ServerType::Paper => Some(Self::Paper),
+			ServerType::Folia => Some(Self::Folia),
+			ServerType::Velocity => Some(Self::Velocity),
+			ServerType::Waterfall => Some(Self::Waterfall),
+			_ => None,
+		}
+	}
+
+	fn id(self) -> &'static str {
+		match self {
+			Self::Paper => "paper",
+			Self::Folia => "folia",
+			Self::Velocity => "velocity",
+			Self::Waterfall => "waterfall",
+		}
+	}
+
+	fn display_name(self) -> &'static str {
+		match self {
+			Self::Paper => "Paper",
+			Self::Folia => "Folia",
+			Self::Velocity => "Velocity",
+			Self::Waterfall => "Waterfall",
+		}
+	}
+}
+
+/// Minimal shape of `GET https://fill.papermc.io/v3/projects/{project}`.
+#[derive(Debug, Clone, Deserialize)]
+struct FillProjectIndex {
+	versions: HashMap<String, Vec<String>>,
+}
+
+/// Resolves `latest` and family keys (`1.21`) to a concrete project version;
+/// anything else is passed through unchanged.
+pub(super) fn resolve_version(
+	project: FillProject,
+	version: &str,
+) -> anyhow::Result<String> {
+	let version = version.trim();
+	let is_latest = version == "latest";
+	if !is_latest && !looks_like_family_key(version) {
+		return Ok(version.to_string());
+	}
+
+	let name = project.display_name();
+	let index = net::get_json::<FillProjectIndex>(&format!(
+		"{FILL_API}/{}",
+		project.id()
+	))?;
+
+	let family = if is_latest {
+		newest_family(&index.versions)
+			.ok_or_else(|| anyhow::anyhow!("No {name} versions published"))?
+	} else {
+		version
+	};
+
+	let versions = index.versions.get(family).ok_or_else(|| {
+		anyhow::anyhow!("Unknown {name} version family: {family}")
+	})?;
+
+	let best = pick_best_version_for_family(versions).ok_or_else(|| {
+		anyhow::anyhow!("No versions found for {name} family: {family}")
+	})?;
+
+	Ok(best.to_string())
+}
+
+fn newest_family(families: &HashMap<String, Vec<String>>) -> Option<&str> {
+	families
+		.keys()
+		.max_by_key(|k| version_key(k))
+		.map(|k| k.as_str())
+}
+
+/// Numeric sort key for dotted versions (`1.21.4` -> `[1, 21, 4]`).
+pub(super) fn version_key(s: &str) -> Vec<u64> {
+	s.split(['.', '-'])
+		.map_while(|p| p.parse::<u64>().ok())
+		.collect()
+}
+
+pub(super) fn looks_like_family_key(s: &str) -> bool {
+	let s = s.trim();
+	if s.is_empty() || s.contains('-') {
+		return false;
+	}
+
+	let parts: Vec<&str> = s.split('.').collect();
+	if parts.len() != 2 {
+		return false;
+	}
+
+	parts
+		.iter()
+		.all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn pick_best_version_for_family(versions: &[String]) -> Option<&str> {
+	versions
+		.iter()
+		.find(|v| !v.contains('-'))
+		.or_else(|| versions.first())
+		.map(|s| s.as_str())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct FillBuild {
+	pub(super) id: u64,
+	channel: String,
+	downloads: HashMap<String, FillDownload>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FillDownload {
+	name: String,
+	checksums: FillChecksums,
+	url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FillChecksums {
+	sha256: String,
+}
+
+/// Looks up a specific build, or the newest stable one when `build` is
+/// `None`.
+pub(super) fn resolve_build(
+	project: FillProject,
+	version: &str,
+	build: Option<u64>,
+) -> anyhow::Result<FillBuild> {
+	let name = project.display_name();
+	let url = format!("{FILL_API}/{}/versions/{version}/builds", project.id());
+	let builds = net::get_json::<Vec<FillBuild>>(&url)?;
+	if builds.is_empty() {
+		anyhow::bail!("No {name} builds found for {version}");
+	}
+
+	let found = match build {
+		Some(id) => builds.iter().find(|b| b.id == id).ok_or_else(|| {
+			anyhow::anyhow!("{name} {version} has no build {id}")
+		})?,
+		None => pick_best_build(&builds).ok_or_else(|| {
+			anyhow::anyhow!("No {name} builds found for {version}")
+		})?,
+	};
+
+	Ok(found.clone())
+}
+
+pub(super) fn download_build(
+	project: FillProject,
+	version: &str,
+	build: &FillBuild,
+	jar_path: &Path,
+) -> anyhow::Result<()> {
+	let name = project.display_name();
+	println!("Downloading {name} {version}...");
+
+	let download = build
+		.downloads
+		.get("server:default")
+		.ok_or_else(|| anyhow::anyhow!("Missing {name} server download"))?;
+
+	println!("Build {}: {}", build.id, download.name);
+
+	net::download_verified(
+		&download.url,
+		jar_path,
+		Some(net::Checksum::Sha256(&download.checksums.sha256)),
+	)?;
+	Ok(())
+}
+
+fn pick_best_build(builds: &[FillBuild]) -> Option<&FillBuild> {
+	builds
+		.iter()
+		.filter(|b| b.channel == "STABLE")
+		.max_by_key(|b| b.id)
+		.or_else(|| builds.iter().max_by_key(|b| b.id))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn family_key_detection() {
+		assert!(looks_like_family_key("1.21"));
+		assert!(looks_like_family_key(" 1.21 "));
+		assert!(!looks_like_family_key("1.21.11"));
+		assert!(!looks_like_family_key("1.21-rc1"));
+		assert!(!looks_like_family_key("paper"));
+		assert!(!looks_like_family_key(""));
+	}
+
+	#[test]
+	fn pick_best_version_prefers_non_prerelease() {
+		let versions = vec![
+			"1.21.11-rc3".to_string(),
+			"1.21.11".to_string(),
+			"1.21.10".to_string(),
+		];
+		assert_eq!(pick_best_version_for_family(&versions), Some("1.21.11"));
+	}
+
+	#[test]
+	fn pick_best_version_falls_back_to_first() {
+		let versions = vec!["1.21.11-rc3".to_string()];
+		assert_eq!(
+			pick_best_version_for_family(&versions),
+			Some("1.21.11-rc3")
+		);
+	}
+
+	#[test]
+	fn version_key_orders_numerically() {
+		assert!(version_key("1.21") > version_key("1.9"));
+		assert!(version_key("1.21.10") > version_key("1.21.4"));
+		assert_eq!(version_key("3.4.0-SNAPSHOT"), vec![3, 4, 0]);
+	}
+
+	#[test]
+	fn newest_family_picks_highest_key() {
+		let families = HashMap::from([
+			("1.9".to_string(), vec![]),
+			("1.21".to_string(), vec![]),
+			("1.20".to_string(), vec![]),
+		]);
+		assert_eq!(newest_family(&families), Some("1.21"));
+	}
+
+	#[test]
+	fn pick_best_build_prefers_stable_highest_id() {
+		let builds = vec![
+			FillBuild {
+				id: 1,
+				channel: "STABLE".to_string(),
+				downloads: HashMap::new(),
+			},
+			FillBuild {
+				id: 10,
+				channel: "BETA".to_string(),
+				downloads: HashMap::new(),
+			},
+			FillBuild {
+				id: 5,
+				channel: "STABLE".to_string(),
+				downloads: HashMap::new(),
+			},
+		];
+
+		assert_eq!(pick_best_build(&builds).map(|b| b.id), Some(5));
+	}
+}
@@ -0,0 +1,259 @@
+//! Forge and NeoForge share an installer lineage: download the installer jar,
+//! run it with `--installServer`, then work out how the result launches.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::fill;
+use super::install;
+use super::manifest::Launch;
+use crate::net;
+
+const FORGE_PROMOTIONS: &str = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+const FORGE_MAVEN: &str =
+	"https://maven.minecraftforge.net/net/minecraftforge/forge";
+const NEOFORGE_VERSIONS: &str = "https://maven.neoforged.net/api/maven/versions/releases/net/neoforged/neoforge";
+const NEOFORGE_MAVEN: &str =
+	"https://maven.neoforged.net/releases/net/neoforged/neoforge";
+const INSTALLER_JAR: &str = "installer.jar";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Flavor {
+	Forge,
+	NeoForge,
+}
+
+impl Flavor {
+	fn display_name(self) -> &'static str {
+		match self {
+			Self::Forge => "Forge",
+			Self::NeoForge => "NeoForge",
+		}
+	}
+}
+
+/// Minimal shape of Forge's `promotions_slim.json`.
+#[derive(Debug, Clone, Deserialize)]
+struct Promotions {
+	promos: HashMap<String, String>,
+}
+
+/// Minimal shape of NeoForge's maven versions API.
+#[derive(Debug, Clone, Deserialize)]
+struct MavenVersions {
+	versions: Vec<String>,
+}
+
+/// Resolves the loader version for a Minecraft version: the pinned one, or
+/// Forge's recommended (else latest) promotion / NeoForge's newest release.
+pub(super) fn resolve_loader(
+	flavor: Flavor,
+	version: &str,
+	pin: Option<&str>,
+) -> anyhow::Result<String> {
+	if let Some(pin) = pin {
+		return Ok(pin.to_string());
+	}
+
+	let name = flavor.display_name();
+	let found = match flavor {
+		Flavor::Forge => {
+			let promotions = net::get_json::<Promotions>(FORGE_PROMOTIONS)?;
+			pick_promotion(&promotions.promos, version).map(str::to_string)
+		}
+		Flavor::NeoForge => {
+			let maven = net::get_json::<MavenVersions>(NEOFORGE_VERSIONS)?;
+			pick_neoforge(&maven.versions, version).map(str::to_string)
+		}
+	};
+
+	found.ok_or_else(|| anyhow::anyhow!("No {name} release for {version}"))
+}
+
+fn pick_promotion<'a>(
+	promos: &'a HashMap<String, String>,
+	version: &str,
+) -> Option<&'a str> {
+	promos
+		.get(&format!("{version}-recommended"))
+		.or_else(|| promos.get(&format!("{version}-latest")))
+		.map(|s| s.as_str())
+}
+
+/// NeoForge versions encode the Minecraft version: `1.21.1` -> `21.1.x`,
+/// `1.21` -> `21.0.x`.
+fn neoforge_prefix(version: &str) -> Option<String> {
+	let rest = version.strip_prefix("1.")?;
+	let mut parts = rest.split('.');
+	let major = parts.next().filter(|p| !p.is_empty())?;
+	let minor = parts.next().unwrap_or("0");
+	Some(format!("{major}.{minor}."))
+}
+
+fn pick_neoforge<'a>(versions: &'a [String], version: &str) -> Option<&'a str> {
+	let prefix = neoforge_prefix(version)?;
+	let matching = versions.iter().filter(|v| v.starts_with(&prefix));
+
+	matching
+		.clone()
+		.filter(|v| !v.contains('-'))
+		.max_by_key(|v| fill::version_key(v))
+		.or_else(|| matching.max_by_key(|v| fill::version_key(v)))
+		.map(|s| s.as_str())
+}
+
+/// Downloads and runs the installer inside `server_dir`.
+pub(super) fn install_server(
+	java: &Path,
+	flavor: Flavor,
+	version: &str,
+	loader: &str,
+	server_dir: &Path,
+) -> anyhow::Result<Launch> {
+	let name = flavor.display_name();
+	println!("Installing {name} {version} (loader {loader})...");
+
+	let url = match flavor {
+		Flavor::Forge => format!(
+			"{FORGE_MAVEN}/{version}-{loader}/forge-{version}-{loader}-installer.jar"
+		),
+		Flavor::NeoForge => {
+			format!("{NEOFORGE_MAVEN}/{loader}/neoforge-{loader}-installer.jar")
+		}
+	};
+
+	install::download_from_maven(&url, &server_dir.join(INSTALLER_JAR))?;
+	install::run_java_installer(
+		java,
+		server_dir,
+		INSTALLER_JAR,
+		&["--installServer"],
+	)?;
+
+	detect_launch(flavor, version, loader, server_dir).ok_or_else(|| {
+		anyhow::anyhow!("{name} installer did not produce a launch target")
+	})
+}
+
+/// Where an install keeps its versioned library folders.
+fn library_root(flavor: Flavor) -> &'static str {
+	match flavor {
+		Flavor::Forge => "libraries/net/minecraftforge/forge",
+		Flavor::NeoForge => "libraries/net/neoforged/neoforge",
+	}
+}
+
+/// Minecraft and loader versions of the installs in `server_dir`, read from
+/// the library folder names (`1.20.1-47.3.0`, `21.1.77`); newest last.
+pub(super) fn detect_installs(
+	flavor: Flavor,
+	server_dir: &Path,
+) -> Vec<(String, String)> {
+	let Ok(entries) = std::fs::read_dir(server_dir.join(library_root(flavor)))
+	else {
+		return Vec::new();
+	};
+	let mut found: Vec<(String, String)> = entries
+		.filter_map(|e| e.ok()?.file_name().into_string().ok())
+		.filter_map(|name| parse_install_dir(flavor, &name))
+		.collect();
+	found.sort_by_key(|(_, loader)| fill::version_key(loader));
+	found
+}
+
+fn parse_install_dir(flavor: Flavor, name: &str) -> Option<(String, String)> {
+	match flavor {
+		Flavor::Forge => {
+			let (version, loader) = name.split_once('-')?;
+			Some((version.to_string(), loader.to_string()))
+		}
+		Flavor::NeoForge => {
+			let mut parts = name.split('.');
+			let major = parts.next().filter(|p| p.parse::<u32>().is_ok())?;
+			let version = match parts.next()? {
+				"0" => format!("1.{major}"),
+				minor => format!("1.{major}.{minor}"),
+			};
+			Some((version, name.to_string()))
+		}
+	}
+}
+
+/// 1.17+ installs write `unix_args.txt`/`win_args.txt` under `libraries/`;
+/// older (and newer shim-based) Forge installs leave a runnable jar.
+pub(super) fn detect_launch(
+	flavor: Flavor,
+	version: &str,
+	loader: &str,
+	server_dir: &Path,
+) -> Option<Launch> {
+	let lib_dir = match flavor {
+		Flavor::Forge => format!("{}/{version}-{loader}", library_root(flavor)),
+		Flavor::NeoForge => format!("{}/{loader}", library_root(flavor)),
+	};
+
+	if server_dir.join(&lib_dir).join("unix_args.txt").exists() {
+		return Some(Launch::ArgsFile {
+			unix: format!("{lib_dir}/unix_args.txt"),
+			windows: format!("{lib_dir}/win_args.txt"),
+		});
+	}
+
+	let stem = match flavor {
+		Flavor::Forge => format!("forge-{version}-{loader}"),
+		Flavor::NeoForge => format!("neoforge-{loader}"),
+	};
+	["-shim.jar", ".jar", "-universal.jar"]
+		.into_iter()
+		.map(|suffix| format!("{stem}{suffix}"))
+		.find(|jar| server_dir.join(jar).exists())
+		.map(|jar| Launch::Jar { jar })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn promotion_prefers_recommended() {
+		let promos = HashMap::from([
+			("1.20.1-latest".to_string(), "47.3.12".to_string()),
+			("1.20.1-recommended".to_string(), "47.3.0".to_string()),
+			("1.21.4-latest".to_string(), "54.0.1".to_string()),
+		]);
+		assert_eq!(pick_promotion(&promos, "1.20.1"), Some("47.3.0"));
+		assert_eq!(pick_promotion(&promos, "1.21.4"), Some("54.0.1"));
+		assert_eq!(pick_promotion(&promos, "1.8"), None);
+	}
+
+	#[test]
+	fn neoforge_prefix_from_minecraft_version() {
+		assert_eq!(neoforge_prefix("1.21.1").as_deref(), Some("21.1."));
+		assert_eq!(neoforge_prefix("1.21").as_deref(), Some("21.0."));
+		assert_eq!(neoforge_prefix("24w14a"), None);
+	}
+
+	#[test]
+	fn reads_versions_from_library_folders() {
+		let forge = parse_install_dir(Flavor::Forge, "1.20.1-47.3.0");
+		assert_eq!(forge, Some(("1.20.1".into(), "47.3.0".into())));
+		let neo = parse_install_dir(Flavor::NeoForge, "21.1.77");
+		assert_eq!(neo, Some(("1.21.1".into(), "21.1.77".into())));
+		let neo = parse_install_dir(Flavor::NeoForge, "21.0.167");
+		assert_eq!(neo, Some(("1.21".into(), "21.0.167".into())));
+		assert_eq!(parse_install_dir(Flavor::Forge, "junk"), None);
+	}
+
+	#[test]
+	fn neoforge_picks_newest_release() {
+		let versions: Vec<String> =
+			["21.1.9", "21.1.77", "21.1.80-beta", "21.0.167", "20.4.237"]
+				.map(String::from)
+				.to_vec();
+		assert_eq!(pick_neoforge(&versions, "1.21.1"), Some("21.1.77"));
+		assert_eq!(pick_neoforge(&versions, "1.21"), Some("21.0.167"));
+		assert_eq!(pick_neoforge(&versions, "1.19.2"), None);
+	}
+}
@@ -1,40 +1,305 @@
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use dialoguer::Select;
+use serde::Deserialize;
+
+use super::manifest::{self, ServerManifest};
+use super::properties::Properties;
+use super::quilt;
+
+/// Folder under the servers root holding world backups.
+pub(super) const BACKUPS_DIR: &str = ".backups";
+/// Path list (`;` on Windows, `:` elsewhere) overriding the config file.
+pub(super) const ROOT_ENV: &str = "EAGLE_MC_ROOT";
+const CONFIG_FILE: &str = "minecraft.json";
+
+/// `--root` values, set once by `minecraft` before dispatching.
+static ROOT_FLAGS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+/// Folders whose manifest warning was already printed.
+static WARNED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Which setting the servers roots came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RootsSource {
+	Flag,
+	Env,
+	Config,
+	Default,
+}
+
+/// Shape of `<eagle config dir>/minecraft.json`.
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+	#[serde(default)]
+	roots: Vec<PathBuf>,
+}
+
+/// A server folder found under the servers root.
+#[derive(Debug, Clone)]
+pub(super) struct Server {
+	pub(super) path: PathBuf,
+	pub(super) manifest: Option<ServerManifest>,
+}
+
+impl Server {
+	pub(super) fn name(&self) -> &str {
+		self.path
+			.file_name()
+			.and_then(|s| s.to_str())
+			.unwrap_or("server")
+	}
+
+	/// The servers root this folder lives in.
+	pub(super) fn root(&self) -> &Path {
+		self.path.parent().unwrap_or(Path::new("."))
+	}
+
+	/// Picker label, e.g. `survival (paper 1.21.4 #130)`.
+	pub(super) fn label(&self) -> String {
+		match &self.manifest {
+			Some(m) => format!("{} ({})", self.name(), m.describe()),
+			None => format!("{} (unknown, run `minecraft adopt`)", self.name()),
+		}
+	}
+}
 
 pub(super) fn documents_dir() -> Option<PathBuf> {
 	directories::UserDirs::new()
 		.and_then(|u| u.document_dir().map(|p| p.to_path_buf()))
 }
 
-pub(super) fn servers_root() -> anyhow::Result<PathBuf> {
+pub(super) fn set_root_flags(roots: Vec<PathBuf>) {
+	let _ = ROOT_FLAGS.set(roots);
+}
+
+/// Every servers root, the primary one (where `create` puts new servers)
+/// first.
+pub(super) fn servers_roots() -> anyhow::Result<Vec<PathBuf>> {
+	Ok(servers_roots_with_source()?.0)
+}
+
+/// The servers roots and which setting they came from.
+pub(super) fn servers_roots_with_source()
+-> anyhow::Result<(Vec<PathBuf>, RootsSource)> {
+	let flags = ROOT_FLAGS.get().map(Vec::as_slice).unwrap_or_default();
+	let env = std::env::var_os(ROOT_ENV);
+	let config = || Ok(load_config(&config_path()?)?.roots);
+	if let Some(found) = choose_roots(flags, env.as_deref(), config)? {
+		return Ok(found);
+	}
+
 	let root = documents_dir()
 		.ok_or_else(|| anyhow::anyhow!("Could not resolve Documents dir"))?
 		.join("mc-servers");
+	Ok((vec![root], RootsSource::Default))
+}
 
-	Ok(root)
+/// `--root`, else `EAGLE_MC_ROOT`, else the config file's `roots`; `None`
+/// falls back to `Documents/mc-servers`. The config is only read when
+/// neither override is set.
+fn choose_roots(
+	flags: &[PathBuf],
+	env: Option<&OsStr>,
+	config: impl FnOnce() -> anyhow::Result<Vec<PathBuf>>,
+) -> anyhow::Result<Option<(Vec<PathBuf>, RootsSource)>> {
+	if !flags.is_empty() {
+		return Ok(Some((flags.to_vec(), RootsSource::Flag)));
+	}
+	let from_env: Vec<PathBuf> = env
+		.into_iter()
+		.flat_map(std::env::split_paths)
+		.filter(|p| !p.as_os_str().is_empty())
+		.collect();
+	if !from_env.is_empty() {
+		return Ok(Some((from_env, RootsSource::Env)));
+	}
+	let from_config = config()?;
+	if !from_config.is_empty() {
+		return Ok(Some((from_config, RootsSource::Config)));
+	}
+	Ok(None)
+}
+
+/// The primary servers root.
+pub(super) fn servers_root() -> anyhow::Result<PathBuf> {
+	servers_roots()?
+		.into_iter()
+		.next()
+		.ok_or_else(|| anyhow::anyhow!("No servers root configured"))
+}
+
+/// Same `ProjectDirs` as the managed Java runtimes.
+pub(super) fn config_path() -> anyhow::Result<PathBuf> {
+	let dirs = directories::ProjectDirs::from("", "", "eagle")
+		.ok_or_else(|| anyhow::anyhow!("Could not resolve config dir"))?;
+	Ok(dirs.config_dir().join(CONFIG_FILE))
 }
 
-pub(super) fn find_servers(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
-	if !root.exists() {
-		return Ok(Vec::new());
+fn load_config(path: &Path) -> anyhow::Result<Config> {
+	match std::fs::read(path) {
+		Ok(bytes) => serde_json::from_slice(&bytes)
+			.map_err(|e| anyhow::anyhow!("Invalid {}: {e}", path.display())),
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+			Ok(Config::default())
+		}
+		Err(e) => Err(e.into()),
 	}
+}
+
+/// Existing folders called `name` in any of `roots`; server names are kept
+/// unique across roots so lookups by name stay unambiguous.
+pub(super) fn folders_named(roots: &[PathBuf], name: &str) -> Vec<PathBuf> {
+	roots
+		.iter()
+		.map(|r| r.join(name))
+		.filter(|p| p.exists())
+		.collect()
+}
+
+/// `a, b` for messages about several roots.
+pub(super) fn display_roots(roots: &[PathBuf]) -> String {
+	let shown: Vec<String> =
+		roots.iter().map(|r| r.display().to_string()).collect();
+	shown.join(", ")
+}
 
+/// Lists server folders in every root: anything with an eagle manifest or
+/// launch files (see `has_launch_files`); a manifest that cannot be read is warned about and the
+/// folder kept with `manifest: None`. Sorted by name; earlier roots win ties.
+pub(super) fn find_servers(roots: &[PathBuf]) -> anyhow::Result<Vec<Server>> {
 	let mut out = Vec::new();
-	for entry in std::fs::read_dir(root)? {
-		let entry = entry?;
-		let path = entry.path();
-		if !path.is_dir() {
+	for root in roots {
+		if !root.exists() {
 			continue;
 		}
+		let mut found = Vec::new();
+		for entry in std::fs::read_dir(root)? {
+			let entry = entry?;
+			let path = entry.path();
+			if !path.is_dir() {
+				continue;
+			}
 
-		if path.join("server.jar").exists() {
-			out.push(path);
+			let has_manifest = path.join(manifest::MANIFEST_FILE).exists();
+			let manifest = manifest::load(&path).unwrap_or_else(|e| {
+				warn_once(&path, &e);
+				None
+			});
+			if has_manifest || has_launch_files(&path) {
+				found.push(Server { path, manifest });
+			}
 		}
+		found.sort_by(|a, b| a.path.cmp(&b.path));
+		out.extend(found);
 	}
 
-	out.sort();
+	out.sort_by(|a, b| a.name().cmp(b.name()));
 	Ok(out)
 }
 
+/// A `server.jar`, Quilt's launcher jar, or a Forge/NeoForge install
+/// (`libraries/` plus a run script), which has no `server.jar`.
+pub(super) fn has_launch_files(dir: &Path) -> bool {
+	let forge_libraries =
+		["libraries/net/minecraftforge", "libraries/net/neoforged"]
+			.iter()
+			.any(|lib| dir.join(lib).is_dir());
+	let run_script = ["run.sh", "run.bat"].iter().any(|s| dir.join(s).exists());
+	dir.join("server.jar").exists()
+		|| dir.join(quilt::LAUNCH_JAR).exists()
+		|| forge_libraries
+		|| (run_script && dir.join("libraries").is_dir())
+}
+
+/// Unreadable manifests are reported, not fatal, so one broken folder does
+/// not hide the others. Each folder is reported once per run.
+fn warn_once(path: &Path, error: &anyhow::Error) {
+	let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+	if !warned.contains(&path.to_path_buf()) {
+		eprintln!("Warning: {error}; treating it as a plain server folder");
+		warned.push(path.to_path_buf());
+	}
+}
+
+/// Resolves a server by folder name, or asks the user to pick one.
+pub(super) fn select_server(
+	roots: &[PathBuf],
+	name: Option<&str>,
+	prompt: &str,
+) -> anyhow::Result<Server> {
+	let mut servers = find_servers(roots)?;
+	if servers.is_empty() {
+		anyhow::bail!("No servers found in: {}", display_roots(roots));
+	}
+
+	if let Some(name) = name {
+		return servers
+			.into_iter()
+			.find(|s| s.name().eq_ignore_ascii_case(name))
+			.ok_or_else(|| {
+				anyhow::anyhow!(
+					"Server not found: {name} (in {})",
+					display_roots(roots)
+				)
+			});
+	}
+
+	let items: Vec<String> = servers.iter().map(Server::label).collect();
+	let selection = Select::new()
+		.with_prompt(prompt)
+		.items(&items)
+		.default(0)
+		.interact()?;
+
+	Ok(servers.swap_remove(selection))
+}
+
+/// Reads `server.properties` as plain `key=value` pairs; see
+/// [`Properties`] for edits that keep the file's layout.
+pub(super) fn read_properties(
+	server_dir: &Path,
+) -> anyhow::Result<HashMap<String, String>> {
+	Ok(Properties::load(server_dir)?.to_map())
+}
+
+/// Port the server listens on: `server-port` from `server.properties`, else
+/// the manifest (proxies), else Minecraft's default.
+pub(super) fn server_port(server: &Server) -> anyhow::Result<u16> {
+	let props = read_properties(&server.path)?;
+	if let Some(port) = props.get("server-port").and_then(|p| p.parse().ok()) {
+		return Ok(port);
+	}
+	Ok(server.manifest.as_ref().map(|m| m.port).unwrap_or(25565))
+}
+
+/// Treats the server as running when something accepts connections on its
+/// port locally; this catches servers started outside eagle too.
+pub(super) fn is_running(server: &Server) -> anyhow::Result<bool> {
+	let port = server_port(server)?;
+	let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+	let timeout = std::time::Duration::from_millis(300);
+	Ok(std::net::TcpStream::connect_timeout(&addr, timeout).is_ok())
+}
+
+/// `path` relative to the server folder, or `None` if it could escape it.
+pub(super) fn safe_path(path: &str) -> Option<PathBuf> {
+	let path = Path::new(path);
+	let safe = !path.as_os_str().is_empty()
+		&& path.components().all(|c| matches!(c, Component::Normal(_)));
+	safe.then(|| path.to_path_buf())
+}
+
+/// Backups live beside the servers, one folder per server name, in the
+/// root of `server_dir`.
+pub(super) fn backups_dir(server_dir: &Path) -> PathBuf {
+	let name = server_dir.file_name().unwrap_or_default();
+	let root = server_dir.parent().unwrap_or(Path::new("."));
+	root.join(BACKUPS_DIR).join(name)
+}
+
 pub(super) struct DirGuard {
 	path: PathBuf,
 	committed: bool,
@@ -62,3 +327,154 @@ impl Drop for DirGuard {
 		let _ = std::fs::remove_dir_all(&self.path);
 	}
 }
+
+/// Fresh per-process folder under the system temp dir for a test, removed
+/// again when dropped (also when the test panics).
+#[cfg(test)]
+pub(super) struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+	pub(super) fn new(name: &str) -> Self {
+		let path = std::env::temp_dir()
+			.join(format!("eagle-{name}-test-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&path);
+		std::fs::create_dir_all(&path).unwrap();
+		Self(path)
+	}
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
+
+/// World folders of a server: `level-name` plus Paper's split
+/// `_nether`/`_the_end` siblings when present.
+pub(super) fn world_dirs(server_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+	let props = read_properties(server_dir)?;
+	let level = props
+		.get("level-name")
+		.filter(|s| !s.is_empty())
+		.map(String::as_str)
+		.unwrap_or("world");
+
+	Ok([
+		level.to_string(),
+		format!("{level}_nether"),
+		format!("{level}_the_end"),
+	]
+	.into_iter()
+	.map(|name| server_dir.join(name))
+	.filter(|p| p.is_dir())
+	.collect())
+}
+
+/// Total size of the files under `path`; 0 if it does not exist.
+pub(super) fn dir_size(path: &Path) -> anyhow::Result<u64> {
+	if !path.is_dir() {
+		return Ok(0);
+	}
+	let mut total = 0;
+	for entry in std::fs::read_dir(path)? {
+		let entry = entry?;
+		total += match entry.file_type()?.is_dir() {
+			true => dir_size(&entry.path())?,
+			false => entry.metadata()?.len(),
+		};
+	}
+	Ok(total)
+}
+
+/// Recursively copies `src` into `dst` (created if missing).
+pub(super) fn copy_dir(src: &Path, dst: &Path) -> anyhow::Result<()> {
+	std::fs::create_dir_all(dst)?;
+	for entry in std::fs::read_dir(src)? {
+		let entry = entry?;
+		let from = entry.path();
+		let to = dst.join(entry.file_name());
+		if entry.file_type()?.is_dir() {
+			copy_dir(&from, &to)?;
+		} else {
+			std::fs::copy(&from, &to)?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn roots(paths: &[&str]) -> Vec<PathBuf> {
+		paths.iter().map(PathBuf::from).collect()
+	}
+
+	#[test]
+	fn roots_precedence() {
+		let env = std::env::join_paths(["/e1", "/e2"]).unwrap();
+		let config = || Ok(roots(&["/c"]));
+		let unread = || -> anyhow::Result<Vec<PathBuf>> {
+			panic!("config read despite an override")
+		};
+
+		let flags = roots(&["/f"]);
+		let found = choose_roots(&flags, Some(&env), unread).unwrap();
+		assert_eq!(found, Some((roots(&["/f"]), RootsSource::Flag)));
+
+		let found = choose_roots(&[], Some(&env), unread).unwrap();
+		assert_eq!(found, Some((roots(&["/e1", "/e2"]), RootsSource::Env)));
+
+		let empty = std::ffi::OsString::new();
+		let found = choose_roots(&[], Some(&empty), config).unwrap();
+		assert_eq!(found, Some((roots(&["/c"]), RootsSource::Config)));
+
+		let found = choose_roots(&[], None, || Ok(Vec::new())).unwrap();
+		assert_eq!(found, None);
+	}
+
+	#[test]
+	fn env_roots_skip_empty_entries() {
+		let env = std::env::join_paths(["/a", "", "/b"]).unwrap();
+		let found = choose_roots(&[], Some(&env), || Ok(Vec::new())).unwrap();
+		assert_eq!(found, Some((roots(&["/a", "/b"]), RootsSource::Env)));
+	}
+
+	#[test]
+	fn rejects_paths_leaving_the_server() {
+		assert_eq!(
+			safe_path("mods/sodium.jar"),
+			Some(PathBuf::from("mods/sodium.jar"))
+		);
+		assert!(safe_path("../evil.jar").is_none());
+		assert!(safe_path("mods/../../evil.jar").is_none());
+		assert!(safe_path("/etc/passwd").is_none());
+		assert!(safe_path("").is_none());
+	}
+
+	#[test]
+	fn broken_manifest_does_not_hide_other_servers() {
+		let root = TestDir::new("fs");
+		std::fs::create_dir_all(root.join("good")).unwrap();
+		std::fs::create_dir_all(root.join("bad")).unwrap();
+		std::fs::write(root.join("good").join("server.jar"), b"").unwrap();
+		std::fs::write(root.join("bad").join(manifest::MANIFEST_FILE), b"{no")
+			.unwrap();
+
+		let servers = find_servers(&[root.to_path_buf()]).unwrap();
+		let names: Vec<&str> = servers.iter().map(|s| s.name()).collect();
+		assert_eq!(names, ["bad", "good"]);
+		assert!(servers[0].manifest.is_none());
+	}
+}
@@ -0,0 +1,245 @@
+//! `ServerType` dispatch for resolving and installing server software, shared
+//! by `create` and `upgrade`.
+
+use std::path::{Path, PathBuf};
+
+use super::fabric::{self, FabricBuild};
+use super::fill::{self, FillProject};
+use super::forge::{self, Flavor};
+use super::java;
+use super::manifest::{Launch, ServerManifest, ServerType};
+use super::purpur;
+use super::quilt;
+use super::vanilla;
+use crate::{net, util};
+
+/// User pins for a download; `None` means newest stable.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Pin {
+	pub(super) build: Option<u64>,
+	pub(super) loader: Option<String>,
+}
+
+/// What a resolved download consists of, in manifest terms.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Build {
+	pub(super) build: Option<u64>,
+	pub(super) loader: Option<String>,
+	pub(super) installer: Option<String>,
+}
+
+impl Build {
+	/// Whether `manifest` already records exactly this build.
+	pub(super) fn is_installed(&self, manifest: &ServerManifest) -> bool {
+		manifest.build == self.build
+			&& manifest.loader == self.loader
+			&& manifest.installer == self.installer
+	}
+
+	pub(super) fn apply(self, manifest: &mut ServerManifest) {
+		manifest.build = self.build;
+		manifest.loader = self.loader;
+		manifest.installer = self.installer;
+	}
+}
+
+/// Resolves aliases like `latest` or `1.21` to a concrete version.
+pub(super) fn resolve_version(
+	server_type: ServerType,
+	input: &str,
+) -> anyhow::Result<String> {
+	if let Some(project) = FillProject::for_type(server_type) {
+		return fill::resolve_version(project, input);
+	}
+
+	match server_type {
+		ServerType::Purpur => purpur::resolve_purpur_version(input),
+		ServerType::Vanilla => vanilla::resolve_vanilla_version(input),
+		_ => Ok(input.trim().to_string()),
+	}
+}
+
+/// Picks the build to install without downloading anything.
+pub(super) fn resolve_build(
+	server_type: ServerType,
+	version: &str,
+	pin: &Pin,
+) -> anyhow::Result<Build> {
+	let name = server_type.as_str();
+	if pin.build.is_some() && !server_type.has_builds() {
+		anyhow::bail!("--build does not apply to {name} servers");
+	}
+	if pin.loader.is_some() && !server_type.has_loader() {
+		anyhow::bail!("--loader does not apply to {name} servers");
+	}
+
+	if let Some(project) = FillProject::for_type(server_type) {
+		let build = fill::resolve_build(project, version, pin.build)?;
+		return Ok(Build {
+			build: Some(build.id),
+			..Build::default()
+		});
+	}
+
+	match server_type {
+		ServerType::Purpur => Ok(Build {
+			build: Some(purpur::resolve_purpur_build(version, pin.build)?),
+			..Build::default()
+		}),
+		ServerType::Fabric => {
+			let fb =
+				fabric::resolve_fabric_build(version, pin.loader.as_deref())?;
+			Ok(Build {
+				loader: Some(fb.loader),
+				installer: Some(fb.installer),
+				..Build::default()
+			})
+		}
+		ServerType::Quilt => {
+			let (loader, installer) =
+				quilt::resolve_quilt_build(version, pin.loader.as_deref())?;
+			Ok(Build {
+				loader: Some(loader),
+				installer: Some(installer),
+				..Build::default()
+			})
+		}
+		ServerType::Forge | ServerType::NeoForge => {
+			let flavor = forge_flavor(server_type);
+			let loader =
+				forge::resolve_loader(flavor, version, pin.loader.as_deref())?;
+			Ok(Build {
+				loader: Some(loader),
+				..Build::default()
+			})
+		}
+		_ => Ok(Build::default()),
+	}
+}
+
+fn forge_flavor(server_type: ServerType) -> Flavor {
+	if server_type == ServerType::NeoForge {
+		Flavor::NeoForge
+	} else {
+		Flavor::Forge
+	}
+}
+
+/// Installs a build previously returned by [`resolve_build`] into
+/// `server_dir` and returns how to launch it.
+///
+/// Plain jars are downloaded straight to `server.jar` (atomically, via
+/// `net`); loaders that ship an installer run it in the server folder.
+pub(super) fn install(
+	server_type: ServerType,
+	version: &str,
+	build: &Build,
+	server_dir: &Path,
+) -> anyhow::Result<Launch> {
+	let jar_path = server_dir.join("server.jar");
+
+	if let Some(project) = FillProject::for_type(server_type) {
+		let fill_build = fill::resolve_build(project, version, build.build)?;
+		fill::download_build(project, version, &fill_build, &jar_path)?;
+		return Ok(Launch::default());
+	}
+
+	let loader = || {
+		build.loader.as_deref().ok_or_else(|| {
+			anyhow::anyhow!("Missing {} loader version", server_type.as_str())
+		})
+	};
+	let installer = || {
+		build.installer.as_deref().ok_or_else(|| {
+			anyhow::anyhow!(
+				"Missing {} installer version",
+				server_type.as_str()
+			)
+		})
+	};
+
+	match server_type {
+		ServerType::Purpur => {
+			let id = build
+				.build
+				.ok_or_else(|| anyhow::anyhow!("Missing Purpur build id"))?;
+			purpur::download_purpur_build(version, id, &jar_path)?;
+		}
+		ServerType::Fabric => {
+			let fb = FabricBuild {
+				loader: loader()?.to_string(),
+				installer: installer()?.to_string(),
+			};
+			fabric::download_fabric_build(version, &fb, &jar_path)?;
+		}
+		ServerType::Vanilla => {
+			vanilla::download_vanilla_server(version, &jar_path)?
+		}
+		ServerType::Quilt => {
+			let java = installer_java(server_type, version)?;
+			return quilt::install_quilt_server(
+				&java,
+				version,
+				loader()?,
+				installer()?,
+				server_dir,
+			);
+		}
+		ServerType::Forge | ServerType::NeoForge => {
+			let java = installer_java(server_type, version)?;
+			let flavor = forge_flavor(server_type);
+			return forge::install_server(
+				&java,
+				flavor,
+				version,
+				loader()?,
+				server_dir,
+			);
+		}
+		_ => anyhow::bail!("No download for {} servers", server_type.as_str()),
+	}
+
+	Ok(Launch::default())
+}
+
+/// Downloads a Maven artifact, verified against the `.sha1` the repository
+/// publishes next to it.
+pub(super) fn download_from_maven(
+	url: &str,
+	out_path: &Path,
+) -> anyhow::Result<()> {
+	let sha1 = net::get_text(&format!("{url}.sha1"))?;
+	let sha1 = sha1.split_whitespace().next().unwrap_or_default();
+	net::download_verified(url, out_path, Some(net::Checksum::Sha1(sha1)))
+}
+
+/// Installers run on the same Java major the server will.
+fn installer_java(
+	server_type: ServerType,
+	version: &str,
+) -> anyhow::Result<PathBuf> {
+	let required = java::required_major(server_type, version);
+	Ok(java::resolve(required, false)?.java)
+}
+
+/// Runs `java -jar <installer> <args>` headlessly in `server_dir` and removes
+/// the installer afterwards.
+pub(super) fn run_java_installer(
+	java: &Path,
+	server_dir: &Path,
+	installer_jar: &str,
+	args: &[&str],
+) -> anyhow::Result<()> {
+	let mut full = vec!["-jar", installer_jar];
+	full.extend_from_slice(args);
+
+	let java = java.to_string_lossy();
+	let status = util::run_inherit_with_dir(&java, &full, server_dir);
+	let _ = std::fs::remove_file(server_dir.join(installer_jar));
+
+	let status = status?;
+	if !status.success() {
+		anyhow::bail!("Installer {installer_jar} failed: {status}");
+	}
+	Ok(())
+}
@@ -0,0 +1,428 @@
+//! Java runtime discovery and eagle-managed Temurin installs.
+//!
+//! Servers need different JDK majors (1.16 wants 8, 1.21 wants 21), so `start`
+//! picks a runtime per server instead of trusting whatever `java` is on PATH.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgMatches, Command};
+use serde::Deserialize;
+
+use super::fill;
+use super::manifest::ServerType;
+use super::vanilla;
+use crate::net;
+
+/// A Java installation eagle can launch servers with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Runtime {
+	pub(super) java: PathBuf,
+	pub(super) major: u32,
+	pub(super) managed: bool,
+}
+
+pub(super) fn build_command() -> Command {
+	Command::new("java")
+		.about("List detected Java runtimes or install a managed one")
+		.subcommand(Command::new("list").about("List detected Java runtimes"))
+		.subcommand(
+			Command::new("install")
+				.about("Download a Temurin JRE into eagle's data dir")
+				.arg(
+					Arg::new("major")
+						.help("Java major version (e.g. 21)")
+						.value_parser(clap::value_parser!(u32))
+						.required(true),
+				),
+		)
+}
+
+/// What `minecraft java` was asked to do.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+	List,
+	Install(u32),
+}
+
+fn action(matches: &ArgMatches) -> anyhow::Result<Action> {
+	match matches.subcommand() {
+		Some(("install", sub)) => {
+			Ok(Action::Install(*sub.get_one::<u32>("major").unwrap_or(&21)))
+		}
+		Some(("list", _)) | None => Ok(Action::List),
+		Some((other, _)) => anyhow::bail!("Unknown subcommand: {other}"),
+	}
+}
+
+pub(super) fn run_java(matches: &ArgMatches) -> anyhow::Result<()> {
+	match action(matches)? {
+		Action::Install(major) => {
+			let runtime = install_temurin(major)?;
+			println!("Java {}: {}", runtime.major, runtime.java.display());
+		}
+		Action::List => {
+			let runtimes = detect_runtimes();
+			if runtimes.is_empty() {
+				println!("No Java runtimes found.");
+			}
+			for r in runtimes {
+				let tag = if r.managed { " (managed)" } else { "" };
+				println!("Java {:<3} {}{tag}", r.major, r.java.display());
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Java major a server needs. Uses Mojang's `javaVersion` when reachable and
+/// falls back to a built-in table offline.
+pub(super) fn required_major(server_type: ServerType, version: &str) -> u32 {
+	match server_type {
+		ServerType::Velocity => return 21,
+		ServerType::Waterfall => return 17,
+		_ => {}
+	}
+
+	match vanilla::java_major(version) {
+		Ok(Some(major)) => major,
+		Ok(None) => 8,
+		Err(_) => fallback_major(version),
+	}
+}
+
+fn fallback_major(version: &str) -> u32 {
+	match fill::version_key(version).as_slice() {
+		[1, minor, ..] if *minor <= 16 => 8,
+		[1, 17, ..] => 16,
+		[1, 18 | 19, ..] => 17,
+		[1, 20, patch, ..] if *patch <= 4 => 17,
+		[1, 20] => 17,
+		_ => 21,
+	}
+}
+
+/// Picks a runtime for `required`: an exact match first, otherwise the
+/// closest newer one. Legacy servers (Java 8) don't run on modern JVMs, so
+/// they only accept up to Java 11.
+pub(super) fn pick(runtimes: &[Runtime], required: u32) -> Option<&Runtime> {
+	let max = if required <= 8 { 11 } else { u32::MAX };
+	runtimes.iter().find(|r| r.major == required).or_else(|| {
+		runtimes
+			.iter()
+			.filter(|r| r.major > required && r.major <= max)
+			.min_by_key(|r| r.major)
+	})
+}
+
+/// Finds (or, with `install`, downloads) a runtime for `required`.
+pub(super) fn resolve(required: u32, install: bool) -> anyhow::Result<Runtime> {
+	let runtimes = detect_runtimes();
+	if let Some(found) = pick(&runtimes, required) {
+		return Ok(found.clone());
+	}
+
+	if install {
+		return install_temurin(required);
+	}
+
+	let found: Vec<String> =
+		runtimes.iter().map(|r| r.major.to_string()).collect();
+	let found = if found.is_empty() {
+		"none".to_string()
+	} else {
+		found.join(", ")
+	};
+	anyhow::bail!(
+		"This server needs Java {required}; found: {found}. Install one with \
+		 `eagle minecraft java install {required}`"
+	)
+}
+
+/// All runtimes found on this machine, managed ones first.
+pub(super) fn detect_runtimes() -> Vec<Runtime> {
+	let managed_root = runtimes_dir().ok();
+
+	let mut exes: Vec<(PathBuf, bool)> = Vec::new();
+	if let Some(root) = &managed_root {
+		exes.extend(homes_in(root).map(|h| (java_exe(&h), true)));
+	}
+	if let Some(home) = std::env::var_os("JAVA_HOME") {
+		exes.push((java_exe(Path::new(&home)), false));
+	}
+	if let Ok(found) = which::which_all("java") {
+		exes.extend(found.map(|p| (p, false)));
+	}
+	for root in system_roots() {
+		exes.extend(homes_in(&root).map(|h| (java_exe(&h), false)));
+	}
+
+	let mut seen = Vec::new();
+	let mut out = Vec::new();
+	for (exe, managed) in exes {
+		let Ok(canonical) = std::fs::canonicalize(&exe) else {
+			continue;
+		};
+		if seen.contains(&canonical) {
+			continue;
+		}
+		seen.push(canonical.clone());
+
+		if let Some(major) = probe_major(&canonical) {
+			out.push(Runtime {
+				java: canonical,
+				major,
+				managed,
+			});
+		}
+	}
+
+	out
+}
+
+fn system_roots() -> Vec<PathBuf> {
+	let mut roots = Vec::new();
+	if cfg!(windows) {
+		for base in ["ProgramFiles", "ProgramFiles(x86)"] {
+			if let Some(base) = std::env::var_os(base) {
+				let base = PathBuf::from(base);
+				for vendor in [
+					"Java",
+					"Eclipse Adoptium",
+					"Microsoft",
+					"Zulu",
+					"BellSoft",
+				] {
+					roots.push(base.join(vendor));
+				}
+			}
+		}
+	} else if cfg!(target_os = "macos") {
+		roots.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+	} else {
+		roots.push(PathBuf::from("/usr/lib/jvm"));
+		roots.push(PathBuf::from("/opt/java"));
+	}
+
+	if let Some(home) = directories::UserDirs::new() {
+		roots.push(home.home_dir().join(".jdks"));
+	}
+	roots
+}
+
+/// JDK homes directly below `root` (macOS bundles included).
+fn homes_in(root: &Path) -> impl Iterator<Item = PathBuf> {
+	std::fs::read_dir(root)
+		.into_iter()
+		.flatten()
+		.flatten()
+		.map(|e| e.path())
+		.filter_map(|p| find_java_home(&p))
+}
+
+fn find_java_home(dir: &Path) -> Option<PathBuf> {
+	[dir.to_path_buf(), dir.join("Contents").join("Home")]
+		.into_iter()
+		.find(|home| java_exe(home).is_file())
+}
+
+fn java_exe(home: &Path) -> PathBuf {
+	let name = if cfg!(windows) { "java.exe" } else { "java" };
+	home.join("bin").join(name)
+}
+
+/// Reads the major from the JDK's `release` file, or asks `java -version`.
+fn probe_major(java: &Path) -> Option<u32> {
+	let home = java.parent()?.parent()?;
+	if let Ok(release) = std::fs::read_to_string(home.join("release")) {
+		let version = release
+			.lines()
+			.find_map(|l| l.strip_prefix("JAVA_VERSION="))
+			.map(|v| v.trim_matches('"'));
+		if let Some(major) = version.and_then(parse_java_version) {
+			return Some(major);
+		}
+	}
+
+	let out = std::process::Command::new(java)
+		.arg("-version")
+		.output()
+		.ok()?;
+	let text = String::from_utf8_lossy(&out.stderr);
+	let quoted = text.split('"').nth(1)?;
+	parse_java_version(quoted)
+}
+
+/// `1.8.0_402` -> 8, `21.0.2` -> 21, `17` -> 17.
+fn parse_java_version(s: &str) -> Option<u32> {
+	let mut parts = s.trim().split(['.', '_', '-', '+']);
+	let first = parts.next()?.parse::<u32>().ok()?;
+	if first == 1 {
+		return parts.next()?.parse::<u32>().ok();
+	}
+	Some(first)
+}
+
+/// Where eagle keeps the runtimes it downloaded.
+pub(super) fn runtimes_dir() -> anyhow::Result<PathBuf> {
+	let dirs = directories::ProjectDirs::from("", "", "eagle")
+		.ok_or_else(|| anyhow::anyhow!("Could not resolve data dir"))?;
+	Ok(dirs.data_dir().join("runtimes"))
+}
+
+/// Minimal shape of `GET https://api.adoptium.net/v3/assets/latest/...`.
+#[derive(Debug, Clone, Deserialize)]
+struct AdoptiumAsset {
+	binary: AdoptiumBinary,
+	release_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AdoptiumBinary {
+	package: AdoptiumPackage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AdoptiumPackage {
+	name: String,
+	link: String,
+	checksum: String,
+}
+
+/// Downloads the latest Temurin JRE for `major` (once) and returns it.
+pub(super) fn install_temurin(major: u32) -> anyhow::Result<Runtime> {
+	let root = runtimes_dir()?;
+	let target = root.join(format!("temurin-{major}"));
+	if let Some(home) = homes_in(&target).next() {
+		return Ok(Runtime {
+			java: java_exe(&home),
+			major,
+			managed: true,
+		});
+	}
+
+	let (os, arch) = adoptium_platform()?;
+	let url = format!(
+		"https://api.adoptium.net/v3/assets/latest/{major}/hotspot?architecture={arch}&image_type=jre&os={os}&vendor=eclipse"
+	);
+	let assets = net::get_json::<Vec<AdoptiumAsset>>(&url)?;
+	let asset = assets.first().ok_or_else(|| {
+		anyhow::anyhow!("No Temurin {major} build for {os}/{arch}")
+	})?;
+
+	println!("Downloading Temurin {}...", asset.release_name);
+	std::fs::create_dir_all(&root)?;
+	let pkg = &asset.binary.package;
+	let archive = root.join(&pkg.name);
+	net::download_verified(
+		&pkg.link,
+		&archive,
+		Some(net::Checksum::Sha256(&pkg.checksum)),
+	)?;
+
+	let staging = root.join(format!("temurin-{major}.part"));
+	if staging.exists() {
+		std::fs::remove_dir_all(&staging)?;
+	}
+	let extracted = extract_archive(&archive, &staging);
+	let _ = std::fs::remove_file(&archive);
+	if let Err(err) = extracted {
+		let _ = std::fs::remove_dir_all(&staging);
+		return Err(err);
+	}
+	std::fs::rename(&staging, &target)?;
+
+	let home = homes_in(&target).next().ok_or_else(|| {
+		anyhow::anyhow!("No java binary in {}", target.display())
+	})?;
+	Ok(Runtime {
+		java: java_exe(&home),
+		major,
+		managed: true,
+	})
+}
+
+fn adoptium_platform() -> anyhow::Result<(&'static str, &'static str)> {
+	let os = match std::env::consts::OS {
+		"windows" => "windows",
+		"linux" => "linux",
+		"macos" => "mac",
+		other => anyhow::bail!("No Temurin builds for OS: {other}"),
+	};
+	let arch = match std::env::consts::ARCH {
+		"x86_64" => "x64",
+		"aarch64" => "aarch64",
+		other => anyhow::bail!("No Temurin builds for arch: {other}"),
+	};
+	Ok((os, arch))
+}
+
+fn extract_archive(archive: &Path, dest: &Path) -> anyhow::Result<()> {
+	let file = std::fs::File::open(archive)?;
+	let name = archive.to_string_lossy();
+
+	if name.ends_with(".zip") {
+		zip::ZipArchive::new(file)?.extract(dest)?;
+	} else if name.ends_with(".tar.gz") {
+		tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(dest)?;
+	} else {
+		anyhow::bail!("Unsupported archive: {}", archive.display());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rt(major: u32) -> Runtime {
+		Runtime {
+			java: PathBuf::from(format!("/jdk{major}/bin/java")),
+			major,
+			managed: false,
+		}
+	}
+
+	#[test]
+	fn subcommands_map_to_actions() {
+		let parse = |args: &[&str]| build_command().try_get_matches_from(args);
+		let action_of = |args: &[&str]| action(&parse(args).unwrap()).unwrap();
+		assert_eq!(action_of(&["java"]), Action::List);
+		assert_eq!(action_of(&["java", "list"]), Action::List);
+		assert!(parse(&["java", "install"]).is_err());
+		assert_eq!(action_of(&["java", "install", "17"]), Action::Install(17));
+	}
+
+	#[test]
+	fn parses_java_versions() {
+		assert_eq!(parse_java_version("1.8.0_402"), Some(8));
+		assert_eq!(parse_java_version("21.0.2"), Some(21));
+		assert_eq!(parse_java_version("17"), Some(17));
+		assert_eq!(parse_java_version("17-ea"), Some(17));
+		assert_eq!(parse_java_version("garbage"), None);
+	}
+
+	#[test]
+	fn fallback_table() {
+		assert_eq!(fallback_major("1.12.2"), 8);
+		assert_eq!(fallback_major("1.16.5"), 8);
+		assert_eq!(fallback_major("1.17.1"), 16);
+		assert_eq!(fallback_major("1.20.4"), 17);
+		assert_eq!(fallback_major("1.20.6"), 21);
+		assert_eq!(fallback_major("1.21.4"), 21);
+	}
+
+	#[test]
+	fn pick_prefers_exact_then_closest_newer() {
+		let runtimes = [rt(8), rt(21), rt(17)];
+		assert_eq!(pick(&runtimes, 17).map(|r| r.major), Some(17));
+		assert_eq!(pick(&runtimes, 16).map(|r| r.major), Some(17));
+		assert_eq!(pick(&runtimes, 8).map(|r| r.major), Some(8));
+	}
+
+	#[test]
+	fn pick_keeps_legacy_servers_off_modern_java() {
+		let runtimes = [rt(17), rt(21)];
+		assert!(pick(&runtimes, 8).is_none());
+		assert_eq!(pick(&[rt(11)], 8).map(|r| r.major), Some(11));
+	}
+}
@@ -0,0 +1,421 @@
+//! `delete`, `rename` and `clone`: whole-folder operations on a server,
+//! keeping its backups, manifest and configs in step.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgMatches, Command};
+use dialoguer::Confirm;
+
+use super::backup;
+use super::console;
+use super::create;
+use super::fs;
+use super::manifest::{self, ServerType};
+use super::ports;
+use super::properties::Properties;
+use super::rcon;
+use super::restart;
+
+/// Not copied by `clone`; they belong to the original's runs.
+const CLONE_SKIP: [&str; 4] = [
+	"logs",
+	"crash-reports",
+	console::LOG_DIR,
+	restart::INCIDENT_DIR,
+];
+
+fn server_arg() -> Arg {
+	Arg::new("server").help("Server folder name").required(true)
+}
+
+fn force_arg(help: &'static str) -> Arg {
+	Arg::new("force")
+		.long("force")
+		.help(help)
+		.action(clap::ArgAction::SetTrue)
+}
+
+pub(super) fn build_delete_command() -> Command {
+	Command::new("delete")
+		.about("Delete a server folder (backups are kept)")
+		.arg(server_arg())
+		.arg(
+			Arg::new("yes")
+				.long("yes")
+				.short('y')
+				.help("Do not ask for confirmation")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("backup")
+				.long("backup")
+				.help("Back up the world before deleting")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(force_arg("Delete even if the server seems to be running"))
+}
+
+pub(super) fn build_rename_command() -> Command {
+	Command::new("rename")
+		.about("Rename a server folder and its backups")
+		.arg(server_arg())
+		.arg(Arg::new("new_name").help("New folder name").required(true))
+}
+
+pub(super) fn build_clone_command() -> Command {
+	Command::new("clone")
+		.about("Copy a server to a new folder with its own ports")
+		.arg(server_arg())
+		.arg(Arg::new("new_name").help("New folder name").required(true))
+		.arg(
+			Arg::new("no_world")
+				.long("no-world")
+				.help("Leave out the world folders (a new world is generated)")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("port")
+				.long("port")
+				.help("Port for the copy (default: next free port)")
+				.value_parser(clap::value_parser!(u16)),
+		)
+		.arg(force_arg("Copy even if the server seems to be running"))
+}
+
+fn ensure_stopped(server: &fs::Server, force: bool) -> anyhow::Result<()> {
+	if !force && fs::is_running(server)? {
+		anyhow::bail!(
+			"{} appears to be running (port {} is open); stop it first or pass --force",
+			server.name(),
+			fs::server_port(server)?
+		);
+	}
+	Ok(())
+}
+
+pub(super) fn run_delete(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"",
+	)?;
+	ensure_stopped(&server, matches.get_flag("force"))?;
+
+	if !matches.get_flag("yes")
+		&& !Confirm::new()
+			.with_prompt(format!(
+				"Delete {} and everything in it?",
+				server.path.display()
+			))
+			.default(false)
+			.interact()?
+	{
+		println!("Aborted; nothing was deleted.");
+		return Ok(());
+	}
+
+	if matches.get_flag("backup") {
+		if fs::world_dirs(&server.path)?.is_empty() {
+			// Never started, so there is nothing worth keeping.
+			println!("No world folders to back up; skipping the backup.");
+		} else {
+			let job = backup::job_for(&server, matches)?;
+			backup::backup_now(&server, job.format, job.retention)?;
+		}
+	}
+	std::fs::remove_dir_all(&server.path)?;
+	println!("Deleted {}", server.path.display());
+
+	let backups = fs::backups_dir(&server.path);
+	if !backup::list_backups(&backups)?.is_empty() {
+		println!("Backups kept in {}", backups.display());
+	}
+	Ok(())
+}
+
+pub(super) fn run_rename(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let mut server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"",
+	)?;
+	let new_path = new_path(&roots, server.root(), matches)?;
+	let new_name = new_path
+		.file_name()
+		.unwrap_or_default()
+		.to_string_lossy()
+		.into_owned();
+	ensure_stopped(&server, false)?;
+
+	let old_backups = fs::backups_dir(&server.path);
+	let new_backups = fs::backups_dir(&new_path);
+	if old_backups.exists() && new_backups.exists() {
+		anyhow::bail!(
+			"Backups folder already exists: {}",
+			new_backups.display()
+		);
+	}
+
+	let old_name = server.name().to_string();
+	let old_path = server.path.clone();
+	std::fs::rename(&old_path, &new_path)?;
+	if old_backups.exists() {
+		// Both folders move or neither does.
+		if let Err(e) = std::fs::rename(&old_backups, &new_backups) {
+			std::fs::rename(&new_path, &old_path).map_err(|undo| {
+				anyhow::anyhow!(
+					"Could not move {} ({e}), nor move {} back ({undo})",
+					old_backups.display(),
+					new_path.display()
+				)
+			})?;
+			anyhow::bail!(
+				"Could not move {} ({e}); nothing was renamed",
+				old_backups.display()
+			);
+		}
+		rename_backup_files(&new_backups, &old_name, &new_name)?;
+	}
+
+	server.path = new_path;
+	if let Some(m) = server.manifest.as_mut()
+		&& rebase_java(m, &old_path, &server.path)
+	{
+		manifest::save(&server.path, m)?;
+	}
+	println!("Renamed {old_name} to {new_name}");
+	Ok(())
+}
+
+pub(super) fn run_clone(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let source = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"",
+	)?;
+	let dest = new_path(&roots, source.root(), matches)?;
+	let with_world = !matches.get_flag("no_world");
+	if with_world {
+		ensure_stopped(&source, matches.get_flag("force"))?;
+	}
+
+	let allocations = ports::allocations(&roots)?;
+	let old_port = fs::server_port(&source)?;
+	let explicit = matches.get_one::<u16>("port").copied();
+	let port = ports::for_new_server(
+		&allocations,
+		&dest,
+		explicit.unwrap_or(old_port),
+		explicit.is_none(),
+	)?;
+
+	let mut guard = fs::DirGuard::new(dest.clone());
+	let skip: Vec<_> = match with_world {
+		true => Vec::new(),
+		false => fs::world_dirs(&source.path)?,
+	};
+	std::fs::create_dir_all(&dest)?;
+	for entry in std::fs::read_dir(&source.path)? {
+		let entry = entry?;
+		let from = entry.path();
+		let skipped = CLONE_SKIP.iter().any(|s| entry.file_name() == *s);
+		if skipped || skip.contains(&from) {
+			continue;
+		}
+		let to = dest.join(entry.file_name());
+		match entry.file_type()?.is_dir() {
+			true => fs::copy_dir(&from, &to)?,
+			false => {
+				std::fs::copy(&from, &to)?;
+			}
+		}
+	}
+
+	let mut claimed: Vec<u16> = allocations.iter().map(|a| a.port).collect();
+	claimed.push(port);
+	let rcon = repoint(&dest, old_port, port, &claimed)?;
+	if let Some(mut m) = source.manifest.clone() {
+		if m.server_type.is_proxy() {
+			repoint_proxy(&dest, m.server_type, old_port, port)?;
+		}
+		m.port = port;
+		m.created_at = Some(manifest::unix_now());
+		m.last_started_at = None;
+		rebase_java(&mut m, &source.path, &dest);
+		manifest::save(&dest, &m)?;
+	}
+	guard.commit();
+
+	println!("Cloned {} to {}", source.name(), dest.display());
+	println!("Port: {port}");
+	if let Some(rcon) = rcon {
+		println!(
+			"RCON: port {} (new password in server.properties)",
+			rcon.port
+		);
+	}
+	if !with_world {
+		println!("World: not copied; a new one is generated on first start");
+	}
+	Ok(())
+}
+
+/// `root/<new_name>`, validated and not taken in any of `roots`; copies and
+/// renames stay in the root of the original.
+fn new_path(
+	roots: &[PathBuf],
+	root: &Path,
+	matches: &ArgMatches,
+) -> anyhow::Result<PathBuf> {
+	let name = matches
+		.get_one::<String>("new_name")
+		.map(|s| s.as_str())
+		.unwrap_or_default();
+	create::validate_server_name(name)?;
+	if let Some(existing) = fs::folders_named(roots, name).first() {
+		anyhow::bail!("Folder already exists: {}", existing.display());
+	}
+	Ok(root.join(name))
+}
+
+/// Moves the copy's `server.properties` to `port` and gives it its own RCON
+/// port and password.
+fn repoint(
+	dir: &Path,
+	old_port: u16,
+	port: u16,
+	claimed: &[u16],
+) -> anyhow::Result<Option<rcon::Config>> {
+	if !Properties::path(dir).exists() {
+		return Ok(None);
+	}
+	let mut props = Properties::load(dir)?;
+	props.set("server-port", &port.to_string());
+	if props.get("query.port") == Some(&old_port.to_string()) {
+		props.set("query.port", &port.to_string());
+	}
+
+	let rcon = match props.get("enable-rcon") == Some("true") {
+		true => Some(rcon::Config::generate(claimed)?),
+		false => None,
+	};
+	if let Some(rcon) = &rcon {
+		props.set("rcon.port", &rcon.port.to_string());
+		props.set("rcon.password", &rcon.password);
+	}
+	props.save(dir)?;
+	Ok(rcon)
+}
+
+/// Rewrites the listener and query ports in a proxy's config.
+fn repoint_proxy(
+	dir: &Path,
+	server_type: ServerType,
+	old_port: u16,
+	port: u16,
+) -> anyhow::Result<()> {
+	let (file, keys): (_, &[&str]) = match server_type {
+		ServerType::Velocity => ("velocity.toml", &["bind", "port"]),
+		_ => ("config.yml", &["host", "query_port"]),
+	};
+	let path = dir.join(file);
+	let Ok(content) = std::fs::read_to_string(&path) else {
+		return Ok(());
+	};
+	let out: Vec<String> = content
+		.lines()
+		.map(|line| replace_port(line, keys, old_port, port))
+		.collect();
+	std::fs::write(&path, out.join("\n") + "\n")?;
+	Ok(())
+}
+
+/// `line` with a trailing `old` port swapped for `new` when it sets one of
+/// `keys` (`bind = "0.0.0.0:25577"`, `- host: 0.0.0.0:25577`, `port = 1`).
+fn replace_port(line: &str, keys: &[&str], old: u16, new: u16) -> String {
+	let body = line.trim_start().trim_start_matches("- ");
+	let sets_key = keys.iter().any(|k| {
+		body.strip_prefix(k)
+			.is_some_and(|rest| rest.trim_start().starts_with(['=', ':']))
+	});
+	let value = line.trim_end().trim_end_matches('"');
+	let old = old.to_string();
+	let Some(prefix) = value.strip_suffix(&old) else {
+		return line.to_string();
+	};
+	let bounded = prefix.ends_with([':', '=', ' ', '"']);
+	if !sets_key || !bounded {
+		return line.to_string();
+	}
+	format!("{prefix}{new}{}", &line.trim_end()[value.len()..])
+}
+
+/// Points an explicit `java` inside the server folder at the new folder.
+fn rebase_java(
+	m: &mut manifest::ServerManifest,
+	old: &Path,
+	new: &Path,
+) -> bool {
+	let Some(rest) = m
+		.java
+		.as_deref()
+		.and_then(|j| Path::new(j).strip_prefix(old).ok())
+	else {
+		return false;
+	};
+	m.java = Some(new.join(rest).to_string_lossy().into_owned());
+	true
+}
+
+/// `old-<stamp>.zip` -> `new-<stamp>.zip`, so file names match the folder.
+fn rename_backup_files(dir: &Path, old: &str, new: &str) -> anyhow::Result<()> {
+	for backup in backup::list_backups(dir)? {
+		let Some(name) = backup.path.file_name().and_then(|n| n.to_str())
+		else {
+			continue;
+		};
+		if let Some(rest) = name.strip_prefix(&format!("{old}-")) {
+			std::fs::rename(&backup.path, dir.join(format!("{new}-{rest}")))?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn replaces_only_listener_ports() {
+		let velocity = ["bind", "port"];
+		assert_eq!(
+			replace_port(r#"bind = "0.0.0.0:25577""#, &velocity, 25577, 25578),
+			r#"bind = "0.0.0.0:25578""#
+		);
+		assert_eq!(
+			replace_port("port = 25577", &velocity, 25577, 25578),
+			"port = 25578"
+		);
+		assert_eq!(
+			replace_port(r#"lobby = "127.0.0.1:25577""#, &velocity, 25577, 1),
+			r#"lobby = "127.0.0.1:25577""#
+		);
+		assert_eq!(
+			replace_port("port = 125577", &velocity, 25577, 1),
+			"port = 125577"
+		);
+
+		let waterfall = ["host", "query_port"];
+		assert_eq!(
+			replace_port("- host: 0.0.0.0:25577", &waterfall, 25577, 25578),
+			"- host: 0.0.0.0:25578"
+		);
+		assert_eq!(
+			replace_port("  query_port: 25577", &waterfall, 25577, 25578),
+			"  query_port: 25578"
+		);
+	}
+}
@@ -0,0 +1,393 @@
+//! `minecraft list`: every folder under the servers roots with its type,
+//! version, port, world size and state, including folders that look broken.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgMatches, Command};
+use serde::Serialize;
+
+use super::fill;
+use super::fs;
+use super::manifest::{self, ServerType};
+use super::properties::PROPERTIES_FILE;
+use crate::net;
+
+const SORT_KEYS: [&str; 6] =
+	["name", "type", "version", "port", "size", "started"];
+
+pub(super) fn build_command() -> Command {
+	Command::new("list")
+		.about("List servers with type, version, port and state")
+		.arg(
+			Arg::new("sort")
+				.long("sort")
+				.help(
+					"Sort by this column (size and started: largest/newest first)",
+				)
+				.value_parser(SORT_KEYS)
+				.default_value("name"),
+		)
+		.arg(
+			Arg::new("type")
+				.long("type")
+				.help("Only servers of this type")
+				.value_parser(ServerType::names()),
+		)
+		.arg(
+			Arg::new("running")
+				.long("running")
+				.help("Only servers that are running")
+				.action(clap::ArgAction::SetTrue)
+				.conflicts_with("stopped"),
+		)
+		.arg(
+			Arg::new("stopped")
+				.long("stopped")
+				.help("Only servers that are not running")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("broken")
+				.long("broken")
+				.help("Only folders with problems")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("json")
+				.long("json")
+				.help("Print the list as JSON")
+				.action(clap::ArgAction::SetTrue),
+		)
+}
+
+pub(super) fn build_roots_command() -> Command {
+	Command::new("roots")
+		.about("Show the servers roots in use and where they come from")
+}
+
+/// One folder under a servers root.
+#[derive(Debug, Clone, Serialize)]
+struct Entry {
+	name: String,
+	path: PathBuf,
+	#[serde(rename = "type")]
+	server_type: Option<ServerType>,
+	version: Option<String>,
+	port: Option<u16>,
+	/// Bytes used by the world folders.
+	world_size: u64,
+	jar: bool,
+	/// Unix seconds; from the manifest, else the last log write.
+	last_started_at: Option<u64>,
+	running: bool,
+	/// Why the folder looks broken; empty when it is fine.
+	problems: Vec<String>,
+}
+
+pub(super) fn run_list(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let mut entries = Vec::new();
+	for root in &roots {
+		entries.extend(scan(root)?);
+	}
+
+	let server_type = matches
+		.get_one::<String>("type")
+		.map(|t| ServerType::parse(t))
+		.transpose()?;
+	entries.retain(|e| {
+		server_type.is_none_or(|t| e.server_type == Some(t))
+			&& (!matches.get_flag("running") || e.running)
+			&& (!matches.get_flag("stopped") || !e.running)
+			&& (!matches.get_flag("broken") || !e.problems.is_empty())
+	});
+	let key = matches
+		.get_one::<String>("sort")
+		.map(|s| s.as_str())
+		.unwrap_or("name");
+	sort(&mut entries, key);
+
+	if matches.get_flag("json") {
+		println!("{}", serde_json::to_string_pretty(&entries)?);
+		return Ok(());
+	}
+	if entries.is_empty() {
+		println!("No servers in {}", fs::display_roots(&roots));
+		return Ok(());
+	}
+	print_table(&entries);
+	Ok(())
+}
+
+pub(super) fn run_roots() -> anyhow::Result<()> {
+	let (roots, source) = fs::servers_roots_with_source()?;
+	let source = match source {
+		fs::RootsSource::Flag => "--root".to_string(),
+		fs::RootsSource::Env => fs::ROOT_ENV.to_string(),
+		fs::RootsSource::Config => fs::config_path()?.display().to_string(),
+		fs::RootsSource::Default => "default".to_string(),
+	};
+	println!("Source: {source}");
+	for (i, root) in roots.iter().enumerate() {
+		let state = match root.exists() {
+			true => {
+				let count = fs::find_servers(std::slice::from_ref(root))?.len();
+				format!("{count} server(s)")
+			}
+			false => "missing".to_string(),
+		};
+		let primary = if i == 0 { ", new servers go here" } else { "" };
+		println!("  {} ({state}{primary})", root.display());
+	}
+	println!(
+		"Set with --root, {} or \"roots\" in {}",
+		fs::ROOT_ENV,
+		fs::config_path()?.display()
+	);
+	Ok(())
+}
+
+/// Every non-hidden folder under `root`, whether or not it looks like a
+/// server; `find_servers` skips the ones without a manifest or jar.
+fn scan(root: &Path) -> anyhow::Result<Vec<Entry>> {
+	if !root.exists() {
+		return Ok(Vec::new());
+	}
+
+	let mut out = Vec::new();
+	for dir in std::fs::read_dir(root)? {
+		let path = dir?.path();
+		let hidden = path
+			.file_name()
+			.is_some_and(|n| n.to_string_lossy().starts_with('.'));
+		if path.is_dir() && !hidden {
+			out.push(inspect(path));
+		}
+	}
+	Ok(out)
+}
+
+fn inspect(path: PathBuf) -> Entry {
+	let mut problems = Vec::new();
+	let manifest = manifest::load(&path).unwrap_or_else(|e| {
+		problems.push(e.to_string());
+		None
+	});
+	let server = fs::Server {
+		path: path.clone(),
+		manifest: manifest.clone(),
+	};
+
+	let target = manifest
+		.as_ref()
+		.map(|m| m.launch.target().to_string())
+		.unwrap_or_else(|| "server.jar".to_string());
+	// Without a manifest, loader installs have no single target to check.
+	let jar = path.join(&target).exists()
+		|| (manifest.is_none() && fs::has_launch_files(&path));
+	if !jar {
+		problems.push(format!("{target} missing"));
+	}
+	if manifest.is_none() && jar {
+		problems.push("no manifest (run `minecraft adopt`)".to_string());
+	}
+
+	let is_proxy = manifest.as_ref().is_some_and(|m| m.server_type.is_proxy());
+	let has_properties = path.join(PROPERTIES_FILE).exists();
+	if !is_proxy && !has_properties {
+		problems.push(format!("no {PROPERTIES_FILE}"));
+	}
+
+	// Read errors mark the folder broken instead of ending the listing.
+	let mut record = |what: &str, e: anyhow::Error| {
+		problems.push(format!("cannot read {what}: {e}"));
+	};
+	let port = match manifest.is_some() || has_properties {
+		true => fs::server_port(&server).map_err(|e| record("port", e)).ok(),
+		false => None,
+	};
+	let running = port.is_some()
+		&& fs::is_running(&server)
+			.map_err(|e| record("state", e))
+			.unwrap_or(false);
+
+	let mut world_size = 0;
+	let worlds = fs::world_dirs(&path)
+		.map_err(|e| record("worlds", e))
+		.unwrap_or_default();
+	for dir in worlds {
+		world_size += fs::dir_size(&dir)
+			.map_err(|e| record("world size", e))
+			.unwrap_or(0);
+	}
+
+	let last_started_at = manifest
+		.as_ref()
+		.and_then(|m| m.last_started_at)
+		.or_else(|| last_log_write(&path));
+
+	Entry {
+		name: server.name().to_string(),
+		server_type: manifest.as_ref().map(|m| m.server_type),
+		version: manifest.as_ref().map(|m| m.version.clone()),
+		port,
+		world_size,
+		jar,
+		last_started_at,
+		running,
+		problems,
+		path,
+	}
+}
+
+/// Covers servers started outside eagle, which never touch the manifest.
+fn last_log_write(server_dir: &Path) -> Option<u64> {
+	let modified = std::fs::metadata(server_dir.join("logs/latest.log"))
+		.and_then(|m| m.modified())
+		.ok()?;
+	modified
+		.duration_since(std::time::UNIX_EPOCH)
+		.ok()
+		.map(|d| d.as_secs())
+}
+
+/// Ascending for text columns, largest/newest first for size and started;
+/// ties fall back to the name.
+fn sort(entries: &mut [Entry], key: &str) {
+	entries.sort_by(|a, b| {
+		let by = match key {
+			"type" => {
+				let name = |e: &Entry| e.server_type.map(|t| t.as_str());
+				name(a).cmp(&name(b))
+			}
+			"version" => {
+				let v = |e: &Entry| e.version.as_deref().map(fill::version_key);
+				v(a).cmp(&v(b))
+			}
+			"port" => a.port.cmp(&b.port),
+			"size" => b.world_size.cmp(&a.world_size),
+			"started" => b.last_started_at.cmp(&a.last_started_at),
+			_ => std::cmp::Ordering::Equal,
+		};
+		by.then_with(|| a.name.cmp(&b.name))
+	});
+}
+
+fn print_table(entries: &[Entry]) {
+	let rows: Vec<[String; 8]> = entries
+		.iter()
+		.map(|e| {
+			let status = match (e.running, e.problems.is_empty()) {
+				(true, _) => "running".to_string(),
+				(false, true) => "stopped".to_string(),
+				(false, false) => format!("broken: {}", e.problems.join(", ")),
+			};
+			[
+				e.name.clone(),
+				e.server_type.map(|t| t.as_str()).unwrap_or("?").to_string(),
+				e.version.clone().unwrap_or_else(|| "?".to_string()),
+				e.port
+					.map(|p| p.to_string())
+					.unwrap_or_else(|| "-".to_string()),
+				net::format_bytes(e.world_size),
+				if e.jar { "yes" } else { "missing" }.to_string(),
+				e.last_started_at
+					.map(format_time)
+					.unwrap_or_else(|| "never".to_string()),
+				status,
+			]
+		})
+		.collect();
+
+	let header = [
+		"NAME", "TYPE", "VERSION", "PORT", "WORLD", "JAR", "STARTED", "STATUS",
+	];
+	let mut widths = header.map(str::len);
+	for row in &rows {
+		for (width, cell) in widths.iter_mut().zip(row) {
+			*width = (*width).max(cell.chars().count());
+		}
+	}
+
+	let line = |cells: Vec<&str>| {
+		let padded: Vec<String> = cells
+			.iter()
+			.zip(widths)
+			.map(|(cell, width)| format!("{cell:<width$}"))
+			.collect();
+		println!("{}", padded.join("  ").trim_end());
+	};
+	line(header.to_vec());
+	for row in &rows {
+		line(row.iter().map(String::as_str).collect());
+	}
+}
+
+/// Local `YYYY-MM-DD HH:MM`.
+fn format_time(unix: u64) -> String {
+	let Ok(t) = time::OffsetDateTime::from_unix_timestamp(unix as i64) else {
+		return "?".to_string();
+	};
+	let t = match time::UtcOffset::current_local_offset() {
+		Ok(offset) => t.to_offset(offset),
+		Err(_) => t,
+	};
+	format!(
+		"{:04}-{:02}-{:02} {:02}:{:02}",
+		t.year(),
+		t.month() as u8,
+		t.day(),
+		t.hour(),
+		t.minute()
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(name: &str, version: &str, size: u64) -> Entry {
+		Entry {
+			name: name.to_string(),
+			path: PathBuf::from(name),
+			server_type: Some(ServerType::Paper),
+			version: Some(version.to_string()),
+			port: None,
+			world_size: size,
+			jar: true,
+			last_started_at: None,
+			running: false,
+			problems: Vec::new(),
+		}
+	}
+
+	fn names(entries: &[Entry]) -> Vec<&str> {
+		entries.iter().map(|e| e.name.as_str()).collect()
+	}
+
+	#[test]
+	fn sorts_versions_numerically_and_sizes_largest_first() {
+		let mut list = [
+			entry("b", "1.21.10", 5),
+			entry("a", "1.21.4", 5),
+			entry("c", "1.20.1", 9),
+		];
+		sort(&mut list, "version");
+		assert_eq!(names(&list), ["c", "a", "b"]);
+		sort(&mut list, "size");
+		assert_eq!(names(&list), ["c", "a", "b"]);
+		sort(&mut list, "name");
+		assert_eq!(names(&list), ["a", "b", "c"]);
+	}
+
+	#[test]
+	fn unreadable_folders_are_listed_as_broken() {
+		let dir = fs::TestDir::new("list");
+		std::fs::create_dir_all(dir.join(PROPERTIES_FILE)).unwrap();
+		std::fs::write(dir.join("server.jar"), b"").unwrap();
+
+		let entry = inspect(dir.to_path_buf());
+		assert!(entry.jar && entry.port.is_none());
+		assert!(entry.problems.iter().any(|p| p.starts_with("cannot read")));
+	}
+}
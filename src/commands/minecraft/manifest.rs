@@ -0,0 +1,421 @@
+//! Per-server manifest (`eagle-server.json`).
+//!
+//! `create` writes one into every server folder so later commands know what
+//! the folder contains (type, resolved versions, port, RAM) instead of
+//! guessing from the files on disk.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::archive;
+
+pub(super) const MANIFEST_FILE: &str = "eagle-server.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum ServerType {
+	Paper,
+	Fabric,
+	Vanilla,
+	Folia,
+	Purpur,
+	Velocity,
+	Waterfall,
+	Quilt,
+	Forge,
+	#[serde(rename = "neoforge")]
+	NeoForge,
+}
+
+impl ServerType {
+	pub(super) const ALL: [Self; 10] = [
+		Self::Paper,
+		Self::Fabric,
+		Self::Vanilla,
+		Self::Folia,
+		Self::Purpur,
+		Self::Velocity,
+		Self::Waterfall,
+		Self::Quilt,
+		Self::Forge,
+		Self::NeoForge,
+	];
+
+	pub(super) fn as_str(self) -> &'static str {
+		match self {
+			Self::Paper => "paper",
+			Self::Fabric => "fabric",
+			Self::Vanilla => "vanilla",
+			Self::Folia => "folia",
+			Self::Purpur => "purpur",
+			Self::Velocity => "velocity",
+			Self::Waterfall => "waterfall",
+			Self::Quilt => "quilt",
+			Self::Forge => "forge",
+			Self::NeoForge => "neoforge",
+		}
+	}
+
+	/// Proxies have no world and use their own config instead of
+	/// `server.properties`.
+	pub(super) fn is_proxy(self) -> bool {
+		matches!(self, Self::Velocity | Self::Waterfall)
+	}
+
+	/// Whether `--build` pins something for this type.
+	pub(super) fn has_builds(self) -> bool {
+		matches!(
+			self,
+			Self::Paper
+				| Self::Folia
+				| Self::Purpur
+				| Self::Velocity
+				| Self::Waterfall
+		)
+	}
+
+	/// Whether `--loader` pins something for this type.
+	pub(super) fn has_loader(self) -> bool {
+		matches!(
+			self,
+			Self::Fabric | Self::Quilt | Self::Forge | Self::NeoForge
+		)
+	}
+
+	pub(super) fn parse(s: &str) -> anyhow::Result<Self> {
+		let needle = s.trim().to_lowercase();
+		Self::ALL
+			.into_iter()
+			.find(|t| t.as_str() == needle)
+			.ok_or_else(|| {
+				anyhow::anyhow!(
+					"Invalid type: {s} (expected: {})",
+					Self::names().join(" | ")
+				)
+			})
+	}
+
+	pub(super) fn names() -> Vec<&'static str> {
+		Self::ALL.iter().map(|t| t.as_str()).collect()
+	}
+}
+
+/// JVM flag set `start` uses.
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub(super) enum JvmProfile {
+	/// `aikar`, or `aikar-large-heap` above 12 GB.
+	#[default]
+	Auto,
+	Aikar,
+	AikarLargeHeap,
+	Zgc,
+	Plain,
+}
+
+impl JvmProfile {
+	pub(super) const ALL: [Self; 5] = [
+		Self::Auto,
+		Self::Aikar,
+		Self::AikarLargeHeap,
+		Self::Zgc,
+		Self::Plain,
+	];
+
+	pub(super) fn as_str(self) -> &'static str {
+		match self {
+			Self::Auto => "auto",
+			Self::Aikar => "aikar",
+			Self::AikarLargeHeap => "aikar-large-heap",
+			Self::Zgc => "zgc",
+			Self::Plain => "plain",
+		}
+	}
+
+	pub(super) fn parse(s: &str) -> anyhow::Result<Self> {
+		Self::ALL
+			.into_iter()
+			.find(|p| p.as_str() == s.trim())
+			.ok_or_else(|| anyhow::anyhow!("Invalid JVM profile: {s}"))
+	}
+
+	pub(super) fn names() -> Vec<&'static str> {
+		Self::ALL.iter().map(|p| p.as_str()).collect()
+	}
+}
+
+/// Defaults for `minecraft backup`; flags override each field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct BackupSettings {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) format: Option<archive::Format>,
+	/// Always keep the newest N backups.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) keep: Option<usize>,
+	/// Keep the newest backup of each of the last N days that have one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) keep_daily: Option<usize>,
+	/// Keep the newest backup of each of the last N ISO weeks.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) keep_weekly: Option<usize>,
+	/// Live backup interval for supervised starts.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) interval_minutes: Option<u64>,
+}
+
+impl BackupSettings {
+	fn is_empty(&self) -> bool {
+		*self == Self::default()
+	}
+}
+
+/// What eagle knows about a server folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct ServerManifest {
+	#[serde(rename = "type")]
+	pub(super) server_type: ServerType,
+	/// Resolved Minecraft version (never a family key like `1.21`); the
+	/// proxy version for Velocity/Waterfall.
+	pub(super) version: String,
+	/// Fill or Purpur build id.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) build: Option<u64>,
+	/// Loader version (Fabric, Quilt, Forge, NeoForge).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) loader: Option<String>,
+	/// Installer version (Fabric, Quilt).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) installer: Option<String>,
+	pub(super) port: u16,
+	#[serde(default)]
+	pub(super) motd: String,
+	/// Max heap (`-Xmx`).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) ram_mb: Option<u32>,
+	/// Initial heap (`-Xms`); defaults to `ram_mb`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) min_ram_mb: Option<u32>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) profile: Option<JvmProfile>,
+	/// Extra JVM flags appended after the profile.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub(super) jvm_args: Vec<String>,
+	/// Extra arguments passed to the server after `nogui`.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub(super) server_args: Vec<String>,
+	/// How `start` launches the server.
+	#[serde(default)]
+	pub(super) launch: Launch,
+	/// Java major the server needs (cached from Mojang's manifest).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) java_major: Option<u32>,
+	/// Explicit `java` executable, overriding runtime detection.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) java: Option<String>,
+	#[serde(default, skip_serializing_if = "BackupSettings::is_empty")]
+	pub(super) backup: BackupSettings,
+	/// Unix timestamp (seconds) of creation or adoption.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) created_at: Option<u64>,
+	/// Unix timestamp (seconds) of the last `minecraft start`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub(super) last_started_at: Option<u64>,
+}
+
+impl ServerManifest {
+	pub(super) fn new(
+		server_type: ServerType,
+		version: impl Into<String>,
+		port: u16,
+	) -> Self {
+		Self {
+			server_type,
+			version: version.into(),
+			build: None,
+			loader: None,
+			installer: None,
+			port,
+			motd: String::new(),
+			ram_mb: None,
+			min_ram_mb: None,
+			profile: None,
+			jvm_args: Vec::new(),
+			server_args: Vec::new(),
+			launch: Launch::default(),
+			java_major: None,
+			java: None,
+			backup: BackupSettings::default(),
+			created_at: Some(unix_now()),
+			last_started_at: None,
+		}
+	}
+
+	/// Short human label, e.g. `paper 1.21.4 #130`.
+	pub(super) fn describe(&self) -> String {
+		let mut s = format!("{} {}", self.server_type.as_str(), self.version);
+		if let Some(build) = self.build {
+			s.push_str(&format!(" #{build}"));
+		}
+		if let Some(loader) = &self.loader {
+			s.push_str(&format!(" (loader {loader})"));
+		}
+		s
+	}
+}
+
+/// Launch target, relative to the server folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(super) enum Launch {
+	/// `java -jar <jar>`.
+	Jar { jar: String },
+	/// `java @<args file>`, as used by Forge/NeoForge since 1.17.
+	ArgsFile { unix: String, windows: String },
+}
+
+impl Default for Launch {
+	fn default() -> Self {
+		Self::Jar {
+			jar: "server.jar".to_string(),
+		}
+	}
+}
+
+impl Launch {
+	/// The file that has to exist for the server to start.
+	pub(super) fn target(&self) -> &str {
+		match self {
+			Self::Jar { jar } => jar,
+			Self::ArgsFile { windows, .. } if cfg!(windows) => windows,
+			Self::ArgsFile { unix, .. } => unix,
+		}
+	}
+
+	/// Arguments that follow the JVM flags on the `java` command line.
+	pub(super) fn java_args(&self) -> Vec<String> {
+		match self {
+			Self::Jar { jar } => vec!["-jar".to_string(), jar.clone()],
+			Self::ArgsFile { .. } => vec![format!("@{}", self.target())],
+		}
+	}
+}
+
+/// Loads the manifest of `server_dir`, or `None` if the folder has none.
+pub(super) fn load(
+	server_dir: &Path,
+) -> anyhow::Result<Option<ServerManifest>> {
+	let path = server_dir.join(MANIFEST_FILE);
+	let bytes = match std::fs::read(&path) {
+		Ok(b) => b,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e.into()),
+	};
+
+	let manifest = serde_json::from_slice(&bytes).map_err(|e| {
+		anyhow::anyhow!("Invalid manifest {}: {e}", path.display())
+	})?;
+	Ok(Some(manifest))
+}
+
+/// Writes the manifest via a temp file + rename so a crash never leaves a
+/// half-written JSON behind.
+pub(super) fn save(
+	server_dir: &Path,
+	manifest: &ServerManifest,
+) -> anyhow::Result<()> {
+	let path = server_dir.join(MANIFEST_FILE);
+	let tmp = server_dir.join(format!("{MANIFEST_FILE}.tmp"));
+
+	let json = serde_json::to_string_pretty(manifest)?;
+	std::fs::write(&tmp, format!("{json}\n"))?;
+	std::fs::rename(&tmp, &path)?;
+	Ok(())
+}
+
+pub(super) fn unix_now() -> u64 {
+	std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn server_type_parse_roundtrip() {
+		for t in ServerType::ALL {
+			assert_eq!(ServerType::parse(t.as_str()).unwrap(), t);
+		}
+		assert_eq!(ServerType::parse(" Paper ").unwrap(), ServerType::Paper);
+		assert!(ServerType::parse("spigot").is_err());
+	}
+
+	#[test]
+	fn all_lists_every_server_type() {
+		// Exhaustive, so a new variant fails to compile until it is placed.
+		let index = |t: ServerType| match t {
+			ServerType::Paper => 0,
+			ServerType::Fabric => 1,
+			ServerType::Vanilla => 2,
+			ServerType::Folia => 3,
+			ServerType::Purpur => 4,
+			ServerType::Velocity => 5,
+			ServerType::Waterfall => 6,
+			ServerType::Quilt => 7,
+			ServerType::Forge => 8,
+			ServerType::NeoForge => 9,
+		};
+		for (i, t) in ServerType::ALL.into_iter().enumerate() {
+			assert_eq!(index(t), i);
+		}
+		assert_eq!(ServerType::ALL.len(), 10);
+	}
+
+	#[test]
+	fn manifest_json_shape() {
+		let mut m = ServerManifest::new(ServerType::Paper, "1.21.4", 25565);
+		m.build = Some(130);
+		m.created_at = None;
+
+		let json = serde_json::to_value(&m).unwrap();
+		assert_eq!(json["type"], "paper");
+		assert_eq!(json["build"], 130);
+		assert!(json.get("loader").is_none());
+
+		let back: ServerManifest = serde_json::from_value(json).unwrap();
+		assert_eq!(back, m);
+	}
+
+	#[test]
+	fn manifest_without_launch_defaults_to_server_jar() {
+		let m: ServerManifest = serde_json::from_str(
+			r#"{"type":"paper","version":"1.21.4","port":25565}"#,
+		)
+		.unwrap();
+		assert_eq!(m.launch.java_args(), ["-jar", "server.jar"]);
+	}
+
+	#[test]
+	fn args_file_launch_roundtrip() {
+		let launch = Launch::ArgsFile {
+			unix: "libraries/x/unix_args.txt".to_string(),
+			windows: "libraries/x/win_args.txt".to_string(),
+		};
+		let json = serde_json::to_value(&launch).unwrap();
+		assert_eq!(json["kind"], "args_file");
+		assert_eq!(serde_json::from_value::<Launch>(json).unwrap(), launch);
+		assert!(launch.java_args()[0].starts_with("@libraries/x/"));
+	}
+
+	#[test]
+	fn describe_includes_build_and_loader() {
+		let mut m = ServerManifest::new(ServerType::Fabric, "1.20.1", 1);
+		m.loader = Some("0.16.9".to_string());
+		assert_eq!(m.describe(), "fabric 1.20.1 (loader 0.16.9)");
+	}
+}
@@ -1,31 +1,110 @@
+use std::path::PathBuf;
+
 use clap::{Arg, ArgMatches, Command};
 
 use crate::commands::CommandSpec;
 use crate::context::Context;
 
+mod adopt;
+mod archive;
+mod backup;
+mod config;
+mod console;
 mod create;
 mod fabric;
+mod fill;
+mod forge;
 mod fs;
-mod paper;
+mod install;
+mod java;
+mod lifecycle;
+mod list;
+mod manifest;
+mod modrinth;
+mod mods;
+mod mrpack;
+mod players;
+mod ports;
+mod properties;
+mod purpur;
+mod query;
+mod quilt;
+mod rcon;
+mod restart;
 mod start;
+mod status;
+mod upgrade;
+mod vanilla;
+mod wizard;
+mod world;
 
 fn build() -> Command {
 	Command::new("minecraft")
-		.about("Minecraft server tools (start, create)")
+		.about("Minecraft server tools")
 		.alias("m")
 		.arg(
-			Arg::new("ram_mb")
-				.long("ram-mb")
-				.help("RAM in MB")
-				.value_parser(clap::value_parser!(u32))
-				.required(false),
+			Arg::new("root")
+				.long("root")
+				.help(
+					"Servers root to use instead of the configured ones (repeatable)",
+				)
+				.value_parser(clap::value_parser!(PathBuf))
+				.action(clap::ArgAction::Append)
+				.global(true),
 		)
+		.args(start::start_args())
+		.subcommand(start::build_command())
 		.subcommand(create::build_command())
+		.subcommand(list::build_command())
+		.subcommand(list::build_roots_command())
+		.subcommand(lifecycle::build_delete_command())
+		.subcommand(lifecycle::build_rename_command())
+		.subcommand(lifecycle::build_clone_command())
+		.subcommand(adopt::build_command())
+		.subcommand(upgrade::build_command())
+		.subcommand(java::build_command())
+		.subcommand(backup::build_command())
+		.subcommand(backup::build_restore_command())
+		.subcommand(rcon::build_command())
+		.subcommand(status::build_command())
+		.subcommand(query::build_command())
+		.subcommand(config::build_command())
+		.subcommand(mods::build_command())
+		.subcommand(players::build_command())
+		.subcommand(world::build_command())
+		.subcommand(ports::build_command())
 }
 
 fn run(matches: &ArgMatches, _: &Context) -> anyhow::Result<()> {
+	fs::set_root_flags(
+		matches
+			.get_many::<PathBuf>("root")
+			.into_iter()
+			.flatten()
+			.cloned()
+			.collect(),
+	);
 	match matches.subcommand() {
+		Some(("start", sub)) => start::run_start(sub),
 		Some(("create", sub)) => create::run_create(sub),
+		Some(("list", sub)) => list::run_list(sub),
+		Some(("roots", _)) => list::run_roots(),
+		Some(("delete", sub)) => lifecycle::run_delete(sub),
+		Some(("rename", sub)) => lifecycle::run_rename(sub),
+		Some(("clone", sub)) => lifecycle::run_clone(sub),
+		Some(("adopt", sub)) => adopt::run_adopt(sub),
+		Some(("upgrade", sub)) => upgrade::run_upgrade(sub),
+		Some(("java", sub)) => java::run_java(sub),
+		Some(("backup", sub)) => backup::run_backup(sub),
+		Some(("restore", sub)) => backup::run_restore(sub),
+		Some(("rcon", sub)) => rcon::run_rcon(sub),
+		Some(("status", sub)) => status::run_status(sub),
+		Some(("query", sub)) => query::run_query(sub),
+		Some(("config", sub)) => config::run_config(sub),
+		Some(("mods", sub)) => mods::run_mods(sub),
+		Some(("players", sub)) => players::run_players(sub),
+		Some(("world", sub)) => world::run_world(sub),
+		Some(("ports", sub)) => ports::run_ports(sub),
 		Some((other, _)) => anyhow::bail!("Unknown subcommand: {other}"),
 		None => start::run_start(matches),
 	}
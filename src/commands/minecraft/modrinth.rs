@@ -0,0 +1,217 @@
+//! Client for the Modrinth API (`api.modrinth.com/v2`): projects, versions
+//! filtered by loader and game version, and their files.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::manifest::ServerType;
+use crate::net;
+
+const API: &str = "https://api.modrinth.com/v2";
+
+/// Minimal shape of `GET /project/{id|slug}`.
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Project {
+	pub(super) id: String,
+	pub(super) slug: String,
+	pub(super) title: String,
+	pub(super) project_type: String,
+	/// `required`, `optional`, `unsupported` or `unknown`.
+	#[serde(default)]
+	pub(super) server_side: String,
+}
+
+/// Minimal shape of a version (`GET /version/{id}` and list entries).
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Version {
+	pub(super) id: String,
+	pub(super) project_id: String,
+	pub(super) version_number: String,
+	/// `release`, `beta` or `alpha`.
+	pub(super) version_type: String,
+	#[serde(default)]
+	pub(super) files: Vec<VersionFile>,
+	#[serde(default)]
+	pub(super) dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct VersionFile {
+	pub(super) url: String,
+	pub(super) filename: String,
+	#[serde(default)]
+	pub(super) primary: bool,
+	pub(super) size: u64,
+	pub(super) hashes: Hashes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Hashes {
+	pub(super) sha512: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Dependency {
+	pub(super) version_id: Option<String>,
+	pub(super) project_id: Option<String>,
+	/// `required`, `optional`, `incompatible` or `embedded`.
+	pub(super) dependency_type: String,
+}
+
+impl Version {
+	/// The file to install: the one marked primary, else the first.
+	pub(super) fn primary_file(&self) -> anyhow::Result<&VersionFile> {
+		self.files
+			.iter()
+			.find(|f| f.primary)
+			.or_else(|| self.files.first())
+			.ok_or_else(|| {
+				anyhow::anyhow!("Version {} has no files", self.version_number)
+			})
+	}
+}
+
+/// Where a server type loads add-ons from, and which Modrinth loaders fit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Target {
+	pub(super) folder: &'static str,
+	pub(super) loaders: &'static [&'static str],
+	/// `None` for proxies, whose plugins are tagged with Minecraft versions
+	/// rather than proxy versions.
+	pub(super) game_version: Option<String>,
+}
+
+impl Target {
+	pub(super) fn for_server(
+		server_type: ServerType,
+		version: &str,
+	) -> anyhow::Result<Self> {
+		let (folder, loaders): (_, &'static [&'static str]) = match server_type
+		{
+			ServerType::Fabric => ("mods", &["fabric"]),
+			ServerType::Quilt => ("mods", &["quilt", "fabric"]),
+			ServerType::Forge => ("mods", &["forge"]),
+			ServerType::NeoForge => ("mods", &["neoforge"]),
+			ServerType::Paper => ("plugins", &["paper", "spigot", "bukkit"]),
+			ServerType::Purpur => {
+				("plugins", &["purpur", "paper", "spigot", "bukkit"])
+			}
+			ServerType::Folia => ("plugins", &["folia"]),
+			ServerType::Velocity => ("plugins", &["velocity"]),
+			ServerType::Waterfall => ("plugins", &["waterfall", "bungeecord"]),
+			ServerType::Vanilla => {
+				anyhow::bail!("Vanilla servers cannot load mods or plugins")
+			}
+		};
+		Ok(Self {
+			folder,
+			loaders,
+			game_version: (!server_type.is_proxy())
+				.then(|| version.to_string()),
+		})
+	}
+
+	pub(super) fn dir(&self, server_dir: &Path) -> std::path::PathBuf {
+		server_dir.join(self.folder)
+	}
+}
+
+pub(super) fn project(id_or_slug: &str) -> anyhow::Result<Project> {
+	net::get_json(&format!("{API}/project/{}", encode(id_or_slug)))
+		.map_err(|e| anyhow::anyhow!("Modrinth project {id_or_slug}: {e}"))
+}
+
+pub(super) fn version(id: &str) -> anyhow::Result<Version> {
+	net::get_json(&format!("{API}/version/{}", encode(id)))
+}
+
+/// Versions of `project` that fit `target`, newest first.
+pub(super) fn versions(
+	project: &str,
+	target: &Target,
+) -> anyhow::Result<Vec<Version>> {
+	net::get_json(&versions_url(project, target))
+}
+
+fn versions_url(project: &str, target: &Target) -> String {
+	let loaders = serde_json::to_string(target.loaders).unwrap_or_default();
+	let mut url = format!(
+		"{API}/project/{}/version?loaders={}",
+		encode(project),
+		encode(&loaders)
+	);
+	if let Some(game_version) = &target.game_version {
+		let versions =
+			serde_json::to_string(&[game_version]).unwrap_or_default();
+		url.push_str(&format!("&game_versions={}", encode(&versions)));
+	}
+	url
+}
+
+/// Newest release, else the newest beta/alpha if nothing stable exists.
+pub(super) fn pick_version(versions: &[Version]) -> Option<&Version> {
+	versions
+		.iter()
+		.find(|v| v.version_type == "release")
+		.or_else(|| versions.first())
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters.
+fn encode(s: &str) -> String {
+	s.bytes()
+		.map(|b| match b {
+			b'A'..=b'Z'
+			| b'a'..=b'z'
+			| b'0'..=b'9'
+			| b'-'
+			| b'_'
+			| b'.'
+			| b'~' => (b as char).to_string(),
+			_ => format!("%{b:02X}"),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn version(id: &str, kind: &str) -> Version {
+		Version {
+			id: id.to_string(),
+			project_id: "P".to_string(),
+			version_number: id.to_string(),
+			version_type: kind.to_string(),
+			files: Vec::new(),
+			dependencies: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn filters_by_loader_and_game_version() {
+		let target = Target::for_server(ServerType::Quilt, "1.21.4").unwrap();
+		assert_eq!(
+			versions_url("fabric-api", &target),
+			"https://api.modrinth.com/v2/project/fabric-api/version\
+			 ?loaders=%5B%22quilt%22%2C%22fabric%22%5D\
+			 &game_versions=%5B%221.21.4%22%5D"
+		);
+
+		let proxy = Target::for_server(ServerType::Velocity, "3.4.0").unwrap();
+		assert!(!versions_url("luckperms", &proxy).contains("game_versions"));
+		assert!(Target::for_server(ServerType::Vanilla, "1.21.4").is_err());
+	}
+
+	#[test]
+	fn prefers_newest_release() {
+		let list = [
+			version("3-beta", "beta"),
+			version("2", "release"),
+			version("1", "release"),
+		];
+		assert_eq!(pick_version(&list).unwrap().id, "2");
+		assert_eq!(pick_version(&list[..1]).unwrap().id, "3-beta");
+		assert!(pick_version(&[]).is_none());
+	}
+}
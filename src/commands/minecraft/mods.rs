@@ -0,0 +1,725 @@
+//! `minecraft mods`: Modrinth mods and plugins per server, recorded in
+//! `eagle-mods.lock.json` so the exact set can be reinstalled with `sync`.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+
+use super::fs;
+use super::modrinth::{self, Project, Target, Version};
+use crate::net;
+
+pub(super) const LOCK_FILE: &str = "eagle-mods.lock.json";
+
+pub(super) fn build_command() -> Command {
+	let server = || {
+		Arg::new("server")
+			.help("Server folder name (prompts if omitted)")
+			.required(false)
+	};
+	let projects = |help: &'static str| {
+		Arg::new("projects")
+			.help(help)
+			.num_args(1..)
+			.required(false)
+	};
+
+	Command::new("mods")
+		.about("Install and update Modrinth mods/plugins")
+		.subcommand_required(true)
+		.subcommand(
+			Command::new("add")
+				.about("Install projects and their required dependencies")
+				.arg(server().required(true))
+				.arg(projects("Modrinth slugs or ids").required(true))
+				.arg(
+					Arg::new("version").long("version").help(
+						"Exact version number or id (single project only)",
+					),
+				),
+		)
+		.subcommand(
+			Command::new("remove")
+				.about("Uninstall projects and dependencies nothing else needs")
+				.arg(server().required(true))
+				.arg(projects("Slugs to remove").required(true))
+				.arg(
+					Arg::new("force")
+						.long("force")
+						.help("Remove even if another project requires it")
+						.action(clap::ArgAction::SetTrue),
+				),
+		)
+		.subcommand(
+			Command::new("list")
+				.about("Show installed projects")
+				.arg(server())
+				.arg(
+					Arg::new("json")
+						.long("json")
+						.help("Print the lockfile entries as JSON")
+						.action(clap::ArgAction::SetTrue),
+				),
+		)
+		.subcommand(
+			Command::new("update")
+				.about("Move projects to their newest compatible version")
+				.arg(server())
+				.arg(projects("Slugs to update (default: all)")),
+		)
+		.subcommand(
+			Command::new("sync")
+				.about("Download missing or modified files from the lockfile")
+				.arg(server()),
+		)
+}
+
+/// `eagle-mods.lock.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Lock {
+	/// Modrinth loaders the set was resolved for.
+	#[serde(default)]
+	loaders: Vec<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	game_version: Option<String>,
+	#[serde(default)]
+	mods: Vec<LockedMod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LockedMod {
+	slug: String,
+	title: String,
+	project_id: String,
+	version_id: String,
+	version_number: String,
+	/// Relative to the server folder, e.g. `mods/sodium-0.6.jar`.
+	file: String,
+	url: String,
+	sha512: String,
+	size: u64,
+	/// Installed only because another entry requires it.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	dependency: bool,
+	/// Project ids this entry requires.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	requires: Vec<String>,
+}
+
+impl LockedMod {
+	fn matches(&self, name: &str) -> bool {
+		self.slug.eq_ignore_ascii_case(name) || self.project_id == name
+	}
+}
+
+impl Lock {
+	fn load(server_dir: &Path) -> anyhow::Result<Self> {
+		let path = server_dir.join(LOCK_FILE);
+		match std::fs::read(&path) {
+			Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+				anyhow::anyhow!("Invalid lockfile {}: {e}", path.display())
+			}),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				Ok(Self::default())
+			}
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Same temp file + rename as the manifest.
+	fn save(&self, server_dir: &Path) -> anyhow::Result<()> {
+		let path = server_dir.join(LOCK_FILE);
+		let tmp = server_dir.join(format!("{LOCK_FILE}.tmp"));
+		let json = serde_json::to_string_pretty(self)?;
+		std::fs::write(&tmp, format!("{json}\n"))?;
+		std::fs::rename(&tmp, &path)?;
+		Ok(())
+	}
+
+	fn find(&self, name: &str) -> Option<&LockedMod> {
+		self.mods.iter().find(|m| m.matches(name))
+	}
+
+	/// Entries that require `project_id`.
+	fn dependents(&self, project_id: &str) -> Vec<&LockedMod> {
+		self.mods
+			.iter()
+			.filter(|m| m.requires.iter().any(|r| r == project_id))
+			.collect()
+	}
+
+	/// Drops dependency entries nothing requires any more; returns them.
+	fn orphans(&mut self) -> Vec<LockedMod> {
+		let mut removed = Vec::new();
+		loop {
+			let Some(index) = self.mods.iter().position(|m| {
+				m.dependency && self.dependents(&m.project_id).is_empty()
+			}) else {
+				return removed;
+			};
+			removed.push(self.mods.remove(index));
+		}
+	}
+}
+
+/// A server plus its lockfile while a command runs.
+struct Session {
+	name: String,
+	dir: PathBuf,
+	target: Target,
+	lock: Lock,
+}
+
+impl Session {
+	fn open(server: &fs::Server) -> anyhow::Result<Self> {
+		let manifest = server.manifest.as_ref().ok_or_else(|| {
+			anyhow::anyhow!(
+				"{} has no manifest (run `eagle minecraft adopt {}` first)",
+				server.name(),
+				server.name()
+			)
+		})?;
+		let target =
+			Target::for_server(manifest.server_type, &manifest.version)?;
+		Ok(Self {
+			name: server.name().to_string(),
+			dir: server.path.clone(),
+			target,
+			lock: Lock::load(&server.path)?,
+		})
+	}
+
+	fn save(&mut self) -> anyhow::Result<()> {
+		self.lock.loaders =
+			self.target.loaders.iter().map(|l| l.to_string()).collect();
+		self.lock.game_version = self.target.game_version.clone();
+		self.lock.save(&self.dir)
+	}
+
+	/// Saves after `add`, `remove` or `update`. When one of them failed, the
+	/// projects handled before it stay recorded, but the lockfile keeps its
+	/// old target since not everything was resolved for the new one.
+	fn finish(&mut self, result: anyhow::Result<()>) -> anyhow::Result<()> {
+		match result {
+			Ok(()) => self.save(),
+			Err(e) => {
+				self.lock.save(&self.dir)?;
+				Err(e)
+			}
+		}
+	}
+
+	fn describe_target(&self) -> String {
+		let loaders = self.target.loaders.join("/");
+		match &self.target.game_version {
+			Some(v) => format!("{loaders} {v}"),
+			None => loaders,
+		}
+	}
+
+	/// Newest compatible version of `project`.
+	fn latest(&self, project: &Project) -> anyhow::Result<Version> {
+		let versions = modrinth::versions(&project.id, &self.target)?;
+		modrinth::pick_version(&versions).cloned().ok_or_else(|| {
+			anyhow::anyhow!(
+				"No {} version for {}",
+				project.title,
+				self.describe_target()
+			)
+		})
+	}
+
+	/// Downloads `version`, replacing any older file of the same project,
+	/// then installs its required dependencies that are missing.
+	fn install(
+		&mut self,
+		project: &Project,
+		version: &Version,
+		dependency: bool,
+	) -> anyhow::Result<()> {
+		self.check_incompatible(project, version)?;
+
+		let file = version.primary_file()?;
+		let filename = plain_file_name(&file.filename)?;
+		let dir = self.target.dir(&self.dir);
+		std::fs::create_dir_all(&dir)?;
+		println!("Downloading {} {}", project.title, version.version_number);
+		net::download_verified(
+			&file.url,
+			&dir.join(filename),
+			Some(net::Checksum::Sha512(&file.hashes.sha512)),
+		)?;
+
+		let rel = format!("{}/{filename}", self.target.folder);
+		let requires: Vec<String> = version
+			.dependencies
+			.iter()
+			.filter(|d| d.dependency_type == "required")
+			.filter_map(|d| d.project_id.clone())
+			.collect();
+		let entry = LockedMod {
+			slug: project.slug.clone(),
+			title: project.title.clone(),
+			project_id: project.id.clone(),
+			version_id: version.id.clone(),
+			version_number: version.version_number.clone(),
+			file: rel.clone(),
+			url: file.url.clone(),
+			sha512: file.hashes.sha512.clone(),
+			size: file.size,
+			dependency,
+			requires,
+		};
+		match self
+			.lock
+			.mods
+			.iter_mut()
+			.find(|m| m.project_id == project.id)
+		{
+			Some(old) => {
+				if old.file != rel {
+					remove_file(&self.dir, &old.file)?;
+				}
+				*old = LockedMod {
+					dependency: old.dependency && dependency,
+					..entry
+				};
+			}
+			None => self.lock.mods.push(entry),
+		}
+
+		for dep in &version.dependencies {
+			if dep.dependency_type != "required" {
+				continue;
+			}
+			let installed = dep
+				.project_id
+				.as_deref()
+				.is_some_and(|id| self.lock.find(id).is_some());
+			if installed {
+				continue;
+			}
+			let (dep_project, dep_version) =
+				match (&dep.version_id, &dep.project_id) {
+					(Some(id), _) => {
+						let v = modrinth::version(id)?;
+						if self.lock.find(&v.project_id).is_some() {
+							continue;
+						}
+						(modrinth::project(&v.project_id)?, v)
+					}
+					(None, Some(id)) => {
+						let p = modrinth::project(id)?;
+						let v = self.latest(&p)?;
+						(p, v)
+					}
+					(None, None) => continue,
+				};
+			println!("  {} requires {}", project.title, dep_project.title);
+			self.install(&dep_project, &dep_version, true)?;
+		}
+		Ok(())
+	}
+
+	fn check_incompatible(
+		&self,
+		project: &Project,
+		version: &Version,
+	) -> anyhow::Result<()> {
+		for dep in &version.dependencies {
+			if dep.dependency_type != "incompatible" {
+				continue;
+			}
+			let clash =
+				dep.project_id.as_deref().and_then(|id| self.lock.find(id));
+			if let Some(clash) = clash {
+				anyhow::bail!(
+					"{} is incompatible with the installed {}",
+					project.title,
+					clash.title
+				);
+			}
+		}
+		Ok(())
+	}
+
+	fn restart_note(&self, server: &fs::Server) -> anyhow::Result<()> {
+		if fs::is_running(server)? {
+			println!(
+				"{} is running; restart it to load the changes.",
+				self.name
+			);
+		}
+		Ok(())
+	}
+}
+
+pub(super) fn run_mods(matches: &ArgMatches) -> anyhow::Result<()> {
+	let (action, sub) = matches.subcommand().expect("subcommand_required");
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		sub.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server",
+	)?;
+	let mut session = Session::open(&server)?;
+	// `list` and `sync` have no project arguments.
+	let names: Vec<&str> = sub
+		.try_get_many::<String>("projects")
+		.ok()
+		.flatten()
+		.map(|v| v.map(|s| s.as_str()).collect())
+		.unwrap_or_default();
+
+	match action {
+		"add" => {
+			let pinned = sub.get_one::<String>("version");
+			if pinned.is_some() && names.len() > 1 {
+				anyhow::bail!("--version needs a single project");
+			}
+			let result = names.iter().try_for_each(|name| {
+				add(&mut session, name, pinned.map(|s| s.as_str()))
+			});
+			session.finish(result)?;
+			session.restart_note(&server)?;
+		}
+		"remove" => {
+			let result = names.iter().try_for_each(|name| {
+				remove(&mut session, name, sub.get_flag("force"))
+			});
+			session.finish(result)?;
+			session.restart_note(&server)?;
+		}
+		"list" => list(&session, sub.get_flag("json"))?,
+		"update" => {
+			let result = update(&mut session, &names);
+			session.finish(result)?;
+			session.restart_note(&server)?;
+		}
+		"sync" => sync(&session)?,
+		other => anyhow::bail!("Unknown subcommand: {other}"),
+	}
+	Ok(())
+}
+
+fn add(
+	session: &mut Session,
+	name: &str,
+	pinned: Option<&str>,
+) -> anyhow::Result<()> {
+	let project = modrinth::project(name)?;
+	if !matches!(project.project_type.as_str(), "mod" | "plugin") {
+		anyhow::bail!(
+			"{} is a {}, not a mod or plugin",
+			project.title,
+			project.project_type
+		);
+	}
+	if project.server_side == "unsupported" {
+		anyhow::bail!("{} is client-side only", project.title);
+	}
+	if let Some(existing) = session
+		.lock
+		.mods
+		.iter_mut()
+		.find(|m| m.project_id == project.id)
+		&& pinned.is_none()
+	{
+		if existing.dependency {
+			existing.dependency = false;
+			println!("{} is now installed explicitly", existing.title);
+		} else {
+			println!(
+				"{} {} is already installed (use `mods update`)",
+				existing.title, existing.version_number
+			);
+		}
+		return Ok(());
+	}
+
+	let version = match pinned {
+		Some(pin) => modrinth::versions(&project.id, &session.target)?
+			.into_iter()
+			.find(|v| v.version_number == pin || v.id == pin)
+			.ok_or_else(|| {
+				anyhow::anyhow!(
+					"{} has no version {pin} for {}",
+					project.title,
+					session.describe_target()
+				)
+			})?,
+		None => session.latest(&project)?,
+	};
+	session.install(&project, &version, false)?;
+	println!("Added {} {}", project.title, version.version_number);
+	Ok(())
+}
+
+fn remove(
+	session: &mut Session,
+	name: &str,
+	force: bool,
+) -> anyhow::Result<()> {
+	let entry = session
+		.lock
+		.find(name)
+		.cloned()
+		.ok_or_else(|| anyhow::anyhow!("{name} is not installed"))?;
+
+	let dependents: Vec<String> = session
+		.lock
+		.dependents(&entry.project_id)
+		.iter()
+		.map(|m| m.title.clone())
+		.collect();
+	if !dependents.is_empty() && !force {
+		anyhow::bail!(
+			"{} is required by {} (use --force)",
+			entry.title,
+			dependents.join(", ")
+		);
+	}
+
+	session
+		.lock
+		.mods
+		.retain(|m| m.project_id != entry.project_id);
+	remove_file(&session.dir, &entry.file)?;
+	println!("Removed {}", entry.title);
+
+	for orphan in session.lock.orphans() {
+		remove_file(&session.dir, &orphan.file)?;
+		println!("Removed {} (no longer required)", orphan.title);
+	}
+	Ok(())
+}
+
+fn list(session: &Session, json: bool) -> anyhow::Result<()> {
+	if json {
+		println!("{}", serde_json::to_string_pretty(&session.lock.mods)?);
+		return Ok(());
+	}
+
+	if session.lock.mods.is_empty() {
+		println!("No Modrinth projects installed in {}", session.name);
+	}
+	let width = session.lock.mods.iter().map(|m| m.slug.len()).max();
+	for entry in &session.lock.mods {
+		let missing = !session.dir.join(&entry.file).exists();
+		println!(
+			"{:<w$}  {:<16}  {}{}{}",
+			entry.slug,
+			entry.version_number,
+			entry.file,
+			if entry.dependency {
+				"  (dependency)"
+			} else {
+				""
+			},
+			if missing {
+				"  (missing; run `mods sync`)"
+			} else {
+				""
+			},
+			w = width.unwrap_or(0)
+		);
+	}
+
+	let untracked = untracked_jars(session)?;
+	if !untracked.is_empty() {
+		println!();
+		println!("Not managed by eagle:");
+		for file in untracked {
+			println!("  {file}");
+		}
+	}
+	Ok(())
+}
+
+fn update(session: &mut Session, names: &[&str]) -> anyhow::Result<()> {
+	for name in names {
+		if session.lock.find(name).is_none() {
+			anyhow::bail!("{name} is not installed");
+		}
+	}
+
+	let entries: Vec<LockedMod> = session
+		.lock
+		.mods
+		.iter()
+		.filter(|m| names.is_empty() || names.iter().any(|n| m.matches(n)))
+		.cloned()
+		.collect();
+	let mut updated = 0;
+	for entry in entries {
+		let project = modrinth::project(&entry.project_id)?;
+		let latest = session.latest(&project)?;
+		if latest.id == entry.version_id {
+			continue;
+		}
+		session.install(&project, &latest, entry.dependency)?;
+		println!(
+			"Updated {}: {} -> {}",
+			entry.title, entry.version_number, latest.version_number
+		);
+		updated += 1;
+	}
+	if updated == 0 {
+		println!("Everything is up to date for {}", session.describe_target());
+	}
+	Ok(())
+}
+
+fn sync(session: &Session) -> anyhow::Result<()> {
+	if session.lock.game_version.is_some()
+		&& session.lock.game_version != session.target.game_version
+	{
+		println!(
+			"Note: the lockfile was resolved for {}; run `mods update` to \
+			 move to {}",
+			session.lock.game_version.as_deref().unwrap_or_default(),
+			session.describe_target()
+		);
+	}
+
+	let mut fetched = 0;
+	for entry in &session.lock.mods {
+		let path = locked_path(&session.dir, &entry.file)?;
+		let checksum = net::Checksum::Sha512(&entry.sha512);
+		if path.exists() && net::verify_file(&path, checksum).is_ok() {
+			continue;
+		}
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		println!("Downloading {} {}", entry.title, entry.version_number);
+		net::download_verified(&entry.url, &path, Some(checksum))?;
+		fetched += 1;
+	}
+	println!(
+		"{} of {} files downloaded",
+		fetched,
+		session.lock.mods.len()
+	);
+	Ok(())
+}
+
+/// Jars in the mods/plugins folder that no lockfile entry owns.
+fn untracked_jars(session: &Session) -> anyhow::Result<Vec<String>> {
+	let dir = session.target.dir(&session.dir);
+	let entries = match std::fs::read_dir(&dir) {
+		Ok(e) => e,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+			return Ok(Vec::new());
+		}
+		Err(e) => return Err(e.into()),
+	};
+
+	let mut out = Vec::new();
+	for entry in entries {
+		let name = entry?.file_name().to_string_lossy().into_owned();
+		let rel = format!("{}/{name}", session.target.folder);
+		if name.ends_with(".jar")
+			&& !session.lock.mods.iter().any(|m| m.file == rel)
+		{
+			out.push(rel);
+		}
+	}
+	out.sort();
+	Ok(out)
+}
+
+/// `rel` from the lockfile under `server_dir`; a shared lockfile must not
+/// reach outside the server folder.
+fn locked_path(server_dir: &Path, rel: &str) -> anyhow::Result<PathBuf> {
+	let rel = fs::safe_path(rel)
+		.ok_or_else(|| anyhow::anyhow!("Unsafe path in lockfile: {rel}"))?;
+	Ok(server_dir.join(rel))
+}
+
+/// A Modrinth file name, which must not point into another folder.
+fn plain_file_name(name: &str) -> anyhow::Result<&str> {
+	if name.contains(['/', '\\']) || fs::safe_path(name).is_none() {
+		anyhow::bail!("Unsafe file name from Modrinth: {name}");
+	}
+	Ok(name)
+}
+
+fn remove_file(server_dir: &Path, rel: &str) -> anyhow::Result<()> {
+	match std::fs::remove_file(locked_path(server_dir, rel)?) {
+		Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+		_ => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(id: &str, dependency: bool, requires: &[&str]) -> LockedMod {
+		LockedMod {
+			slug: id.to_lowercase(),
+			title: id.to_string(),
+			project_id: id.to_string(),
+			version_id: format!("{id}-v"),
+			version_number: "1.0".to_string(),
+			file: format!("mods/{id}.jar"),
+			url: String::new(),
+			sha512: String::new(),
+			size: 0,
+			dependency,
+			requires: requires.iter().map(|r| r.to_string()).collect(),
+		}
+	}
+
+	#[test]
+	fn prunes_dependencies_nothing_needs() {
+		let mut lock = Lock {
+			mods: vec![
+				entry("A", false, &["API"]),
+				entry("API", true, &["LIB"]),
+				entry("LIB", true, &[]),
+				entry("B", false, &["SHARED"]),
+				entry("SHARED", true, &[]),
+			],
+			..Lock::default()
+		};
+		assert!(lock.orphans().is_empty());
+
+		lock.mods.retain(|m| m.project_id != "A");
+		let removed: Vec<String> =
+			lock.orphans().into_iter().map(|m| m.project_id).collect();
+		assert_eq!(removed, ["API", "LIB"]);
+		assert_eq!(lock.mods.len(), 2);
+		assert!(lock.find("shared").is_some());
+	}
+
+	#[test]
+	fn lockfile_omits_defaults() {
+		let json = serde_json::to_string(&entry("A", false, &[])).unwrap();
+		assert!(!json.contains("dependency") && !json.contains("requires"));
+		let back: LockedMod = serde_json::from_str(&json).unwrap();
+		assert_eq!(back, entry("A", false, &[]));
+	}
+
+	#[test]
+	fn lockfile_and_modrinth_paths_stay_in_the_server() {
+		let dir = Path::new("srv");
+		assert_eq!(
+			locked_path(dir, "mods/a.jar").unwrap(),
+			dir.join("mods/a.jar")
+		);
+		assert!(locked_path(dir, "../../.bashrc").is_err());
+		assert!(locked_path(dir, "/etc/passwd").is_err());
+
+		assert_eq!(
+			plain_file_name("sodium-0.6.jar").unwrap(),
+			"sodium-0.6.jar"
+		);
+		assert!(plain_file_name("../a.jar").is_err());
+		assert!(plain_file_name("mods/a.jar").is_err());
+		assert!(plain_file_name("..\\a.jar").is_err());
+		assert!(plain_file_name("..").is_err());
+	}
+}
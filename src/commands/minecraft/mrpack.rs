@@ -0,0 +1,350 @@
+//! Modrinth modpacks (`.mrpack`): a zip with `modrinth.index.json`, the files
+//! to download, and `overrides/` / `server-overrides/` folders to copy over.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use dialoguer::Confirm;
+use serde::Deserialize;
+
+use super::fs;
+use super::manifest::ServerType;
+use crate::net;
+
+const INDEX_FILE: &str = "modrinth.index.json";
+/// Applied in this order, so server-specific files win.
+const OVERRIDE_DIRS: [&str; 2] = ["overrides", "server-overrides"];
+/// Hosts the `.mrpack` format allows downloads from.
+const ALLOWED_HOSTS: [&str; 4] = [
+	"cdn.modrinth.com",
+	"github.com",
+	"raw.githubusercontent.com",
+	"gitlab.com",
+];
+
+/// Minimal shape of `modrinth.index.json` (format version 1).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Index {
+	format_version: u32,
+	game: String,
+	pub(super) version_id: String,
+	pub(super) name: String,
+	#[serde(default)]
+	files: Vec<PackFile>,
+	dependencies: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackFile {
+	path: String,
+	hashes: PackHashes,
+	env: Option<PackEnv>,
+	downloads: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackHashes {
+	sha1: String,
+	sha512: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PackEnv {
+	/// `required`, `optional` or `unsupported`.
+	server: String,
+}
+
+/// What the pack runs on, from its `dependencies`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PackTarget {
+	pub(super) server_type: ServerType,
+	pub(super) minecraft: String,
+	/// Exact loader version, pinned when installing.
+	pub(super) loader: Option<String>,
+}
+
+/// A pack read from disk.
+#[derive(Debug, Clone)]
+pub(super) struct Pack {
+	pub(super) path: PathBuf,
+	pub(super) index: Index,
+	pub(super) target: PackTarget,
+}
+
+impl Pack {
+	pub(super) fn open(path: &Path) -> anyhow::Result<Self> {
+		let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)
+			.map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+		let mut json = Vec::new();
+		zip.by_name(INDEX_FILE)
+			.map_err(|_| {
+				anyhow::anyhow!("{} has no {INDEX_FILE}", path.display())
+			})?
+			.read_to_end(&mut json)?;
+
+		let index: Index = serde_json::from_slice(&json)
+			.map_err(|e| anyhow::anyhow!("Invalid {INDEX_FILE}: {e}"))?;
+		if index.format_version != 1 || index.game != "minecraft" {
+			anyhow::bail!(
+				"Unsupported modpack (format {}, game {})",
+				index.format_version,
+				index.game
+			);
+		}
+		let target = pack_target(&index.dependencies)?;
+		Ok(Self {
+			path: path.to_path_buf(),
+			index,
+			target,
+		})
+	}
+
+	/// Folder name derived from the pack name.
+	pub(super) fn folder_name(&self) -> String {
+		folder_name(&self.index.name)
+	}
+
+	/// Files the server needs (anything not marked client-only).
+	fn server_files(&self) -> impl Iterator<Item = &PackFile> {
+		self.index.files.iter().filter(|f| {
+			f.env.as_ref().is_none_or(|e| e.server != "unsupported")
+		})
+	}
+
+	/// Downloads the server files into `server_dir`, then copies the
+	/// override folders. Returns how many files were downloaded.
+	pub(super) fn apply(
+		&self,
+		server_dir: &Path,
+		download: bool,
+	) -> anyhow::Result<usize> {
+		let mut downloaded = 0;
+		if download {
+			let other_hosts = self.confirm_other_hosts()?;
+			for file in self.server_files() {
+				let rel = fs::safe_path(&file.path).ok_or_else(|| {
+					anyhow::anyhow!("Unsafe path in modpack: {}", file.path)
+				})?;
+				let dest = server_dir.join(rel);
+				if let Some(parent) = dest.parent() {
+					std::fs::create_dir_all(parent)?;
+				}
+				download_any(file, &dest, other_hosts)?;
+				downloaded += 1;
+			}
+		}
+		self.copy_overrides(server_dir)?;
+		Ok(downloaded)
+	}
+
+	/// Asks once before touching hosts outside [`ALLOWED_HOSTS`]; `false`
+	/// means those URLs are skipped.
+	fn confirm_other_hosts(&self) -> anyhow::Result<bool> {
+		let mut hosts: Vec<&str> = self
+			.server_files()
+			.flat_map(|f| &f.downloads)
+			.filter(|url| !allowed_url(url))
+			.map(|url| url_host(url).unwrap_or(url))
+			.collect();
+		if hosts.is_empty() {
+			return Ok(true);
+		}
+		hosts.sort_unstable();
+		hosts.dedup();
+		Ok(Confirm::new()
+			.with_prompt(format!(
+				"The modpack downloads from hosts Modrinth does not allow: {}. \
+				 Continue?",
+				hosts.join(", ")
+			))
+			.default(false)
+			.interact()?)
+	}
+
+	fn copy_overrides(&self, server_dir: &Path) -> anyhow::Result<()> {
+		let mut zip = zip::ZipArchive::new(std::fs::File::open(&self.path)?)?;
+		for dir in OVERRIDE_DIRS {
+			for i in 0..zip.len() {
+				let mut entry = zip.by_index(i)?;
+				let Some(name) = entry.enclosed_name() else {
+					continue;
+				};
+				let Ok(rel) = name.strip_prefix(dir) else {
+					continue;
+				};
+				if rel.as_os_str().is_empty() {
+					continue;
+				}
+				let dest = server_dir.join(rel);
+				if entry.is_dir() {
+					std::fs::create_dir_all(&dest)?;
+					continue;
+				}
+				if let Some(parent) = dest.parent() {
+					std::fs::create_dir_all(parent)?;
+				}
+				std::io::copy(&mut entry, &mut std::fs::File::create(&dest)?)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Maps `dependencies` (`minecraft`, `fabric-loader`, ...) to a server type.
+fn pack_target(deps: &HashMap<String, String>) -> anyhow::Result<PackTarget> {
+	let minecraft = deps
+		.get("minecraft")
+		.ok_or_else(|| {
+			anyhow::anyhow!("Modpack does not name a Minecraft version")
+		})?
+		.clone();
+
+	let loaders: Vec<(ServerType, &String)> = [
+		("fabric-loader", ServerType::Fabric),
+		("quilt-loader", ServerType::Quilt),
+		("forge", ServerType::Forge),
+		("neoforge", ServerType::NeoForge),
+	]
+	.into_iter()
+	.filter_map(|(key, t)| deps.get(key).map(|v| (t, v)))
+	.collect();
+
+	let (server_type, loader) = match loaders.as_slice() {
+		[] => (ServerType::Vanilla, None),
+		[(t, v)] => (*t, Some(v.to_string())),
+		_ => anyhow::bail!("Modpack names more than one mod loader"),
+	};
+	Ok(PackTarget {
+		server_type,
+		minecraft,
+		loader,
+	})
+}
+
+/// Tries each mirror in order; the last error wins. URLs outside
+/// [`ALLOWED_HOSTS`] are only tried with `other_hosts`.
+fn download_any(
+	file: &PackFile,
+	dest: &Path,
+	other_hosts: bool,
+) -> anyhow::Result<()> {
+	let checksum = match &file.hashes.sha512 {
+		Some(sha512) => net::Checksum::Sha512(sha512),
+		None => net::Checksum::Sha1(&file.hashes.sha1),
+	};
+	let mut last = anyhow::anyhow!("No download URL for {}", file.path);
+	for url in &file.downloads {
+		if !other_hosts && !allowed_url(url) {
+			last = anyhow::anyhow!(
+				"Refusing to download {} from {url}",
+				file.path
+			);
+			continue;
+		}
+		println!("Downloading {}", file.path);
+		match net::download_verified(url, dest, Some(checksum)) {
+			Ok(()) => return Ok(()),
+			Err(e) => last = e,
+		}
+	}
+	Err(last)
+}
+
+/// Host of an `https://` URL, without port or user info.
+fn url_host(url: &str) -> Option<&str> {
+	let rest = url.strip_prefix("https://")?;
+	let authority = rest.split(['/', '?', '#']).next()?;
+	let host = authority.rsplit('@').next()?;
+	Some(host.split(':').next()?).filter(|h| !h.is_empty())
+}
+
+fn allowed_url(url: &str) -> bool {
+	url_host(url).is_some_and(|host| {
+		ALLOWED_HOSTS.iter().any(|h| host.eq_ignore_ascii_case(h))
+	})
+}
+
+/// Replaces characters Windows refuses and collapses runs of dots, falling
+/// back to "modpack" when nothing but dots would be left.
+fn folder_name(pack_name: &str) -> String {
+	let name: String = pack_name
+		.chars()
+		.map(|c| match c {
+			'<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '-',
+			c => c,
+		})
+		.collect();
+	let name = name
+		.split('.')
+		.filter(|part| !part.is_empty())
+		.collect::<Vec<_>>()
+		.join(".");
+	let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+	if name.is_empty() {
+		"modpack".to_string()
+	} else {
+		name.to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn deps(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+		pairs
+			.iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn picks_loader_from_dependencies() {
+		let target = pack_target(&deps(&[
+			("minecraft", "1.20.1"),
+			("fabric-loader", "0.15.11"),
+		]))
+		.unwrap();
+		assert_eq!(
+			target,
+			PackTarget {
+				server_type: ServerType::Fabric,
+				minecraft: "1.20.1".to_string(),
+				loader: Some("0.15.11".to_string()),
+			}
+		);
+
+		let vanilla = pack_target(&deps(&[("minecraft", "1.21")])).unwrap();
+		assert_eq!(vanilla.server_type, ServerType::Vanilla);
+		assert!(pack_target(&deps(&[("forge", "47.2.0")])).is_err());
+		assert!(
+			pack_target(&deps(&[
+				("minecraft", "1.20.1"),
+				("forge", "47.2.0"),
+				("fabric-loader", "0.15.11"),
+			]))
+			.is_err()
+		);
+	}
+
+	#[test]
+	fn folder_name_never_names_the_root() {
+		assert_eq!(folder_name(" My Pack: 1.2 "), "My Pack- 1.2");
+		assert_eq!(folder_name("a...b"), "a.b");
+		assert_eq!(folder_name("..."), "modpack");
+		assert_eq!(folder_name(". ."), "modpack");
+	}
+
+	#[test]
+	fn only_allowlisted_https_hosts_pass() {
+		assert!(allowed_url("https://cdn.modrinth.com/data/AANobbMI/x.jar"));
+		assert!(allowed_url("https://GitHub.com/a/b/releases/x.jar"));
+		assert!(!allowed_url("http://cdn.modrinth.com/x.jar"));
+		assert!(!allowed_url("https://cdn.modrinth.com.evil.net/x.jar"));
+		assert!(!allowed_url("https://cdn.modrinth.com@evil.net/x.jar"));
+		assert_eq!(url_host("https://evil.net:8443/x"), Some("evil.net"));
+	}
+}
@@ -0,0 +1,433 @@
+//! `minecraft players`: `whitelist.json` and `ops.json`. Names are resolved
+//! to UUIDs through Mojang (or as offline-mode UUIDs) while the server is
+//! stopped; a running server is told over RCON so its copy stays current.
+
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, Command};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+use super::fs;
+use super::rcon;
+use crate::net;
+
+const PROFILE_API: &str = "https://api.mojang.com/users/profiles/minecraft";
+/// What `op` grants without `--level` (vanilla `op-permission-level`).
+const DEFAULT_OP_LEVEL: u8 = 4;
+
+/// One of the two player lists the server keeps as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum List {
+	Whitelist,
+	Ops,
+}
+
+impl List {
+	fn file(self) -> &'static str {
+		match self {
+			Self::Whitelist => "whitelist.json",
+			Self::Ops => "ops.json",
+		}
+	}
+
+	fn rcon_add(self, name: &str) -> String {
+		match self {
+			Self::Whitelist => format!("whitelist add {name}"),
+			Self::Ops => format!("op {name}"),
+		}
+	}
+
+	fn rcon_remove(self, name: &str) -> String {
+		match self {
+			Self::Whitelist => format!("whitelist remove {name}"),
+			Self::Ops => format!("deop {name}"),
+		}
+	}
+}
+
+/// An entry of `whitelist.json` or `ops.json`; ops carry the extra fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+	uuid: String,
+	name: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	level: Option<u8>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	bypasses_player_limit: Option<bool>,
+}
+
+/// Minimal shape of Mojang's profile lookup.
+#[derive(Debug, Deserialize)]
+struct Profile {
+	id: String,
+	name: String,
+}
+
+pub(super) fn build_command() -> Command {
+	let names = || {
+		Arg::new("names")
+			.help("Player names")
+			.num_args(1..)
+			.required(true)
+	};
+	let json = || {
+		Arg::new("json")
+			.long("json")
+			.help("Print the list as JSON")
+			.action(clap::ArgAction::SetTrue)
+	};
+	let uuid_args = [
+		Arg::new("online")
+			.long("online")
+			.help("Look up Mojang UUIDs even if online-mode=false (proxies)")
+			.action(clap::ArgAction::SetTrue)
+			.conflicts_with("offline"),
+		Arg::new("offline")
+			.long("offline")
+			.help("Use offline-mode UUIDs even if online-mode=true")
+			.action(clap::ArgAction::SetTrue),
+	];
+
+	Command::new("players")
+		.about("Manage the whitelist and operators")
+		.arg(Arg::new("server").help("Server folder name").required(true))
+		.subcommand_required(true)
+		.subcommand(
+			Command::new("whitelist")
+				.about("Players allowed to join when white-list=true")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("add")
+						.about("Add players")
+						.arg(names())
+						.args(uuid_args.clone()),
+				)
+				.subcommand(
+					Command::new("remove").about("Remove players").arg(names()),
+				)
+				.subcommand(
+					Command::new("list").about("Show players").arg(json()),
+				),
+		)
+		.subcommand(
+			Command::new("ops")
+				.about("Operators and their permission level")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("add")
+						.about("Make players operators")
+						.arg(names())
+						.arg(
+							Arg::new("level")
+								.long("level")
+								.help("Permission level 1-4 (default: 4)")
+								.value_parser(
+									clap::value_parser!(u8).range(1..=4),
+								),
+						)
+						.arg(
+							Arg::new("bypass_limit")
+								.long("bypass-limit")
+								.help("Let them join when the server is full")
+								.action(clap::ArgAction::SetTrue),
+						)
+						.args(uuid_args),
+				)
+				.subcommand(
+					Command::new("remove")
+						.about("Remove operators")
+						.arg(names()),
+				)
+				.subcommand(
+					Command::new("list").about("Show operators").arg(json()),
+				),
+		)
+}
+
+pub(super) fn run_players(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"",
+	)?;
+	if server
+		.manifest
+		.as_ref()
+		.is_some_and(|m| m.server_type.is_proxy())
+	{
+		anyhow::bail!(
+			"{} is a proxy; whitelists and ops live on the backend servers",
+			server.name()
+		);
+	}
+
+	let (list, sub) = match matches.subcommand() {
+		Some(("whitelist", sub)) => (List::Whitelist, sub),
+		Some(("ops", sub)) => (List::Ops, sub),
+		_ => unreachable!("subcommand_required"),
+	};
+	let names = |m: &ArgMatches| -> anyhow::Result<Vec<String>> {
+		let names: Vec<String> = m
+			.get_many::<String>("names")
+			.into_iter()
+			.flatten()
+			.cloned()
+			.collect();
+		for name in &names {
+			validate_name(name)?;
+		}
+		Ok(names)
+	};
+
+	match sub.subcommand() {
+		Some(("add", m)) => add(&server, list, &names(m)?, m),
+		Some(("remove", m)) => remove(&server, list, &names(m)?),
+		Some(("list", m)) => print_list(&server, list, m.get_flag("json")),
+		_ => unreachable!("subcommand_required"),
+	}
+}
+
+fn add(
+	server: &fs::Server,
+	list: List,
+	names: &[String],
+	matches: &ArgMatches,
+) -> anyhow::Result<()> {
+	let level = matches.try_get_one::<u8>("level").ok().flatten().copied();
+	let bypass = matches.try_get_one::<bool>("bypass_limit").ok().flatten();
+	let bypass = bypass.copied().unwrap_or(false);
+
+	if fs::is_running(server)? {
+		if level.is_some_and(|l| l != DEFAULT_OP_LEVEL) || bypass {
+			anyhow::bail!(
+				"{} is running; `op` over RCON cannot set --level or --bypass-limit. Stop it first.",
+				server.name()
+			);
+		}
+		let commands: Vec<String> =
+			names.iter().map(|n| list.rcon_add(n)).collect();
+		return send(server, &commands);
+	}
+
+	let props = fs::read_properties(&server.path)?;
+	let online = match (matches.get_flag("online"), matches.get_flag("offline"))
+	{
+		(true, _) => true,
+		(_, true) => false,
+		_ => props.get("online-mode").map(String::as_str) != Some("false"),
+	};
+
+	let mut entries = load(&server.path, list)?;
+	for name in names {
+		let (uuid, name) = match online {
+			true => lookup(name)?,
+			false => (offline_uuid(name), name.clone()),
+		};
+		let entry = Entry {
+			uuid,
+			name,
+			level: (list == List::Ops)
+				.then_some(level.unwrap_or(DEFAULT_OP_LEVEL)),
+			bypasses_player_limit: (list == List::Ops).then_some(bypass),
+		};
+		println!("Added {} ({})", entry.name, entry.uuid);
+		upsert(&mut entries, entry);
+	}
+	save(&server.path, list, &entries)?;
+
+	if list == List::Whitelist
+		&& props.get("white-list").map(String::as_str) != Some("true")
+	{
+		println!(
+			"Note: the whitelist is off; turn it on with `eagle minecraft config {} set white-list true`",
+			server.name()
+		);
+	}
+	Ok(())
+}
+
+fn remove(
+	server: &fs::Server,
+	list: List,
+	names: &[String],
+) -> anyhow::Result<()> {
+	if fs::is_running(server)? {
+		let commands: Vec<String> =
+			names.iter().map(|n| list.rcon_remove(n)).collect();
+		return send(server, &commands);
+	}
+
+	let mut entries = load(&server.path, list)?;
+	for name in names {
+		match take(&mut entries, name) {
+			Some(entry) => println!("Removed {}", entry.name),
+			None => println!("{name} is not in {}", list.file()),
+		}
+	}
+	save(&server.path, list, &entries)
+}
+
+fn print_list(
+	server: &fs::Server,
+	list: List,
+	json: bool,
+) -> anyhow::Result<()> {
+	let entries = load(&server.path, list)?;
+	if json {
+		println!("{}", serde_json::to_string_pretty(&entries)?);
+		return Ok(());
+	}
+	if entries.is_empty() {
+		println!("{} is empty", list.file());
+		return Ok(());
+	}
+	for e in &entries {
+		match e.level {
+			Some(level) => {
+				println!("{:<16}  {}  level {level}", e.name, e.uuid)
+			}
+			None => println!("{:<16}  {}", e.name, e.uuid),
+		}
+	}
+	Ok(())
+}
+
+/// Runs `commands` over RCON, for a server that is up.
+fn send(server: &fs::Server, commands: &[String]) -> anyhow::Result<()> {
+	let config = rcon::Config::from_properties(server).map_err(|e| {
+		anyhow::anyhow!("{} is running and RCON is needed: {e}", server.name())
+	})?;
+	let mut client =
+		rcon::Client::connect("127.0.0.1", config.port, &config.password)?;
+	for command in commands {
+		let reply = rcon::strip_formatting(&client.command(command)?);
+		println!("{}", reply.trim_end());
+	}
+	Ok(())
+}
+
+fn load(server_dir: &Path, list: List) -> anyhow::Result<Vec<Entry>> {
+	let path = server_dir.join(list.file());
+	match std::fs::read_to_string(&path) {
+		Ok(s) if s.trim().is_empty() => Ok(Vec::new()),
+		Ok(s) => serde_json::from_str(&s)
+			.map_err(|e| anyhow::anyhow!("Invalid {}: {e}", path.display())),
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+		Err(e) => Err(e.into()),
+	}
+}
+
+fn save(
+	server_dir: &Path,
+	list: List,
+	entries: &[Entry],
+) -> anyhow::Result<()> {
+	let json = serde_json::to_string_pretty(entries)?;
+	std::fs::write(server_dir.join(list.file()), format!("{json}\n"))?;
+	Ok(())
+}
+
+/// Replaces an entry with the same UUID or name, else appends.
+fn upsert(entries: &mut Vec<Entry>, entry: Entry) {
+	entries.retain(|e| {
+		e.uuid != entry.uuid && !e.name.eq_ignore_ascii_case(&entry.name)
+	});
+	entries.push(entry);
+}
+
+fn take(entries: &mut Vec<Entry>, name: &str) -> Option<Entry> {
+	let index = entries
+		.iter()
+		.position(|e| e.name.eq_ignore_ascii_case(name))?;
+	Some(entries.remove(index))
+}
+
+/// Usernames are 1-16 letters, digits or underscores.
+fn validate_name(name: &str) -> anyhow::Result<()> {
+	let valid = (1..=16).contains(&name.len())
+		&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+	if !valid {
+		anyhow::bail!("Invalid player name: {name}");
+	}
+	Ok(())
+}
+
+/// (dashed UUID, name with Mojang's capitalization).
+fn lookup(name: &str) -> anyhow::Result<(String, String)> {
+	let profile: Profile = net::get_json(&format!("{PROFILE_API}/{name}"))
+		.map_err(|e| {
+			anyhow::anyhow!(
+				"Could not look up {name} with Mojang ({e}); use --offline for offline-mode servers"
+			)
+		})?;
+	Ok((dashed(&profile.id)?, profile.name))
+}
+
+/// `069a79f444e94726a5befca90e38aaf5` -> `069a79f4-44e9-4726-a5be-fca90e38aaf5`.
+fn dashed(id: &str) -> anyhow::Result<String> {
+	if id.len() != 32 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+		anyhow::bail!("Unexpected UUID from Mojang: {id}");
+	}
+	Ok(format!(
+		"{}-{}-{}-{}-{}",
+		&id[..8],
+		&id[8..12],
+		&id[12..16],
+		&id[16..20],
+		&id[20..]
+	))
+}
+
+/// The UUID an offline-mode server gives `name`: a version 3 (MD5) UUID of
+/// `OfflinePlayer:<name>`.
+fn offline_uuid(name: &str) -> String {
+	let mut hash: [u8; 16] =
+		Md5::digest(format!("OfflinePlayer:{name}").as_bytes()).into();
+	hash[6] = (hash[6] & 0x0f) | 0x30;
+	hash[8] = (hash[8] & 0x3f) | 0x80;
+	let hex: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+	dashed(&hex).unwrap_or(hex)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(uuid: &str, name: &str) -> Entry {
+		Entry {
+			uuid: uuid.to_string(),
+			name: name.to_string(),
+			level: None,
+			bypasses_player_limit: None,
+		}
+	}
+
+	#[test]
+	fn offline_uuid_matches_the_server() {
+		assert_eq!(
+			offline_uuid("Notch"),
+			"b50ad385-829d-3141-a216-7e7d7539ba7f"
+		);
+	}
+
+	#[test]
+	fn upserts_by_uuid_or_name() {
+		let mut list = vec![entry("1", "Alex"), entry("2", "Steve")];
+		upsert(&mut list, entry("1", "Alex2"));
+		upsert(&mut list, entry("3", "steve"));
+		assert_eq!(list, [entry("1", "Alex2"), entry("3", "steve")]);
+		assert_eq!(take(&mut list, "ALEX2"), Some(entry("1", "Alex2")));
+		assert!(take(&mut list, "nobody").is_none());
+	}
+
+	#[test]
+	fn validates_names() {
+		assert!(validate_name("Notch_99").is_ok());
+		assert!(validate_name("").is_err());
+		assert!(validate_name("a/../b").is_err());
+		assert!(validate_name("seventeen_chars_x").is_err());
+	}
+}
@@ -0,0 +1,318 @@
+//! Port bookkeeping across server folders: which server claims which port,
+//! free-port picking for `create`, and the `minecraft ports` table.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgMatches, Command};
+use serde::Serialize;
+
+use super::fs;
+
+pub(super) fn build_command() -> Command {
+	Command::new("ports")
+		.about("Show which server uses which port")
+		.arg(
+			Arg::new("json")
+				.long("json")
+				.help("Print the table as JSON")
+				.action(clap::ArgAction::SetTrue),
+		)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Protocol {
+	Tcp,
+	Udp,
+}
+
+/// One port a server folder claims.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(super) struct Allocation {
+	pub(super) port: u16,
+	pub(super) protocol: Protocol,
+	pub(super) server: String,
+	/// The server folder; names can repeat across roots, paths cannot.
+	pub(super) path: PathBuf,
+	/// `game`, `rcon` or `query`.
+	pub(super) purpose: &'static str,
+}
+
+/// Every port claimed by the servers in `roots`, sorted by port.
+pub(super) fn allocations(
+	roots: &[PathBuf],
+) -> anyhow::Result<Vec<Allocation>> {
+	let mut out = Vec::new();
+	for server in fs::find_servers(roots)? {
+		let name = server.name().to_string();
+		let game = fs::server_port(&server)?;
+		let props = fs::read_properties(&server.path)?;
+		let enabled = |key: &str| props.get(key).is_some_and(|v| v == "true");
+		let port = |key: &str, default: u16| {
+			props
+				.get(key)
+				.and_then(|p| p.parse().ok())
+				.unwrap_or(default)
+		};
+
+		let mut claim = |port, protocol, purpose| {
+			out.push(Allocation {
+				port,
+				protocol,
+				server: name.clone(),
+				path: server.path.clone(),
+				purpose,
+			})
+		};
+		claim(game, Protocol::Tcp, "game");
+		if enabled("enable-rcon") {
+			claim(port("rcon.port", 25575), Protocol::Tcp, "rcon");
+		}
+		if enabled("enable-query") {
+			claim(port("query.port", game), Protocol::Udp, "query");
+		}
+	}
+	out.sort_by_key(|a| (a.port, a.protocol));
+	Ok(out)
+}
+
+/// Whether something already listens on `port` (TCP, any interface).
+pub(super) fn is_bound(port: u16) -> bool {
+	std::net::TcpListener::bind(("0.0.0.0", port)).is_err()
+}
+
+/// First port from `start` up that no allocation claims and `bound` rejects.
+pub(super) fn first_free(
+	start: u16,
+	claimed: &[u16],
+	bound: impl Fn(u16) -> bool,
+) -> Option<u16> {
+	(start..=u16::MAX).find(|p| !claimed.contains(p) && !bound(*p))
+}
+
+/// Who else claims `port` over TCP, ignoring the server folder `except`.
+pub(super) fn owner<'a>(
+	allocations: &'a [Allocation],
+	port: u16,
+	except: &Path,
+) -> Option<&'a Allocation> {
+	allocations.iter().find(|a| {
+		a.port == port && a.protocol == Protocol::Tcp && a.path != except
+	})
+}
+
+/// Ports claimed by servers other than the folder `except`.
+fn claimed_by_others(allocations: &[Allocation], except: &Path) -> Vec<u16> {
+	allocations
+		.iter()
+		.filter(|a| a.path != except)
+		.map(|a| a.port)
+		.collect()
+}
+
+/// First free port from `wanted` up for the server folder `dir`, or
+/// `wanted` itself when nothing is free.
+pub(super) fn suggest(
+	allocations: &[Allocation],
+	dir: &Path,
+	wanted: u16,
+) -> u16 {
+	first_free(wanted, &claimed_by_others(allocations, dir), is_bound)
+		.unwrap_or(wanted)
+}
+
+/// Port for the new server folder `dir`. A taken `wanted` port moves to the
+/// next free one when `auto` (it came from the default); otherwise it is
+/// kept with a warning.
+pub(super) fn for_new_server(
+	allocations: &[Allocation],
+	dir: &Path,
+	wanted: u16,
+	auto: bool,
+) -> anyhow::Result<u16> {
+	let taken = allocations
+		.iter()
+		.find(|a| a.port == wanted && a.path != dir);
+	let reason = match taken {
+		Some(a) => format!("used by {} ({})", a.server, a.purpose),
+		None if is_bound(wanted) => "in use by another program".to_string(),
+		None => return Ok(wanted),
+	};
+
+	if !auto {
+		println!(
+			"Warning: port {wanted} is {reason}; both cannot run at once."
+		);
+		return Ok(wanted);
+	}
+	let claimed = claimed_by_others(allocations, dir);
+	let port = first_free(wanted, &claimed, is_bound)
+		.ok_or_else(|| anyhow::anyhow!("No free port from {wanted} up"))?;
+	println!("Port {wanted} is {reason}; using {port} instead.");
+	Ok(port)
+}
+
+/// Fails with a readable message when `server`'s game port is taken.
+pub(super) fn ensure_free(
+	roots: &[PathBuf],
+	server: &fs::Server,
+) -> anyhow::Result<()> {
+	let port = fs::server_port(server)?;
+	if !is_bound(port) {
+		return Ok(());
+	}
+	let allocations = allocations(roots)?;
+	let by = match owner(&allocations, port, &server.path) {
+		Some(a) => format!(" by server {} ({})", a.server, a.purpose),
+		None if fs::is_running(server)? => {
+			format!(" ({} looks like it is already running)", server.name())
+		}
+		None => String::new(),
+	};
+	anyhow::bail!(
+		"Port {port} is already in use{by}. Stop it or change the port with \
+		 `eagle minecraft config {} set server-port <port>`.",
+		server.name()
+	)
+}
+
+/// Allocations that share a port and protocol with a different server.
+fn conflicts(allocations: &[Allocation]) -> Vec<&Allocation> {
+	allocations
+		.iter()
+		.filter(|a| {
+			allocations.iter().any(|b| {
+				b.port == a.port
+					&& b.protocol == a.protocol
+					&& (b.path != a.path || b.purpose != a.purpose)
+			})
+		})
+		.collect()
+}
+
+pub(super) fn run_ports(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let allocations = allocations(&roots)?;
+	let conflicts = conflicts(&allocations);
+
+	if matches.get_flag("json") {
+		println!("{}", serde_json::to_string_pretty(&allocations)?);
+		return Ok(());
+	}
+	if allocations.is_empty() {
+		println!("No servers in {}", fs::display_roots(&roots));
+		return Ok(());
+	}
+
+	let width = allocations
+		.iter()
+		.map(|a| a.server.len())
+		.fold("SERVER".len(), usize::max);
+	println!(
+		"{:<5}  {:<5}  {:<w$}  {:<6}  STATUS",
+		"PORT",
+		"PROTO",
+		"SERVER",
+		"USE",
+		w = width
+	);
+	for a in &allocations {
+		let status = if conflicts.contains(&a) {
+			"conflict"
+		} else if a.protocol == Protocol::Tcp && is_bound(a.port) {
+			"in use"
+		} else {
+			""
+		};
+		let protocol = match a.protocol {
+			Protocol::Tcp => "tcp",
+			Protocol::Udp => "udp",
+		};
+		println!(
+			"{:<5}  {:<5}  {:<w$}  {:<6}  {status}",
+			a.port,
+			protocol,
+			a.server,
+			a.purpose,
+			w = width
+		);
+	}
+	if !conflicts.is_empty() {
+		println!();
+		println!(
+			"Conflicting servers cannot run at the same time; change one with \
+			 `eagle minecraft config <server> set server-port <port>`."
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn alloc(port: u16, server: &str, purpose: &'static str) -> Allocation {
+		Allocation {
+			port,
+			protocol: if purpose == "query" {
+				Protocol::Udp
+			} else {
+				Protocol::Tcp
+			},
+			server: server.to_string(),
+			path: PathBuf::from(server),
+			purpose,
+		}
+	}
+
+	#[test]
+	fn skips_claimed_and_bound_ports() {
+		let bound = |p: u16| p == 22224;
+		assert_eq!(first_free(22222, &[22222, 22223], bound), Some(22225));
+		assert_eq!(first_free(22222, &[], |_| false), Some(22222));
+		assert_eq!(first_free(u16::MAX, &[u16::MAX], |_| false), None);
+	}
+
+	#[test]
+	fn query_on_the_game_port_is_not_a_conflict() {
+		let list = [
+			alloc(22222, "a", "game"),
+			alloc(22222, "a", "query"),
+			alloc(22223, "b", "game"),
+			alloc(22223, "c", "game"),
+		];
+		let found: Vec<&str> =
+			conflicts(&list).iter().map(|a| a.server.as_str()).collect();
+		assert_eq!(found, ["b", "c"]);
+		assert_eq!(owner(&list, 22222, Path::new("b")).unwrap().server, "a");
+		assert!(owner(&list, 22222, Path::new("a")).is_none());
+	}
+
+	#[test]
+	fn same_name_in_another_root_is_not_self() {
+		let at = |path: &str| Allocation {
+			path: PathBuf::from(path),
+			..alloc(22222, "survival", "game")
+		};
+		let list = [at("/a/survival"), at("/b/survival")];
+		let a = Path::new("/a/survival");
+		assert_eq!(
+			owner(&list, 22222, a).unwrap().path,
+			Path::new("/b/survival")
+		);
+		assert_eq!(claimed_by_others(&list, a), [22222]);
+		assert_eq!(conflicts(&list).len(), 2);
+	}
+
+	#[test]
+	fn own_port_does_not_hide_another_server_on_it() {
+		let list = [
+			alloc(22222, "a", "game"),
+			alloc(22222, "b", "game"),
+			alloc(22223, "b", "rcon"),
+		];
+		let port = for_new_server(&list, Path::new("a"), 22222, true).unwrap();
+		assert_ne!(port, 22222);
+		assert_ne!(port, 22223);
+	}
+}
@@ -0,0 +1,605 @@
+//! `server.properties`: a Java properties parser/serializer that keeps
+//! comments, ordering and untouched lines byte-for-byte, plus the typed model
+//! of the keys vanilla Minecraft knows.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub(super) const PROPERTIES_FILE: &str = "server.properties";
+
+/// A parsed properties file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Properties {
+	lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+	/// Comment or blank line, kept verbatim.
+	Text(String),
+	Entry {
+		key: String,
+		value: String,
+		/// Original physical line(s); `None` once the entry was changed.
+		raw: Option<String>,
+	},
+}
+
+impl Properties {
+	pub(super) fn path(server_dir: &Path) -> PathBuf {
+		server_dir.join(PROPERTIES_FILE)
+	}
+
+	/// Loads `server.properties`; a missing file is an empty document.
+	pub(super) fn load(server_dir: &Path) -> anyhow::Result<Self> {
+		match std::fs::read_to_string(Self::path(server_dir)) {
+			Ok(content) => Ok(Self::parse(&content)),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				Ok(Self::default())
+			}
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	pub(super) fn save(&self, server_dir: &Path) -> anyhow::Result<()> {
+		std::fs::write(Self::path(server_dir), self.to_string())?;
+		Ok(())
+	}
+
+	pub(super) fn parse(content: &str) -> Self {
+		let mut lines = Vec::new();
+		let mut physical = content.lines();
+		while let Some(line) = physical.next() {
+			let trimmed = line.trim_start();
+			if trimmed.is_empty()
+				|| trimmed.starts_with('#')
+				|| trimmed.starts_with('!')
+			{
+				lines.push(Line::Text(line.to_string()));
+				continue;
+			}
+
+			let mut raw = line.to_string();
+			let mut logical = line.to_string();
+			while ends_with_continuation(&logical) {
+				logical.pop();
+				let Some(next) = physical.next() else {
+					break;
+				};
+				raw.push('\n');
+				raw.push_str(next);
+				logical.push_str(next.trim_start());
+			}
+
+			let (key, value) = split_entry(logical.trim_start());
+			lines.push(Line::Entry {
+				key,
+				value,
+				raw: Some(raw),
+			});
+		}
+		Self { lines }
+	}
+
+	/// Last value for `key`, as Java's `Properties` would see it.
+	pub(super) fn get(&self, key: &str) -> Option<&str> {
+		self.entries()
+			.filter(|(k, _)| *k == key)
+			.last()
+			.map(|(_, v)| v)
+	}
+
+	/// Updates the last occurrence of `key` (dropping earlier duplicates) or
+	/// appends it.
+	pub(super) fn set(&mut self, key: &str, value: &str) {
+		let Some(last) = self.position(key) else {
+			self.lines.push(Line::Entry {
+				key: key.to_string(),
+				value: value.to_string(),
+				raw: None,
+			});
+			return;
+		};
+
+		if let Line::Entry { value: v, raw, .. } = &mut self.lines[last]
+			&& v != value
+		{
+			*v = value.to_string();
+			*raw = None;
+		}
+		let mut index = 0;
+		self.lines.retain(|line| {
+			let keep = index >= last || !line.is_entry(key);
+			index += 1;
+			keep
+		});
+	}
+
+	/// Removes every occurrence of `key`; returns whether it was present.
+	pub(super) fn unset(&mut self, key: &str) -> bool {
+		let before = self.lines.len();
+		self.lines.retain(|line| !line.is_entry(key));
+		self.lines.len() != before
+	}
+
+	/// Entries in file order, duplicates included.
+	pub(super) fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.lines.iter().filter_map(|line| match line {
+			Line::Entry { key, value, .. } => {
+				Some((key.as_str(), value.as_str()))
+			}
+			Line::Text(_) => None,
+		})
+	}
+
+	pub(super) fn to_map(&self) -> HashMap<String, String> {
+		self.entries()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect()
+	}
+
+	fn position(&self, key: &str) -> Option<usize> {
+		self.lines.iter().rposition(|line| line.is_entry(key))
+	}
+}
+
+impl Line {
+	fn is_entry(&self, name: &str) -> bool {
+		matches!(self, Line::Entry { key, .. } if key == name)
+	}
+}
+
+impl fmt::Display for Properties {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for line in &self.lines {
+			match line {
+				Line::Text(text) => writeln!(f, "{text}")?,
+				Line::Entry { raw: Some(raw), .. } => writeln!(f, "{raw}")?,
+				Line::Entry {
+					key,
+					value,
+					raw: None,
+				} => writeln!(
+					f,
+					"{}={}",
+					escape(key, true),
+					escape(value, false)
+				)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An odd number of trailing backslashes continues the line.
+fn ends_with_continuation(line: &str) -> bool {
+	line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+/// Splits a logical line at the first unescaped `=`, `:` or whitespace.
+fn split_entry(line: &str) -> (String, String) {
+	let mut end = line.len();
+	let mut escaped = false;
+	for (i, c) in line.char_indices() {
+		if escaped {
+			escaped = false;
+		} else if c == '\\' {
+			escaped = true;
+		} else if c == '=' || c == ':' || c.is_whitespace() {
+			end = i;
+			break;
+		}
+	}
+
+	let key = &line[..end];
+	let mut rest = line[end..].trim_start();
+	if let Some(r) = rest.strip_prefix(['=', ':']) {
+		rest = r.trim_start();
+	}
+	(unescape(key), unescape(rest))
+}
+
+fn unescape(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			out.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('t') => out.push('\t'),
+			Some('n') => out.push('\n'),
+			Some('r') => out.push('\r'),
+			Some('f') => out.push('\u{c}'),
+			Some('u') => {
+				let hex: String = chars.clone().take(4).collect();
+				match u32::from_str_radix(&hex, 16)
+					.ok()
+					.and_then(char::from_u32)
+				{
+					Some(decoded) if hex.len() == 4 => {
+						out.push(decoded);
+						chars.nth(3);
+					}
+					_ => out.push('u'),
+				}
+			}
+			Some(other) => out.push(other),
+			None => {}
+		}
+	}
+	out
+}
+
+/// Escapes like `Properties.store` does, except that non-ASCII text is kept
+/// as UTF-8 (which Minecraft reads).
+fn escape(s: &str, is_key: bool) -> String {
+	let mut out = String::with_capacity(s.len());
+	for (i, c) in s.chars().enumerate() {
+		match c {
+			'\\' => out.push_str("\\\\"),
+			'\t' => out.push_str("\\t"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\u{c}' => out.push_str("\\f"),
+			'=' | ':' | '#' | '!' => {
+				out.push('\\');
+				out.push(c);
+			}
+			' ' if i == 0 || is_key => out.push_str("\\ "),
+			_ => out.push(c),
+		}
+	}
+	out
+}
+
+/// How a known key's value is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Kind {
+	Bool,
+	Int {
+		min: i64,
+		max: i64,
+	},
+	/// Names in id order; Minecraft also accepts the numeric ids.
+	Enum(&'static [&'static str]),
+	Text,
+}
+
+/// A typed property value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Value {
+	Bool(bool),
+	Int(i64),
+	Enum(&'static str),
+	Text(String),
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Bool(b) => write!(f, "{b}"),
+			Value::Int(i) => write!(f, "{i}"),
+			Value::Enum(name) => f.write_str(name),
+			Value::Text(text) => f.write_str(text),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Spec {
+	pub(super) key: &'static str,
+	pub(super) kind: Kind,
+	/// Vanilla default, as written to a fresh `server.properties`.
+	pub(super) default: &'static str,
+}
+
+pub(super) const DIFFICULTIES: &[&str] =
+	&["peaceful", "easy", "normal", "hard"];
+pub(super) const GAMEMODES: &[&str] =
+	&["survival", "creative", "adventure", "spectator"];
+const COMPRESSIONS: &[&str] = &["deflate", "lz4", "none"];
+const PORT: Kind = Kind::Int { min: 1, max: 65535 };
+const DISTANCE: Kind = Kind::Int { min: 3, max: 32 };
+const NON_NEGATIVE: Kind = Kind::Int {
+	min: 0,
+	max: i32::MAX as i64,
+};
+
+const fn spec(key: &'static str, kind: Kind, default: &'static str) -> Spec {
+	Spec { key, kind, default }
+}
+
+/// Keys of a vanilla 1.21 `server.properties` (plus the removed
+/// `spawn-animals`/`spawn-npcs`, which older servers still read).
+pub(super) const KNOWN: &[Spec] = &[
+	spec("accepts-transfers", Kind::Bool, "false"),
+	spec("allow-flight", Kind::Bool, "false"),
+	spec("allow-nether", Kind::Bool, "true"),
+	spec("broadcast-console-to-ops", Kind::Bool, "true"),
+	spec("broadcast-rcon-to-ops", Kind::Bool, "true"),
+	spec("bug-report-link", Kind::Text, ""),
+	spec("difficulty", Kind::Enum(DIFFICULTIES), "easy"),
+	spec("enable-command-block", Kind::Bool, "false"),
+	spec("enable-jmx-monitoring", Kind::Bool, "false"),
+	spec("enable-query", Kind::Bool, "false"),
+	spec("enable-rcon", Kind::Bool, "false"),
+	spec("enable-status", Kind::Bool, "true"),
+	spec("enforce-secure-profile", Kind::Bool, "true"),
+	spec("enforce-whitelist", Kind::Bool, "false"),
+	spec(
+		"entity-broadcast-range-percentage",
+		Kind::Int { min: 10, max: 1000 },
+		"100",
+	),
+	spec("force-gamemode", Kind::Bool, "false"),
+	spec(
+		"function-permission-level",
+		Kind::Int { min: 1, max: 4 },
+		"2",
+	),
+	spec("gamemode", Kind::Enum(GAMEMODES), "survival"),
+	spec("generate-structures", Kind::Bool, "true"),
+	spec("generator-settings", Kind::Text, "{}"),
+	spec("hardcore", Kind::Bool, "false"),
+	spec("hide-online-players", Kind::Bool, "false"),
+	spec("initial-disabled-packs", Kind::Text, ""),
+	spec("initial-enabled-packs", Kind::Text, "vanilla"),
+	spec("level-name", Kind::Text, "world"),
+	spec("level-seed", Kind::Text, ""),
+	spec("level-type", Kind::Text, "minecraft:normal"),
+	spec("log-ips", Kind::Bool, "true"),
+	spec("max-chained-neighbor-updates", NON_NEGATIVE, "1000000"),
+	spec("max-players", NON_NEGATIVE, "20"),
+	spec(
+		"max-tick-time",
+		Kind::Int {
+			min: -1,
+			max: i64::MAX,
+		},
+		"60000",
+	),
+	spec(
+		"max-world-size",
+		Kind::Int {
+			min: 1,
+			max: 29_999_984,
+		},
+		"29999984",
+	),
+	spec("motd", Kind::Text, "A Minecraft Server"),
+	spec(
+		"network-compression-threshold",
+		Kind::Int {
+			min: -1,
+			max: i32::MAX as i64,
+		},
+		"256",
+	),
+	spec("online-mode", Kind::Bool, "true"),
+	spec("op-permission-level", Kind::Int { min: 0, max: 4 }, "4"),
+	spec("pause-when-empty-seconds", NON_NEGATIVE, "60"),
+	spec("player-idle-timeout", NON_NEGATIVE, "0"),
+	spec("prevent-proxy-connections", Kind::Bool, "false"),
+	spec("pvp", Kind::Bool, "true"),
+	spec("query.port", PORT, "25565"),
+	spec("rate-limit", NON_NEGATIVE, "0"),
+	spec("rcon.password", Kind::Text, ""),
+	spec("rcon.port", PORT, "25575"),
+	spec(
+		"region-file-compression",
+		Kind::Enum(COMPRESSIONS),
+		"deflate",
+	),
+	spec("require-resource-pack", Kind::Bool, "false"),
+	spec("resource-pack", Kind::Text, ""),
+	spec("resource-pack-id", Kind::Text, ""),
+	spec("resource-pack-prompt", Kind::Text, ""),
+	spec("resource-pack-sha1", Kind::Text, ""),
+	spec("server-ip", Kind::Text, ""),
+	spec("server-port", PORT, "25565"),
+	spec("simulation-distance", DISTANCE, "10"),
+	spec("spawn-animals", Kind::Bool, "true"),
+	spec("spawn-monsters", Kind::Bool, "true"),
+	spec("spawn-npcs", Kind::Bool, "true"),
+	spec("spawn-protection", NON_NEGATIVE, "16"),
+	spec("sync-chunk-writes", Kind::Bool, "true"),
+	spec("text-filtering-config", Kind::Text, ""),
+	spec("text-filtering-version", Kind::Int { min: 0, max: 1 }, "0"),
+	spec("use-native-transport", Kind::Bool, "true"),
+	spec("view-distance", DISTANCE, "10"),
+	spec("white-list", Kind::Bool, "false"),
+];
+
+pub(super) fn lookup(key: &str) -> Option<&'static Spec> {
+	KNOWN.iter().find(|s| s.key == key)
+}
+
+/// Validates and normalizes a known key's value. Unknown keys pass through
+/// (mods and forks add their own) unless they look like a typo.
+pub(super) fn normalize(key: &str, value: &str) -> anyhow::Result<String> {
+	match lookup(key) {
+		Some(spec) => Ok(spec.parse(value)?.to_string()),
+		None => match key_hint(key) {
+			hint if hint.is_empty() => Ok(value.to_string()),
+			hint => anyhow::bail!("Unknown property {key}{hint}"),
+		},
+	}
+}
+
+/// `; did you mean ...` for misspelled keys, else empty.
+pub(super) fn key_hint(key: &str) -> String {
+	match closest(key, KNOWN.iter().map(|s| s.key)) {
+		Some(near) if near != key => format!("; did you mean `{near}`?"),
+		_ => String::new(),
+	}
+}
+
+impl Spec {
+	pub(super) fn parse(&self, value: &str) -> anyhow::Result<Value> {
+		let trimmed = value.trim();
+		match self.kind {
+			Kind::Bool => match trimmed.to_ascii_lowercase().as_str() {
+				"true" => Ok(Value::Bool(true)),
+				"false" => Ok(Value::Bool(false)),
+				_ => anyhow::bail!(
+					"Invalid {}: `{value}` (expected true or false)",
+					self.key
+				),
+			},
+			Kind::Int { min, max } => {
+				let n: i64 = trimmed.parse().map_err(|_| {
+					anyhow::anyhow!(
+						"Invalid {}: `{value}` (expected a whole number)",
+						self.key
+					)
+				})?;
+				if n < min || n > max {
+					anyhow::bail!(
+						"Invalid {}: {n} (expected {min}..={max})",
+						self.key
+					);
+				}
+				Ok(Value::Int(n))
+			}
+			Kind::Enum(names) => {
+				let lower = trimmed.to_ascii_lowercase();
+				let by_name = names.iter().find(|n| **n == lower);
+				let by_id =
+					lower.parse::<usize>().ok().and_then(|i| names.get(i));
+				if let Some(name) = by_name.or(by_id) {
+					return Ok(Value::Enum(name));
+				}
+				let hint = match closest(&lower, names.iter().copied()) {
+					Some(near) => format!("; did you mean `{near}`?"),
+					None => String::new(),
+				};
+				anyhow::bail!(
+					"Invalid {}: `{value}` (expected {}){hint}",
+					self.key,
+					names.join(", ")
+				)
+			}
+			Kind::Text => Ok(Value::Text(value.to_string())),
+		}
+	}
+
+	/// What the value looks like, for help output.
+	pub(super) fn describe(&self) -> String {
+		match self.kind {
+			Kind::Bool => "true|false".to_string(),
+			Kind::Int { min, max } if max == i64::MAX => format!("{min}.."),
+			Kind::Int { min, max } => format!("{min}..={max}"),
+			Kind::Enum(names) => names.join("|"),
+			Kind::Text => "text".to_string(),
+		}
+	}
+}
+
+/// Nearest candidate within a couple of edits, for typo hints.
+pub(super) fn closest<'a>(
+	input: &str,
+	candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+	candidates
+		.map(|c| (edit_distance(input, c), c))
+		.filter(|(d, c)| *d <= 2.max(c.len() / 4))
+		.min_by_key(|(d, _)| *d)
+		.map(|(_, c)| c)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut row: Vec<usize> = (0..=b.len()).collect();
+	for (i, ca) in a.chars().enumerate() {
+		let mut prev = row[0];
+		row[0] = i + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let cost = if ca == *cb { prev } else { prev + 1 };
+			prev = row[j + 1];
+			row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+		}
+	}
+	row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SAMPLE: &str = "#Minecraft server properties\n\
+		#Sat Jan 01 00:00:00 UTC 2025\n\
+		level-type=minecraft\\:normal\n\
+		motd=A \\u00A76fancy\\ server\n\
+		\n\
+		! bang comment\n\
+		long-list = one,\\\n    two\n\
+		spaced key\n\
+		difficulty=easy\n";
+
+	#[test]
+	fn parses_java_properties() {
+		let props = Properties::parse(SAMPLE);
+		assert_eq!(props.get("level-type"), Some("minecraft:normal"));
+		assert_eq!(props.get("motd"), Some("A \u{a7}6fancy server"));
+		assert_eq!(props.get("long-list"), Some("one,two"));
+		assert_eq!(props.get("spaced"), Some("key"));
+		assert_eq!(props.entries().count(), 5);
+	}
+
+	#[test]
+	fn untouched_lines_round_trip() {
+		let mut props = Properties::parse(SAMPLE);
+		assert_eq!(props.to_string(), SAMPLE);
+
+		props.set("difficulty", "hard");
+		props.set("level-seed", "a:b c");
+		assert!(props.unset("spaced"));
+		let out = props.to_string();
+		assert!(out.starts_with("#Minecraft server properties\n"));
+		assert!(out.contains("long-list = one,\\\n    two\n"));
+		assert!(out.contains("difficulty=hard\n"));
+		assert!(out.ends_with("level-seed=a\\:b c\n"));
+		assert!(!out.contains("spaced"));
+
+		let reparsed = Properties::parse(&out);
+		assert_eq!(reparsed.get("level-seed"), Some("a:b c"));
+		assert_eq!(reparsed.get("motd"), props.get("motd"));
+	}
+
+	#[test]
+	fn set_collapses_duplicates_onto_the_last() {
+		let mut props = Properties::parse("pvp=true\n# x\npvp=false\n");
+		assert_eq!(props.get("pvp"), Some("false"));
+		props.set("pvp", "true");
+		assert_eq!(props.to_string(), "# x\npvp=true\n");
+	}
+
+	#[test]
+	fn validates_known_keys() {
+		let difficulty = lookup("difficulty").unwrap();
+		assert_eq!(difficulty.parse("Hard").unwrap(), Value::Enum("hard"));
+		assert_eq!(difficulty.parse("0").unwrap(), Value::Enum("peaceful"));
+		let err = difficulty.parse("eazy").unwrap_err().to_string();
+		assert!(err.contains("did you mean `easy`"), "{err}");
+
+		let view = lookup("view-distance").unwrap();
+		assert_eq!(view.parse("12").unwrap(), Value::Int(12));
+		assert!(view.parse("64").is_err());
+		assert!(lookup("pvp").unwrap().parse("yes").is_err());
+		assert_eq!(
+			lookup("pvp").unwrap().parse("TRUE").unwrap().to_string(),
+			"true"
+		);
+	}
+
+	#[test]
+	fn suggests_close_keys() {
+		let keys = KNOWN.iter().map(|s| s.key);
+		assert_eq!(closest("difficuly", keys.clone()), Some("difficulty"));
+		assert_eq!(closest("whitelist", keys.clone()), Some("white-list"));
+		assert_eq!(closest("completely-unknown", keys), None);
+	}
+}
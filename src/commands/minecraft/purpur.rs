@@ -0,0 +1,116 @@
+//! Purpur is not on Fill; it has its own (older, Paper v2 style) API.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::fill;
+use crate::net;
+
+const PURPUR_API: &str = "https://api.purpurmc.org/v2/purpur";
+
+/// Minimal shape of `GET https://api.purpurmc.org/v2/purpur`.
+#[derive(Debug, Clone, Deserialize)]
+struct PurpurProject {
+	/// Oldest first.
+	versions: Vec<String>,
+}
+
+/// Minimal shape of `GET .../purpur/{version}`.
+#[derive(Debug, Clone, Deserialize)]
+struct PurpurVersion {
+	builds: PurpurBuilds,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PurpurBuilds {
+	latest: String,
+}
+
+/// Minimal shape of `GET .../purpur/{version}/{build}`.
+#[derive(Debug, Clone, Deserialize)]
+struct PurpurBuild {
+	result: String,
+	md5: String,
+}
+
+/// Resolves `latest` and family keys (`1.21`) the same way as Fill projects.
+pub(super) fn resolve_purpur_version(version: &str) -> anyhow::Result<String> {
+	let version = version.trim();
+	if version != "latest" && !fill::looks_like_family_key(version) {
+		return Ok(version.to_string());
+	}
+
+	let project = net::get_json::<PurpurProject>(PURPUR_API)?;
+	pick_version(&project.versions, version)
+		.map(str::to_string)
+		.ok_or_else(|| anyhow::anyhow!("Unknown Purpur version: {version}"))
+}
+
+fn pick_version<'a>(versions: &'a [String], input: &str) -> Option<&'a str> {
+	if input == "latest" {
+		return versions.last().map(|s| s.as_str());
+	}
+
+	let prefix = format!("{input}.");
+	versions
+		.iter()
+		.rev()
+		.find(|v| *v == input || v.starts_with(&prefix))
+		.map(|s| s.as_str())
+}
+
+/// Returns the requested build, or the latest one when `build` is `None`.
+pub(super) fn resolve_purpur_build(
+	version: &str,
+	build: Option<u64>,
+) -> anyhow::Result<u64> {
+	if let Some(build) = build {
+		return Ok(build);
+	}
+
+	let info =
+		net::get_json::<PurpurVersion>(&format!("{PURPUR_API}/{version}"))?;
+	info.builds
+		.latest
+		.parse::<u64>()
+		.map_err(|_| anyhow::anyhow!("Unexpected Purpur build id"))
+}
+
+pub(super) fn download_purpur_build(
+	version: &str,
+	build: u64,
+	jar_path: &Path,
+) -> anyhow::Result<()> {
+	println!("Downloading Purpur {version}...");
+
+	let info = net::get_json::<PurpurBuild>(&format!(
+		"{PURPUR_API}/{version}/{build}"
+	))?;
+	if info.result != "SUCCESS" {
+		anyhow::bail!("Purpur {version} build {build} is {}", info.result);
+	}
+
+	println!("Build {build}");
+	net::download_verified(
+		&format!("{PURPUR_API}/{version}/{build}/download"),
+		jar_path,
+		Some(net::Checksum::Md5(&info.md5)),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn picks_newest_in_family() {
+		let versions: Vec<String> = ["1.20.6", "1.21", "1.21.1", "1.21.4"]
+			.map(String::from)
+			.to_vec();
+		assert_eq!(pick_version(&versions, "1.21"), Some("1.21.4"));
+		assert_eq!(pick_version(&versions, "1.20"), Some("1.20.6"));
+		assert_eq!(pick_version(&versions, "latest"), Some("1.21.4"));
+		assert_eq!(pick_version(&versions, "1.19"), None);
+	}
+}
@@ -0,0 +1,285 @@
+//! `minecraft query`: the GameSpy4 UDP protocol behind `enable-query`, which
+//! unlike Server List Ping returns every online player.
+
+use std::collections::BTreeMap;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use clap::{Arg, ArgMatches, Command};
+use rand::Rng;
+use serde::Serialize;
+
+use super::fs;
+use super::status;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAGIC: [u8; 2] = [0xfe, 0xfd];
+const TYPE_HANDSHAKE: u8 = 0x09;
+const TYPE_STAT: u8 = 0x00;
+/// Fixed bytes between the header and the key/value section.
+const KV_PADDING: usize = 11;
+
+pub(super) fn build_command() -> Command {
+	Command::new("query")
+		.about("Full server info and player list over the Query protocol")
+		.arg(
+			Arg::new("target")
+				.help("Server folder name or host[:port] (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("json")
+				.long("json")
+				.help("Print the result as JSON")
+				.action(clap::ArgAction::SetTrue),
+		)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(super) struct FullStat {
+	pub(super) motd: String,
+	pub(super) game_type: String,
+	pub(super) version: String,
+	/// Server software, e.g. `Paper on Bukkit 1.21.4-R0.1-SNAPSHOT`.
+	pub(super) software: String,
+	pub(super) plugins: Vec<String>,
+	pub(super) map: String,
+	pub(super) online: u32,
+	pub(super) max: u32,
+	pub(super) players: Vec<String>,
+	/// Every key/value the server sent, including ones not listed above.
+	pub(super) raw: BTreeMap<String, String>,
+}
+
+pub(super) fn run_query(matches: &ArgMatches) -> anyhow::Result<()> {
+	let target = matches.get_one::<String>("target").map(|s| s.as_str());
+	let (host, port) = status::resolve_target(target, query_port)?;
+
+	let stat = full_stat(&host, port)?;
+	if matches.get_flag("json") {
+		println!("{}", serde_json::to_string_pretty(&stat)?);
+		return Ok(());
+	}
+
+	println!("{host}:{port}");
+	println!("MOTD:     {}", stat.motd);
+	println!("Version:  {} ({})", stat.version, stat.game_type);
+	if !stat.software.is_empty() {
+		println!("Software: {}", stat.software);
+	}
+	println!("Map:      {}", stat.map);
+	println!("Players:  {}/{}", stat.online, stat.max);
+	for player in &stat.players {
+		println!("  {player}");
+	}
+	if !stat.plugins.is_empty() {
+		println!("Plugins:  {}", stat.plugins.join(", "));
+	}
+	Ok(())
+}
+
+/// Local servers use `query.port` (falling back to the game port).
+fn query_port(server: &fs::Server) -> anyhow::Result<u16> {
+	let props = fs::read_properties(&server.path)?;
+	if props.get("enable-query").map(String::as_str) == Some("false") {
+		anyhow::bail!(
+			"Query is not enabled for {} (set enable-query=true in server.properties)",
+			server.name()
+		);
+	}
+	let port = match props.get("query.port").and_then(|p| p.parse().ok()) {
+		Some(port) => port,
+		None => fs::server_port(server)?,
+	};
+	Ok(port)
+}
+
+pub(super) fn full_stat(host: &str, port: u16) -> anyhow::Result<FullStat> {
+	let addr = (host, port)
+		.to_socket_addrs()?
+		.next()
+		.ok_or_else(|| anyhow::anyhow!("Could not resolve {host}"))?;
+	let socket = UdpSocket::bind(if addr.is_ipv4() {
+		"0.0.0.0:0"
+	} else {
+		"[::]:0"
+	})?;
+	socket.set_read_timeout(Some(TIMEOUT))?;
+	socket.connect(addr)?;
+
+	let session = rand::rng().random::<i32>() & 0x0f0f_0f0f;
+	let no_reply = |e: std::io::Error| {
+		anyhow::anyhow!(
+			"No Query reply from {host}:{port} ({e}); is enable-query on?"
+		)
+	};
+
+	socket.send(&request(TYPE_HANDSHAKE, session, &[]))?;
+	let mut buf = [0u8; 65536];
+	let n = socket.recv(&mut buf).map_err(no_reply)?;
+	let token = parse_challenge(&buf[..n], session)?;
+
+	let mut payload = token.to_be_bytes().to_vec();
+	payload.extend_from_slice(&[0, 0, 0, 0]);
+	socket.send(&request(TYPE_STAT, session, &payload))?;
+	let n = socket.recv(&mut buf).map_err(no_reply)?;
+	parse_full_stat(&buf[..n], session)
+}
+
+fn request(kind: u8, session: i32, payload: &[u8]) -> Vec<u8> {
+	let mut out = MAGIC.to_vec();
+	out.push(kind);
+	out.extend_from_slice(&session.to_be_bytes());
+	out.extend_from_slice(payload);
+	out
+}
+
+/// Checks the reply header and returns the rest.
+fn reply_body(reply: &[u8], kind: u8, session: i32) -> anyhow::Result<&[u8]> {
+	if reply.len() < 5 || reply[0] != kind {
+		anyhow::bail!("Unexpected Query reply");
+	}
+	if reply[1..5] != session.to_be_bytes() {
+		anyhow::bail!("Query reply for another session");
+	}
+	Ok(&reply[5..])
+}
+
+/// The token arrives as a NUL-terminated decimal string.
+fn parse_challenge(reply: &[u8], session: i32) -> anyhow::Result<i32> {
+	let body = reply_body(reply, TYPE_HANDSHAKE, session)?;
+	let text = String::from_utf8_lossy(body);
+	let text = text.trim_end_matches('\0').trim();
+	text.parse::<i64>()
+		.map(|t| t as i32)
+		.map_err(|_| anyhow::anyhow!("Invalid challenge token: {text}"))
+}
+
+fn parse_full_stat(reply: &[u8], session: i32) -> anyhow::Result<FullStat> {
+	let body = reply_body(reply, TYPE_STAT, session)?;
+	let mut fields = body
+		.get(KV_PADDING..)
+		.ok_or_else(|| anyhow::anyhow!("Truncated Query reply"))?
+		.split(|b| *b == 0)
+		.map(|s| String::from_utf8_lossy(s).into_owned());
+
+	let mut raw = BTreeMap::new();
+	loop {
+		let key = fields.next().unwrap_or_default();
+		if key.is_empty() {
+			break;
+		}
+		raw.insert(key, fields.next().unwrap_or_default());
+	}
+
+	// `\x01player_\0\0` splits into the marker and an empty field.
+	let marker = fields.next().unwrap_or_default();
+	if !marker.is_empty() && marker != "\u{1}player_" {
+		anyhow::bail!("Unexpected Query player section");
+	}
+	fields.next();
+	let players: Vec<String> = fields.take_while(|p| !p.is_empty()).collect();
+
+	let get = |k: &str| raw.get(k).cloned().unwrap_or_default();
+	let (software, plugins) = split_plugins(&get("plugins"));
+
+	Ok(FullStat {
+		motd: super::rcon::strip_formatting(&get("hostname")),
+		game_type: get("gametype"),
+		version: get("version"),
+		software,
+		plugins,
+		map: get("map"),
+		online: get("numplayers").parse().unwrap_or(players.len() as u32),
+		max: get("maxplayers").parse().unwrap_or(0),
+		players,
+		raw,
+	})
+}
+
+/// `Paper on Bukkit 1.21.4: LuckPerms 5.4; Vault 1.7` -> software + plugins.
+fn split_plugins(s: &str) -> (String, Vec<String>) {
+	match s.split_once(": ") {
+		Some((software, list)) => (
+			software.trim().to_string(),
+			list.split("; ")
+				.map(str::trim)
+				.filter(|p| !p.is_empty())
+				.map(str::to_string)
+				.collect(),
+		),
+		None => (s.trim().to_string(), Vec::new()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SESSION: i32 = 0x0102_0304;
+
+	#[test]
+	fn builds_requests() {
+		assert_eq!(
+			request(TYPE_HANDSHAKE, SESSION, &[]),
+			[0xfe, 0xfd, 0x09, 1, 2, 3, 4]
+		);
+	}
+
+	#[test]
+	fn parses_challenge_token() {
+		let mut reply = vec![TYPE_HANDSHAKE, 1, 2, 3, 4];
+		reply.extend_from_slice(b"9513307\0");
+		assert_eq!(parse_challenge(&reply, SESSION).unwrap(), 9513307);
+		assert!(parse_challenge(&reply, 7).is_err());
+	}
+
+	#[test]
+	fn parses_full_stat() {
+		let mut reply = vec![TYPE_STAT, 1, 2, 3, 4];
+		reply.extend_from_slice(b"splitnum\0\x80\0");
+		for (k, v) in [
+			("hostname", "A Minecraft Server"),
+			("gametype", "SMP"),
+			("game_id", "MINECRAFT"),
+			("version", "1.21.4"),
+			(
+				"plugins",
+				"Paper on Bukkit 1.21.4: LuckPerms 5.4; Vault 1.7",
+			),
+			("map", "world"),
+			("numplayers", "2"),
+			("maxplayers", "20"),
+			("hostport", "25565"),
+			("hostip", "127.0.0.1"),
+		] {
+			reply.extend_from_slice(k.as_bytes());
+			reply.push(0);
+			reply.extend_from_slice(v.as_bytes());
+			reply.push(0);
+		}
+		reply.push(0);
+		reply.extend_from_slice(b"\x01player_\0\0");
+		reply.extend_from_slice(b"Notch\0jeb_\0\0");
+
+		let stat = parse_full_stat(&reply, SESSION).unwrap();
+		assert_eq!(stat.motd, "A Minecraft Server");
+		assert_eq!(stat.game_type, "SMP");
+		assert_eq!(stat.version, "1.21.4");
+		assert_eq!(stat.software, "Paper on Bukkit 1.21.4");
+		assert_eq!(stat.plugins, ["LuckPerms 5.4", "Vault 1.7"]);
+		assert_eq!(stat.map, "world");
+		assert_eq!((stat.online, stat.max), (2, 20));
+		assert_eq!(stat.players, ["Notch", "jeb_"]);
+		assert_eq!(stat.raw["hostport"], "25565");
+	}
+
+	#[test]
+	fn vanilla_plugins_field_has_no_list() {
+		assert_eq!(split_plugins(""), (String::new(), Vec::new()));
+		assert_eq!(
+			split_plugins("CraftBukkit on Bukkit 1.8"),
+			("CraftBukkit on Bukkit 1.8".to_string(), Vec::new())
+		);
+	}
+}
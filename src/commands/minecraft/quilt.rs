@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::install;
+use super::manifest::Launch;
+use crate::net;
+
+const QUILT_META: &str = "https://meta.quiltmc.org/v3/versions";
+const INSTALLER_JAR: &str = "quilt-installer.jar";
+
+/// Minimal shape of `GET https://meta.quiltmc.org/v3/versions/loader/{game_version}`.
+#[derive(Debug, Clone, Deserialize)]
+struct LoaderEntry {
+	loader: LoaderPart,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LoaderPart {
+	version: String,
+}
+
+/// Minimal shape of `GET https://meta.quiltmc.org/v3/versions/installer`.
+#[derive(Debug, Clone, Deserialize)]
+struct InstallerEntry {
+	version: String,
+}
+
+/// Resolves the loader (pinned or newest non-beta) and the newest installer.
+pub(super) fn resolve_quilt_build(
+	version: &str,
+	loader: Option<&str>,
+) -> anyhow::Result<(String, String)> {
+	let entries = net::get_json::<Vec<LoaderEntry>>(&format!(
+		"{QUILT_META}/loader/{version}"
+	))?;
+	let loaders: Vec<&str> =
+		entries.iter().map(|e| e.loader.version.as_str()).collect();
+
+	let loader = match loader {
+		Some(l) if loaders.contains(&l) => l,
+		Some(l) => anyhow::bail!("Unknown Quilt loader {l} for {version}"),
+		None => pick_stable_loader(&loaders).ok_or_else(|| {
+			anyhow::anyhow!("No Quilt loader versions found for {version}")
+		})?,
+	};
+
+	let installers = net::get_json::<Vec<InstallerEntry>>(&format!(
+		"{QUILT_META}/installer"
+	))?;
+	let installer = installers
+		.first()
+		.ok_or_else(|| anyhow::anyhow!("No Quilt installer versions found"))?;
+
+	Ok((loader.to_string(), installer.version.clone()))
+}
+
+/// Meta lists newest first; betas are tagged in the version string.
+fn pick_stable_loader<'a>(loaders: &[&'a str]) -> Option<&'a str> {
+	loaders
+		.iter()
+		.find(|v| !v.contains('-'))
+		.or_else(|| loaders.first())
+		.copied()
+}
+
+/// The jar the Quilt installer writes; also how `adopt` recognises Quilt.
+pub(super) const LAUNCH_JAR: &str = "quilt-server-launch.jar";
+
+pub(super) fn launch() -> Launch {
+	Launch::Jar {
+		jar: LAUNCH_JAR.to_string(),
+	}
+}
+
+/// Runs the Quilt installer headlessly; it downloads the vanilla jar and
+/// writes `quilt-server-launch.jar`.
+pub(super) fn install_quilt_server(
+	java: &Path,
+	version: &str,
+	loader: &str,
+	installer: &str,
+	server_dir: &Path,
+) -> anyhow::Result<Launch> {
+	println!("Installing Quilt {version} (loader {loader})...");
+
+	let url = format!(
+		"https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-installer/{installer}/quilt-installer-{installer}.jar"
+	);
+	install::download_from_maven(&url, &server_dir.join(INSTALLER_JAR))?;
+	install::run_java_installer(
+		java,
+		server_dir,
+		INSTALLER_JAR,
+		&[
+			"install",
+			"server",
+			version,
+			loader,
+			"--download-server",
+			"--install-dir=.",
+		],
+	)?;
+
+	let launch = launch();
+	if !server_dir.join(launch.target()).exists() {
+		anyhow::bail!("Quilt installer did not produce {}", launch.target());
+	}
+	Ok(launch)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn prefers_non_beta_loader() {
+		let loaders = ["0.28.0-beta.1", "0.27.1", "0.27.0"];
+		assert_eq!(pick_stable_loader(&loaders), Some("0.27.1"));
+	}
+
+	#[test]
+	fn falls_back_to_newest_beta() {
+		let loaders = ["0.28.0-beta.2", "0.28.0-beta.1"];
+		assert_eq!(pick_stable_loader(&loaders), Some("0.28.0-beta.2"));
+	}
+}
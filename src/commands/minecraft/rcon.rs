@@ -0,0 +1,318 @@
+//! Source RCON client (the protocol Minecraft's `enable-rcon` speaks) and
+//! the `minecraft rcon` command.
+
+use std::io::{BufRead, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use clap::{Arg, ArgMatches, Command};
+use rand::Rng;
+
+use super::fs;
+
+const TYPE_RESPONSE: i32 = 0;
+const TYPE_COMMAND: i32 = 2;
+const TYPE_AUTH: i32 = 3;
+/// Anything Minecraft doesn't know; it answers with `Unknown request`, which
+/// marks the end of a (possibly split) command response.
+const TYPE_SENTINEL: i32 = 200;
+/// Body limit for packets sent to the server.
+const MAX_COMMAND_LEN: usize = 1446;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(super) fn build_command() -> Command {
+	Command::new("rcon")
+		.about("Send commands over RCON (interactive shell without a command)")
+		.arg(
+			Arg::new("server")
+				.help("Server folder name (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("command")
+				.help("Command to run, e.g. `say hello`")
+				.num_args(1..)
+				.trailing_var_arg(true)
+				.allow_hyphen_values(true)
+				.required(false),
+		)
+		.arg(
+			Arg::new("host")
+				.long("host")
+				.help("Host to connect to (default: 127.0.0.1)")
+				.default_value("127.0.0.1"),
+		)
+}
+
+/// `enable-rcon` settings written by `create --rcon`.
+#[derive(Debug, Clone)]
+pub(super) struct Config {
+	pub(super) port: u16,
+	pub(super) password: String,
+}
+
+impl Config {
+	/// Random password and a random free port in 30000..40000 that no
+	/// other server in `claimed` uses.
+	pub(super) fn generate(claimed: &[u16]) -> anyhow::Result<Self> {
+		let mut rng = rand::rng();
+		let password: String = (0..24)
+			.map(|_| rng.sample(rand::distr::Alphanumeric) as char)
+			.collect();
+
+		for _ in 0..100 {
+			let port = rng.random_range(30000..40000);
+			if !claimed.contains(&port)
+				&& std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
+			{
+				return Ok(Self { port, password });
+			}
+		}
+		anyhow::bail!("Could not find a free port for RCON")
+	}
+
+	/// Reads `enable-rcon`, `rcon.port` and `rcon.password`.
+	pub(super) fn from_properties(server: &fs::Server) -> anyhow::Result<Self> {
+		let props = fs::read_properties(&server.path)?;
+		if props.get("enable-rcon").map(String::as_str) != Some("true") {
+			anyhow::bail!(
+				"RCON is not enabled for {} (set enable-rcon=true in server.properties)",
+				server.name()
+			);
+		}
+
+		let port = match props.get("rcon.port") {
+			Some(p) => p
+				.parse()
+				.map_err(|_| anyhow::anyhow!("Invalid rcon.port: {p}"))?,
+			None => 25575,
+		};
+		let password = props
+			.get("rcon.password")
+			.filter(|p| !p.is_empty())
+			.cloned()
+			.ok_or_else(|| anyhow::anyhow!("rcon.password is empty"))?;
+
+		Ok(Self { port, password })
+	}
+}
+
+/// An authenticated RCON connection.
+pub(super) struct Client {
+	stream: TcpStream,
+	next_id: i32,
+}
+
+impl Client {
+	pub(super) fn connect(
+		host: &str,
+		port: u16,
+		password: &str,
+	) -> anyhow::Result<Self> {
+		let addr: SocketAddr = (host, port)
+			.to_socket_addrs()?
+			.next()
+			.ok_or_else(|| anyhow::anyhow!("Could not resolve {host}"))?;
+		let stream =
+			TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| {
+				anyhow::anyhow!("Could not connect to RCON at {addr}: {e}")
+			})?;
+		stream.set_read_timeout(Some(TIMEOUT))?;
+		stream.set_write_timeout(Some(TIMEOUT))?;
+
+		let mut client = Self { stream, next_id: 1 };
+		let id = client.send(TYPE_AUTH, password)?;
+		// Some servers send an empty RESPONSE_VALUE before the auth reply.
+		loop {
+			let packet = read_packet(&mut client.stream)?;
+			if packet.kind == TYPE_RESPONSE {
+				continue;
+			}
+			if packet.id == -1 {
+				anyhow::bail!("RCON authentication failed (wrong password)");
+			}
+			if packet.id == id {
+				return Ok(client);
+			}
+		}
+	}
+
+	/// Runs a command and joins the response fragments.
+	pub(super) fn command(&mut self, command: &str) -> anyhow::Result<String> {
+		if command.len() > MAX_COMMAND_LEN {
+			anyhow::bail!(
+				"Command too long for RCON ({} bytes)",
+				command.len()
+			);
+		}
+
+		let id = self.send(TYPE_COMMAND, command)?;
+		let sentinel = self.send(TYPE_SENTINEL, "")?;
+
+		let mut body = String::new();
+		loop {
+			let packet = read_packet(&mut self.stream)?;
+			if packet.id == sentinel {
+				return Ok(body);
+			}
+			if packet.id == id {
+				body.push_str(&packet.body);
+			}
+		}
+	}
+
+	fn send(&mut self, kind: i32, body: &str) -> anyhow::Result<i32> {
+		let id = self.next_id;
+		self.next_id = self.next_id.wrapping_add(1).max(1);
+		self.stream.write_all(&encode_packet(id, kind, body))?;
+		Ok(id)
+	}
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Packet {
+	id: i32,
+	kind: i32,
+	body: String,
+}
+
+/// `length | id | type | body \0 | \0`, integers little-endian.
+fn encode_packet(id: i32, kind: i32, body: &str) -> Vec<u8> {
+	let len = (4 + 4 + body.len() + 2) as i32;
+	let mut out = Vec::with_capacity(len as usize + 4);
+	out.extend_from_slice(&len.to_le_bytes());
+	out.extend_from_slice(&id.to_le_bytes());
+	out.extend_from_slice(&kind.to_le_bytes());
+	out.extend_from_slice(body.as_bytes());
+	out.extend_from_slice(&[0, 0]);
+	out
+}
+
+fn read_packet(reader: &mut impl Read) -> anyhow::Result<Packet> {
+	let mut word = [0u8; 4];
+	reader.read_exact(&mut word)?;
+	let len = i32::from_le_bytes(word);
+	if !(10..=1 << 20).contains(&len) {
+		anyhow::bail!("Invalid RCON packet length: {len}");
+	}
+
+	let mut buf = vec![0u8; len as usize];
+	reader.read_exact(&mut buf)?;
+	let id = i32::from_le_bytes(buf[0..4].try_into()?);
+	let kind = i32::from_le_bytes(buf[4..8].try_into()?);
+	let body = &buf[8..];
+	let end = body.iter().position(|b| *b == 0).unwrap_or(body.len());
+
+	Ok(Packet {
+		id,
+		kind,
+		body: String::from_utf8_lossy(&body[..end]).into_owned(),
+	})
+}
+
+/// Drops `§x` formatting codes, which terminals can't render.
+pub(super) fn strip_formatting(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		if c == '§' {
+			chars.next();
+		} else {
+			out.push(c);
+		}
+	}
+	out
+}
+
+pub(super) fn run_rcon(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server",
+	)?;
+	let config = Config::from_properties(&server)?;
+	let host = matches
+		.get_one::<String>("host")
+		.map(|s| s.as_str())
+		.unwrap_or("127.0.0.1");
+
+	let mut client = Client::connect(host, config.port, &config.password)?;
+
+	let words: Vec<&str> = matches
+		.get_many::<String>("command")
+		.map(|v| v.map(|s| s.as_str()).collect())
+		.unwrap_or_default();
+	if !words.is_empty() {
+		let reply = client.command(&words.join(" "))?;
+		print_reply(&reply);
+		return Ok(());
+	}
+
+	println!(
+		"Connected to {} over RCON ({host}:{}). Type `exit` to quit.",
+		server.name(),
+		config.port
+	);
+	let stdin = std::io::stdin();
+	let mut lines = stdin.lock().lines();
+	loop {
+		print!("rcon> ");
+		std::io::stdout().flush()?;
+		let Some(line) = lines.next() else {
+			break;
+		};
+		let line = line?;
+		let line = line.trim();
+		match line {
+			"" => continue,
+			"exit" | "quit" => break,
+			_ => print_reply(&client.command(line)?),
+		}
+	}
+	Ok(())
+}
+
+fn print_reply(reply: &str) {
+	let reply = strip_formatting(reply);
+	if !reply.trim().is_empty() {
+		println!("{}", reply.trim_end());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn packet_round_trip() {
+		let bytes = encode_packet(7, TYPE_COMMAND, "list");
+		assert_eq!(&bytes[..4], &14i32.to_le_bytes());
+		assert_eq!(bytes.len(), 18);
+
+		let packet = read_packet(&mut std::io::Cursor::new(bytes)).unwrap();
+		assert_eq!(
+			packet,
+			Packet {
+				id: 7,
+				kind: TYPE_COMMAND,
+				body: "list".to_string()
+			}
+		);
+	}
+
+	#[test]
+	fn rejects_bogus_lengths() {
+		let mut bytes = encode_packet(1, TYPE_AUTH, "");
+		bytes[..4].copy_from_slice(&3i32.to_le_bytes());
+		assert!(read_packet(&mut std::io::Cursor::new(bytes)).is_err());
+	}
+
+	#[test]
+	fn strips_section_sign_codes() {
+		assert_eq!(
+			strip_formatting("§6There are §c1§6 players"),
+			"There are 1 players"
+		);
+	}
+}
@@ -0,0 +1,268 @@
+//! `start --restart`: rerun a supervised server after it exits, backing off
+//! between attempts and keeping evidence of each crash.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::console::{self, Outcome};
+
+pub(super) const INCIDENT_DIR: &str = "eagle-incidents";
+const BASE_DELAY: Duration = Duration::from_secs(5);
+const MAX_DELAY: Duration = Duration::from_secs(300);
+const TAIL_LINES: usize = 200;
+const MTIME_SLACK: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Policy {
+	Never,
+	OnFailure,
+	Always,
+}
+
+impl Policy {
+	pub(super) const ALL: [Self; 3] =
+		[Self::Never, Self::OnFailure, Self::Always];
+
+	pub(super) fn as_str(self) -> &'static str {
+		match self {
+			Self::Never => "never",
+			Self::OnFailure => "on-failure",
+			Self::Always => "always",
+		}
+	}
+
+	pub(super) fn parse(s: &str) -> anyhow::Result<Self> {
+		Self::ALL
+			.into_iter()
+			.find(|p| p.as_str() == s.trim())
+			.ok_or_else(|| anyhow::anyhow!("Invalid restart policy: {s}"))
+	}
+
+	pub(super) fn names() -> Vec<&'static str> {
+		Self::ALL.iter().map(|p| p.as_str()).collect()
+	}
+
+	/// A stop the user asked for (Ctrl+C) never restarts.
+	fn should_restart(self, success: bool, requested: bool) -> bool {
+		match self {
+			_ if requested => false,
+			Self::Never => false,
+			Self::OnFailure => !success,
+			Self::Always => true,
+		}
+	}
+}
+
+/// Restarts allowed within a sliding window.
+pub(super) struct Limit {
+	pub(super) max: usize,
+	pub(super) window: Duration,
+}
+
+/// Remembers recent restarts to enforce the cap and grow the delay.
+struct Tracker {
+	limit: Limit,
+	recent: VecDeque<Instant>,
+}
+
+impl Tracker {
+	fn new(limit: Limit) -> Self {
+		Self {
+			limit,
+			recent: VecDeque::new(),
+		}
+	}
+
+	/// Records a restart at `now`; `None` once the cap is hit.
+	fn next_delay(&mut self, now: Instant) -> Option<Duration> {
+		while self
+			.recent
+			.front()
+			.is_some_and(|t| now.duration_since(*t) > self.limit.window)
+		{
+			self.recent.pop_front();
+		}
+		if self.recent.len() >= self.limit.max {
+			return None;
+		}
+
+		let delay = backoff_delay(self.recent.len());
+		self.recent.push_back(now);
+		Some(delay)
+	}
+}
+
+/// 5s, 10s, 20s, ... capped at five minutes.
+fn backoff_delay(attempt: usize) -> Duration {
+	let factor = 1u32 << attempt.min(16);
+	BASE_DELAY.saturating_mul(factor).min(MAX_DELAY)
+}
+
+/// Runs `launch` until the policy says stop. Returns the last outcome.
+pub(super) fn run(
+	server_dir: &Path,
+	policy: Policy,
+	limit: Limit,
+	mut launch: impl FnMut() -> anyhow::Result<Outcome>,
+) -> anyhow::Result<Outcome> {
+	let mut tracker = Tracker::new(limit);
+	loop {
+		let outcome = launch()?;
+		let success = outcome.status.success();
+
+		if !success && !outcome.requested {
+			match capture_incident(server_dir, &outcome) {
+				Ok(dir) => {
+					println!("[eagle] Crash details saved to {}", dir.display())
+				}
+				Err(e) => {
+					eprintln!("[eagle] Could not save crash details: {e}")
+				}
+			}
+		}
+
+		if !policy.should_restart(success, outcome.requested) {
+			return Ok(outcome);
+		}
+
+		let Some(delay) = tracker.next_delay(Instant::now()) else {
+			anyhow::bail!(
+				"Restart limit reached ({} restarts within {}s); last exit: {}",
+				tracker.limit.max,
+				tracker.limit.window.as_secs(),
+				outcome.status
+			);
+		};
+
+		println!(
+			"[eagle] Server exited ({}); restarting in {}s (Ctrl+C to cancel)",
+			outcome.status,
+			delay.as_secs()
+		);
+		if console::sleep_interruptible(delay)? {
+			println!("[eagle] Restart cancelled");
+			return Ok(outcome);
+		}
+	}
+}
+
+/// Copies the newest crash report written during the run plus the tails of
+/// `logs/latest.log` and eagle's console log into a fresh incident folder.
+fn capture_incident(
+	server_dir: &Path,
+	outcome: &Outcome,
+) -> anyhow::Result<PathBuf> {
+	let dir = server_dir.join(INCIDENT_DIR).join(console::stamp());
+	std::fs::create_dir_all(&dir)?;
+
+	let mut summary = format!(
+		"exit: {}\nran for: {}s\nconsole log: {}\n",
+		outcome.status,
+		outcome.ran_for.as_secs(),
+		outcome.log.display()
+	);
+
+	// Some filesystems only keep mtimes to the nearest second or two.
+	let since = SystemTime::now() - outcome.ran_for - MTIME_SLACK;
+	if let Some(report) = newest_crash_report(server_dir, since)? {
+		let name = report.file_name().unwrap_or_default();
+		std::fs::copy(&report, dir.join(name))?;
+		summary.push_str(&format!("crash report: {}\n", report.display()));
+	}
+
+	let latest = server_dir.join("logs").join("latest.log");
+	for (src, dst) in [
+		(latest.as_path(), "latest.log.tail"),
+		(outcome.log.as_path(), "console.log.tail"),
+	] {
+		if let Ok(text) = std::fs::read(src) {
+			let text = String::from_utf8_lossy(&text);
+			std::fs::write(dir.join(dst), tail_lines(&text, TAIL_LINES))?;
+		}
+	}
+
+	std::fs::write(dir.join("incident.txt"), summary)?;
+	Ok(dir)
+}
+
+fn newest_crash_report(
+	server_dir: &Path,
+	since: SystemTime,
+) -> anyhow::Result<Option<PathBuf>> {
+	let dir = server_dir.join("crash-reports");
+	if !dir.is_dir() {
+		return Ok(None);
+	}
+
+	let mut newest: Option<(SystemTime, PathBuf)> = None;
+	for entry in std::fs::read_dir(dir)? {
+		let entry = entry?;
+		let modified = entry.metadata()?.modified()?;
+		if modified < since {
+			continue;
+		}
+		if newest.as_ref().is_none_or(|(t, _)| modified > *t) {
+			newest = Some((modified, entry.path()));
+		}
+	}
+	Ok(newest.map(|(_, p)| p))
+}
+
+fn tail_lines(text: &str, n: usize) -> String {
+	let lines: Vec<&str> = text.lines().collect();
+	let start = lines.len().saturating_sub(n);
+	let mut out = lines[start..].join("\n");
+	out.push('\n');
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn policy_decisions() {
+		assert!(Policy::OnFailure.should_restart(false, false));
+		assert!(!Policy::OnFailure.should_restart(true, false));
+		assert!(Policy::Always.should_restart(true, false));
+		assert!(!Policy::Always.should_restart(false, true));
+		assert!(!Policy::Never.should_restart(false, false));
+	}
+
+	#[test]
+	fn backoff_doubles_up_to_cap() {
+		assert_eq!(backoff_delay(0), Duration::from_secs(5));
+		assert_eq!(backoff_delay(1), Duration::from_secs(10));
+		assert_eq!(backoff_delay(3), Duration::from_secs(40));
+		assert_eq!(backoff_delay(10), MAX_DELAY);
+		assert_eq!(backoff_delay(usize::MAX), MAX_DELAY);
+	}
+
+	#[test]
+	fn tracker_caps_restarts_per_window() {
+		let mut tracker = Tracker::new(Limit {
+			max: 2,
+			window: Duration::from_secs(60),
+		});
+		let t0 = Instant::now();
+		assert_eq!(tracker.next_delay(t0), Some(Duration::from_secs(5)));
+		assert_eq!(
+			tracker.next_delay(t0 + Duration::from_secs(10)),
+			Some(Duration::from_secs(10))
+		);
+		assert_eq!(tracker.next_delay(t0 + Duration::from_secs(20)), None);
+
+		// Both earlier restarts have left the window.
+		assert_eq!(
+			tracker.next_delay(t0 + Duration::from_secs(90)),
+			Some(Duration::from_secs(5))
+		);
+	}
+
+	#[test]
+	fn tail_keeps_last_lines() {
+		assert_eq!(tail_lines("a\nb\nc\n", 2), "b\nc\n");
+		assert_eq!(tail_lines("a", 5), "a\n");
+	}
+}
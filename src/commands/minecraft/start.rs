@@ -1,53 +1,207 @@
-use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::ArgMatches;
-use dialoguer::Select;
+use clap::{Arg, ArgMatches, Command};
 
+use super::backup;
+use super::console;
 use super::fs;
+use super::java;
+use super::manifest::{self, JvmProfile};
+use super::ports;
+use super::restart;
 
-pub(super) fn run_start(matches: &ArgMatches) -> anyhow::Result<()> {
-	let ram_mb = *matches.get_one::<u32>("ram_mb").unwrap_or(&8192);
+const DEFAULT_RAM_MB: u32 = 8192;
+/// Aikar's recommended cut-over to the large-heap G1 values.
+const LARGE_HEAP_MB: u32 = 12 * 1024;
+
+pub(super) fn build_command() -> Command {
+	Command::new("start")
+		.about("Start a server (picker when no name is given)")
+		.arg(
+			Arg::new("name")
+				.help("Server folder name (under a servers root)")
+				.required(false),
+		)
+		.args(start_args())
+}
+
+/// Flags shared by `minecraft start` and the bare `minecraft` picker.
+pub(super) fn start_args() -> Vec<Arg> {
+	let profile_help = format!(
+		"JVM flag profile: {} (default: auto)",
+		JvmProfile::names().join(", ")
+	);
+
+	vec![
+		Arg::new("ram_mb")
+			.long("ram-mb")
+			.help("Max heap in MB (-Xmx)")
+			.value_parser(clap::value_parser!(u32))
+			.required(false),
+		Arg::new("min_ram_mb")
+			.long("min-ram-mb")
+			.help("Initial heap in MB (-Xms, default: same as --ram-mb)")
+			.value_parser(clap::value_parser!(u32))
+			.required(false),
+		Arg::new("profile")
+			.long("profile")
+			.help(profile_help)
+			.value_parser(JvmProfile::names())
+			.required(false),
+		Arg::new("jvm_arg")
+			.long("jvm-arg")
+			.help("Extra JVM argument (repeatable)")
+			.action(clap::ArgAction::Append)
+			.allow_hyphen_values(true),
+		Arg::new("server_arg")
+			.long("server-arg")
+			.help("Extra server argument after nogui (repeatable)")
+			.action(clap::ArgAction::Append)
+			.allow_hyphen_values(true),
+		Arg::new("dry_run")
+			.long("dry-run")
+			.help("Print the command line instead of starting the server")
+			.action(clap::ArgAction::SetTrue),
+		Arg::new("supervise")
+			.long("supervise")
+			.help(
+				"Pipe the console through eagle (log, startup time, clean Ctrl+C stop)",
+			)
+			.action(clap::ArgAction::SetTrue),
+		Arg::new("stop_timeout")
+			.long("stop-timeout")
+			.help(
+				"Seconds to wait for a supervised server to stop before killing it",
+			)
+			.value_parser(clap::value_parser!(u64))
+			.default_value("60"),
+		Arg::new("backup_every")
+			.long("backup-every")
+			.help("Live backup interval in minutes (implies --supervise)")
+			.value_parser(clap::value_parser!(u64)),
+		Arg::new("restart")
+			.long("restart")
+			.help(format!(
+				"Restart policy: {} (implies --supervise)",
+				restart::Policy::names().join(", ")
+			))
+			.value_parser(restart::Policy::names())
+			.default_value("never"),
+		Arg::new("restart_max")
+			.long("restart-max")
+			.help("Give up after this many restarts within --restart-window")
+			.value_parser(clap::value_parser!(usize))
+			.default_value("5"),
+		Arg::new("restart_window")
+			.long("restart-window")
+			.help("Window in seconds for --restart-max")
+			.value_parser(clap::value_parser!(u64))
+			.default_value("600"),
+		Arg::new("java")
+			.long("java")
+			.help("Java executable (default: detected per server version)")
+			.required(false),
+		Arg::new("install_java")
+			.long("install-java")
+			.help("Download a matching Temurin runtime if none is installed")
+			.action(clap::ArgAction::SetTrue),
+	]
+}
+
+/// A fully resolved java invocation for one server.
+pub(super) struct LaunchPlan {
+	pub(super) java: PathBuf,
+	pub(super) args: Vec<String>,
+	pub(super) dir: PathBuf,
+}
 
-	let root = fs::servers_root()?;
-	let servers = fs::find_servers(&root)?;
-	if servers.is_empty() {
-		anyhow::bail!("No servers found in: {}", root.display());
+impl LaunchPlan {
+	pub(super) fn command(&self) -> std::process::Command {
+		let mut cmd = std::process::Command::new(&self.java);
+		cmd.args(&self.args).current_dir(&self.dir);
+		cmd
 	}
 
-	let items: Vec<String> = servers
-		.iter()
-		.map(|p| {
-			p.file_name()
-				.and_then(|s| s.to_str())
-				.unwrap_or("server")
-				.to_string()
-		})
-		.collect();
+	/// The command line as it could be pasted into a POSIX shell.
+	pub(super) fn command_line(&self) -> String {
+		std::iter::once(self.java.to_string_lossy().into_owned())
+			.chain(self.args.iter().cloned())
+			.map(|a| shell_quote(&a))
+			.collect::<Vec<_>>()
+			.join(" ")
+	}
+}
+
+pub(super) fn run_start(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let name = matches.try_get_one::<String>("name").ok().flatten();
+	let mut server = fs::select_server(
+		&roots,
+		name.map(|s| s.as_str()),
+		"Select a Minecraft server",
+	)?;
 
-	let selection = Select::new()
-		.with_prompt("Select a Minecraft server")
-		.items(&items)
-		.default(0)
-		.interact()?;
+	// Before `plan`, which may download Java for a server that cannot start.
+	if !matches.get_flag("dry_run") {
+		ports::ensure_free(&roots, &server)?;
+	}
+	let plan = plan(matches, &mut server)?;
 
-	let server_path = &servers[selection];
-	let jar_path = server_path.join("server.jar");
-	if !jar_path.exists() {
-		anyhow::bail!("server.jar not found: {}", jar_path.display());
+	if matches.get_flag("dry_run") {
+		println!("cd {}", shell_quote(&plan.dir.to_string_lossy()));
+		println!("{}", plan.command_line());
+		return Ok(());
 	}
 
 	crossterm::execute!(
 		std::io::stdout(),
-		crossterm::terminal::SetTitle(format!(
-			"MC-SERVER: {}",
-			items[selection]
-		))
+		crossterm::terminal::SetTitle(format!("MC-SERVER: {}", server.name()))
 	)?;
+	if let Some(m) = server.manifest.as_mut() {
+		m.last_started_at = Some(manifest::unix_now());
+		manifest::save(&server.path, m)?;
+	}
+
+	let policy = match matches.get_one::<String>("restart") {
+		Some(p) => restart::Policy::parse(p)?,
+		None => restart::Policy::Never,
+	};
 
-	let java_args = build_java_args(ram_mb, &jar_path);
-	let status = std::process::Command::new("java")
-		.args(java_args)
-		.current_dir(server_path)
+	let opts = console_options(matches, &server)?;
+	let interval = opts.backup.as_ref().and_then(|b| b.interval);
+	if matches.get_flag("supervise")
+		|| policy != restart::Policy::Never
+		|| interval.is_some()
+	{
+		let limit = restart::Limit {
+			max: matches
+				.get_one::<usize>("restart_max")
+				.copied()
+				.unwrap_or(5),
+			window: Duration::from_secs(
+				matches
+					.get_one::<u64>("restart_window")
+					.copied()
+					.unwrap_or(600),
+			),
+		};
+		let outcome = restart::run(&plan.dir, policy, limit, || {
+			console::supervise(&plan, &opts)
+		})?;
+		if !outcome.status.success() && !outcome.requested {
+			anyhow::bail!(
+				"java exited with: {} (log: {})",
+				outcome.status,
+				outcome.log.display()
+			);
+		}
+		println!("Server stopped.");
+		return Ok(());
+	}
+
+	let status = plan
+		.command()
 		.stdin(std::process::Stdio::inherit())
 		.stdout(std::process::Stdio::inherit())
 		.stderr(std::process::Stdio::inherit())
@@ -61,37 +215,271 @@ pub(super) fn run_start(matches: &ArgMatches) -> anyhow::Result<()> {
 	Ok(())
 }
 
-fn build_java_args(ram_mb: u32, jar_path: &Path) -> Vec<String> {
-	let ram = format!("-Xmx{ram_mb}M");
-	let ram2 = format!("-Xms{ram_mb}M");
-
-	let args = vec![
-		ram,
-		ram2,
-		"-XX:+UseG1GC".to_string(),
-		"-XX:+ParallelRefProcEnabled".to_string(),
-		"-XX:MaxGCPauseMillis=200".to_string(),
-		"-XX:+UnlockExperimentalVMOptions".to_string(),
-		"-XX:+DisableExplicitGC".to_string(),
-		"-XX:+AlwaysPreTouch".to_string(),
-		"-XX:G1NewSizePercent=30".to_string(),
-		"-XX:G1MaxNewSizePercent=40".to_string(),
-		"-XX:G1HeapRegionSize=8M".to_string(),
-		"-XX:G1ReservePercent=20".to_string(),
-		"-XX:G1HeapWastePercent=5".to_string(),
-		"-XX:G1MixedGCCountTarget=4".to_string(),
-		"-XX:InitiatingHeapOccupancyPercent=15".to_string(),
-		"-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
-		"-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
-		"-XX:SurvivorRatio=32".to_string(),
-		"-XX:+PerfDisableSharedMem".to_string(),
-		"-XX:MaxTenuringThreshold=1".to_string(),
-		"-Daikars.new.flags=true".to_string(),
-		"-Dusing.aikars.flags=https://mcutils.com".to_string(),
-		"-jar".to_string(),
-		jar_path.to_string_lossy().to_string(),
-		"nogui".to_string(),
+/// Proxies have no world to back up and stop with `end`.
+fn console_options(
+	matches: &ArgMatches,
+	server: &fs::Server,
+) -> anyhow::Result<console::Options> {
+	let is_proxy = server
+		.manifest
+		.as_ref()
+		.is_some_and(|m| m.server_type.is_proxy());
+	let timeout = matches.get_one::<u64>("stop_timeout").copied();
+	let backup = match is_proxy {
+		true => None,
+		false => Some(backup::job_for(server, matches)?),
+	};
+
+	Ok(console::Options {
+		stop_command: if is_proxy { "end" } else { "stop" },
+		stop_timeout: Duration::from_secs(timeout.unwrap_or(60)),
+		backup,
+	})
+}
+
+/// Flags win over the manifest, which wins over the defaults.
+pub(super) fn plan(
+	matches: &ArgMatches,
+	server: &mut fs::Server,
+) -> anyhow::Result<LaunchPlan> {
+	let m = server.manifest.as_ref();
+
+	let max_mb = matches
+		.get_one::<u32>("ram_mb")
+		.copied()
+		.or_else(|| m.and_then(|m| m.ram_mb))
+		.unwrap_or(DEFAULT_RAM_MB);
+	let min_mb = matches
+		.get_one::<u32>("min_ram_mb")
+		.copied()
+		.or_else(|| m.and_then(|m| m.min_ram_mb))
+		.unwrap_or(max_mb);
+	if min_mb > max_mb {
+		anyhow::bail!(
+			"Initial heap ({min_mb} MB) is larger than max heap ({max_mb} MB)"
+		);
+	}
+
+	let profile = match matches.get_one::<String>("profile") {
+		Some(p) => JvmProfile::parse(p)?,
+		None => m.and_then(|m| m.profile).unwrap_or_default(),
+	};
+
+	let launch = m.map(|m| m.launch.clone()).unwrap_or_default();
+	let target = server.path.join(launch.target());
+	if !target.exists() {
+		anyhow::bail!("Launch target not found: {}", target.display());
+	}
+
+	let mut args = heap_args(min_mb, max_mb);
+	args.extend(profile_flags(profile, max_mb).iter().map(|s| s.to_string()));
+	if let Some(m) = m {
+		args.extend(m.jvm_args.iter().cloned());
+	}
+	args.extend(flag_values(matches, "jvm_arg"));
+	args.extend(launch.java_args());
+
+	if !m.is_some_and(|m| m.server_type.is_proxy()) {
+		args.push("nogui".to_string());
+	}
+	if let Some(m) = m {
+		args.extend(m.server_args.iter().cloned());
+	}
+	args.extend(flag_values(matches, "server_arg"));
+
+	let java = resolve_java(matches, server)?;
+	Ok(LaunchPlan {
+		java,
+		args,
+		dir: server.path.clone(),
+	})
+}
+
+fn flag_values(matches: &ArgMatches, id: &str) -> Vec<String> {
+	matches
+		.get_many::<String>(id)
+		.map(|v| v.cloned().collect())
+		.unwrap_or_default()
+}
+
+/// `--java` wins, then the manifest's explicit path, then a detected (or,
+/// with `--install-java`, downloaded) runtime matching the server's version.
+fn resolve_java(
+	matches: &ArgMatches,
+	server: &mut fs::Server,
+) -> anyhow::Result<PathBuf> {
+	if let Some(path) = matches.get_one::<String>("java") {
+		return Ok(PathBuf::from(path));
+	}
+
+	let Some(m) = server.manifest.as_mut() else {
+		return Ok(PathBuf::from("java"));
+	};
+	if let Some(path) = &m.java {
+		return Ok(PathBuf::from(path));
+	}
+
+	// A dry run changes nothing: no manifest write, no Java download.
+	let dry_run = matches.get_flag("dry_run");
+	let required = match m.java_major {
+		Some(major) => major,
+		None => {
+			let major = java::required_major(m.server_type, &m.version);
+			if !dry_run {
+				m.java_major = Some(major);
+				manifest::save(&server.path, m)?;
+			}
+			major
+		}
+	};
+
+	let install = matches.get_flag("install_java") && !dry_run;
+	let runtime = match java::resolve(required, install) {
+		Ok(runtime) => runtime,
+		Err(e) if dry_run => {
+			println!("# No matching Java: {e}");
+			return Ok(PathBuf::from("java"));
+		}
+		Err(e) => anyhow::bail!("{e} or pass --install-java"),
+	};
+	println!("Using Java {} ({})", runtime.major, runtime.java.display());
+	Ok(runtime.java)
+}
+
+fn heap_args(min_mb: u32, max_mb: u32) -> Vec<String> {
+	vec![format!("-Xms{min_mb}M"), format!("-Xmx{max_mb}M")]
+}
+
+/// Flags for a profile; see https://docs.papermc.io/paper/aikars-flags.
+fn profile_flags(profile: JvmProfile, max_mb: u32) -> Vec<&'static str> {
+	let profile = match profile {
+		JvmProfile::Auto if max_mb >= LARGE_HEAP_MB => {
+			JvmProfile::AikarLargeHeap
+		}
+		JvmProfile::Auto => JvmProfile::Aikar,
+		other => other,
+	};
+
+	let g1_sizing = match profile {
+		JvmProfile::Aikar => [
+			"-XX:G1NewSizePercent=30",
+			"-XX:G1MaxNewSizePercent=40",
+			"-XX:G1HeapRegionSize=8M",
+			"-XX:G1ReservePercent=20",
+			"-XX:InitiatingHeapOccupancyPercent=15",
+		],
+		JvmProfile::AikarLargeHeap => [
+			"-XX:G1NewSizePercent=40",
+			"-XX:G1MaxNewSizePercent=50",
+			"-XX:G1HeapRegionSize=16M",
+			"-XX:G1ReservePercent=15",
+			"-XX:InitiatingHeapOccupancyPercent=20",
+		],
+		JvmProfile::Zgc => {
+			return vec![
+				"-XX:+UseZGC",
+				"-XX:+AlwaysPreTouch",
+				"-XX:+DisableExplicitGC",
+				"-XX:+PerfDisableSharedMem",
+			];
+		}
+		JvmProfile::Plain | JvmProfile::Auto => return Vec::new(),
+	};
+
+	let mut flags = vec![
+		"-XX:+UseG1GC",
+		"-XX:+ParallelRefProcEnabled",
+		"-XX:MaxGCPauseMillis=200",
+		"-XX:+UnlockExperimentalVMOptions",
+		"-XX:+DisableExplicitGC",
+		"-XX:+AlwaysPreTouch",
 	];
+	flags.extend(g1_sizing);
+	flags.extend([
+		"-XX:G1HeapWastePercent=5",
+		"-XX:G1MixedGCCountTarget=4",
+		"-XX:G1MixedGCLiveThresholdPercent=90",
+		"-XX:G1RSetUpdatingPauseTimePercent=5",
+		"-XX:SurvivorRatio=32",
+		"-XX:+PerfDisableSharedMem",
+		"-XX:MaxTenuringThreshold=1",
+		"-Daikars.new.flags=true",
+		"-Dusing.aikars.flags=https://mcutils.com",
+	]);
+	flags
+}
 
-	args
+/// Leaves plain words alone and single-quotes everything else.
+fn shell_quote(s: &str) -> String {
+	let safe = !s.is_empty()
+		&& s.chars()
+			.all(|c| c.is_ascii_alphanumeric() || "-_./:=@+%,".contains(c));
+	if safe {
+		s.to_string()
+	} else {
+		format!("'{}'", s.replace('\'', r"'\''"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn auto_profile_switches_at_twelve_gigabytes() {
+		let small = profile_flags(JvmProfile::Auto, 8192);
+		assert!(small.contains(&"-XX:G1HeapRegionSize=8M"));
+		assert_eq!(small, profile_flags(JvmProfile::Aikar, 8192));
+
+		let large = profile_flags(JvmProfile::Auto, 16384);
+		assert!(large.contains(&"-XX:G1HeapRegionSize=16M"));
+		assert!(large.contains(&"-XX:G1NewSizePercent=40"));
+		assert!(!large.contains(&"-XX:G1NewSizePercent=30"));
+	}
+
+	#[test]
+	fn zgc_and_plain_profiles() {
+		let zgc = profile_flags(JvmProfile::Zgc, 8192);
+		assert_eq!(zgc[0], "-XX:+UseZGC");
+		assert!(!zgc.contains(&"-XX:+UseG1GC"));
+		assert!(profile_flags(JvmProfile::Plain, 32768).is_empty());
+	}
+
+	#[test]
+	fn heap_args_keep_min_and_max_separate() {
+		assert_eq!(heap_args(2048, 8192), ["-Xms2048M", "-Xmx8192M"]);
+	}
+
+	#[test]
+	fn quotes_only_when_needed() {
+		assert_eq!(shell_quote("-Xmx8192M"), "-Xmx8192M");
+		assert_eq!(shell_quote("/opt/my java/bin"), "'/opt/my java/bin'");
+		assert_eq!(shell_quote("it's"), r"'it'\''s'");
+		assert_eq!(shell_quote(""), "''");
+	}
+
+	#[test]
+	fn dry_run_reports_missing_java_instead_of_failing() {
+		let dir = fs::TestDir::new("start");
+		std::fs::write(dir.join("server.jar"), b"").unwrap();
+		let mut m = manifest::ServerManifest::new(
+			manifest::ServerType::Paper,
+			"1.21.4",
+			25565,
+		);
+		m.java_major = Some(999);
+		let mut server = fs::Server {
+			path: dir.to_path_buf(),
+			manifest: Some(m),
+		};
+
+		let args = |extra: &[&str]| {
+			let mut argv = vec!["start", "s"];
+			argv.extend_from_slice(extra);
+			build_command().try_get_matches_from(argv).unwrap()
+		};
+		let dry = plan(&args(&["--dry-run"]), &mut server).unwrap();
+		assert_eq!(dry.java, PathBuf::from("java"));
+		assert!(plan(&args(&[]), &mut server).is_err());
+	}
 }
@@ -0,0 +1,431 @@
+//! `minecraft status`: Server List Ping (the multiplayer screen's protocol),
+//! with the pre-1.7 legacy ping as a fallback.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgMatches, Command};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::fs;
+use super::manifest;
+use super::rcon;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_PORT: u16 = 25565;
+/// Sent as the protocol version in the handshake; servers answer a status
+/// request regardless, and -1 is the documented "just pinging" value.
+const PING_PROTOCOL: i32 = -1;
+/// Largest status response we accept (the spec caps strings at 32767 chars).
+const MAX_PACKET: i32 = 1 << 21;
+
+pub(super) fn build_command() -> Command {
+	Command::new("status")
+		.about("Ping a server (version, MOTD, players)")
+		.arg(
+			Arg::new("target")
+				.help("Server folder name or host[:port] (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("json")
+				.long("json")
+				.help("Print the result as JSON")
+				.action(clap::ArgAction::SetTrue),
+		)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(super) struct Status {
+	pub(super) version: String,
+	pub(super) protocol: i64,
+	pub(super) motd: String,
+	pub(super) online: i64,
+	pub(super) max: i64,
+	pub(super) sample: Vec<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub(super) latency_ms: Option<u64>,
+	/// Answered only the pre-1.7 ping.
+	pub(super) legacy: bool,
+}
+
+pub(super) fn run_status(matches: &ArgMatches) -> anyhow::Result<()> {
+	let target = matches.get_one::<String>("target").map(|s| s.as_str());
+	let (host, port) = resolve_target(target, fs::server_port)?;
+
+	let status = ping(&host, port)?;
+	if matches.get_flag("json") {
+		println!("{}", serde_json::to_string_pretty(&status)?);
+		return Ok(());
+	}
+
+	println!("{host}:{port} is online");
+	println!(
+		"Version:  {} (protocol {})",
+		status.version, status.protocol
+	);
+	println!("MOTD:     {}", status.motd.replace('\n', "\n          "));
+	println!("Players:  {}/{}", status.online, status.max);
+	if !status.sample.is_empty() {
+		println!("          {}", status.sample.join(", "));
+	}
+	if let Some(ms) = status.latency_ms {
+		println!("Latency:  {ms} ms");
+	}
+	if status.legacy {
+		println!("(legacy ping)");
+	}
+	Ok(())
+}
+
+/// A local server name maps to `127.0.0.1` and the port `port_of` picks
+/// for it; `host:port`, or a name no local server has, is read as
+/// `host[:port]`. Shared with `query`.
+pub(super) fn resolve_target(
+	target: Option<&str>,
+	port_of: impl Fn(&fs::Server) -> anyhow::Result<u16>,
+) -> anyhow::Result<(String, u16)> {
+	let server = match target {
+		None => {
+			let roots = fs::servers_roots()?;
+			fs::select_server(&roots, None, "Select a server")?
+		}
+		Some(t) if t.contains(':') => return parse_host_port(t),
+		Some(t) => match find_local(t) {
+			Some(server) => server,
+			None => return parse_host_port(t),
+		},
+	};
+	Ok(("127.0.0.1".to_string(), port_of(&server)?))
+}
+
+/// Errors scanning the local roots count as no match, so remote targets
+/// never depend on them.
+fn find_local(name: &str) -> Option<fs::Server> {
+	let roots = fs::servers_roots().ok()?;
+	fs::find_servers(&roots)
+		.ok()?
+		.into_iter()
+		.find(|s| s.name().eq_ignore_ascii_case(name))
+}
+
+pub(super) fn parse_host_port(s: &str) -> anyhow::Result<(String, u16)> {
+	// A bare IPv6 address has colons but no port.
+	if s.matches(':').count() > 1 && !s.starts_with('[') {
+		return Ok((s.to_string(), DEFAULT_PORT));
+	}
+	let (host, port) = match s.rsplit_once(':') {
+		Some((host, port)) => (
+			host,
+			port.parse()
+				.map_err(|_| anyhow::anyhow!("Invalid port in {s}"))?,
+		),
+		None => (s, DEFAULT_PORT),
+	};
+	let host = host.trim_start_matches('[').trim_end_matches(']');
+	if host.is_empty() {
+		anyhow::bail!("Invalid target: {s}");
+	}
+	Ok((host.to_string(), port))
+}
+
+/// Modern ping first; servers older than 1.7 only answer the legacy one.
+pub(super) fn ping(host: &str, port: u16) -> anyhow::Result<Status> {
+	let addr = resolve(host, port)?;
+	match ping_modern(addr, host, port) {
+		Ok(status) => Ok(status),
+		Err(modern) => ping_legacy(addr, host, port).map_err(|legacy| {
+			anyhow::anyhow!(
+				"{host}:{port} did not answer a status ping ({modern}; legacy: {legacy})"
+			)
+		}),
+	}
+}
+
+fn resolve(host: &str, port: u16) -> anyhow::Result<SocketAddr> {
+	(host, port)
+		.to_socket_addrs()?
+		.next()
+		.ok_or_else(|| anyhow::anyhow!("Could not resolve {host}"))
+}
+
+fn connect(addr: SocketAddr) -> anyhow::Result<TcpStream> {
+	let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+	stream.set_read_timeout(Some(TIMEOUT))?;
+	stream.set_write_timeout(Some(TIMEOUT))?;
+	Ok(stream)
+}
+
+fn ping_modern(
+	addr: SocketAddr,
+	host: &str,
+	port: u16,
+) -> anyhow::Result<Status> {
+	let mut stream = connect(addr)?;
+
+	let mut handshake = Vec::new();
+	write_varint(&mut handshake, 0x00);
+	write_varint(&mut handshake, PING_PROTOCOL);
+	write_string(&mut handshake, host);
+	handshake.extend_from_slice(&port.to_be_bytes());
+	write_varint(&mut handshake, 1);
+	send_packet(&mut stream, &handshake)?;
+	send_packet(&mut stream, &[0x00])?;
+
+	let body = read_packet(&mut stream)?;
+	let mut cursor = body.as_slice();
+	let id = read_varint(&mut cursor)?;
+	if id != 0x00 {
+		anyhow::bail!("unexpected packet id {id:#x}");
+	}
+	let json = read_string(&mut cursor)?;
+	let mut status = parse_status_json(&json)?;
+
+	// The payload is echoed back verbatim; any value works.
+	let mut ping = vec![0x01];
+	ping.extend_from_slice(&(manifest::unix_now() as i64).to_be_bytes());
+	let sent = Instant::now();
+	if send_packet(&mut stream, &ping).is_ok()
+		&& read_packet(&mut stream).is_ok()
+	{
+		status.latency_ms = Some(sent.elapsed().as_millis() as u64);
+	}
+	Ok(status)
+}
+
+fn send_packet(stream: &mut TcpStream, body: &[u8]) -> anyhow::Result<()> {
+	let mut framed = Vec::with_capacity(body.len() + 5);
+	write_varint(&mut framed, body.len() as i32);
+	framed.extend_from_slice(body);
+	stream.write_all(&framed)?;
+	Ok(())
+}
+
+fn read_packet(stream: &mut impl Read) -> anyhow::Result<Vec<u8>> {
+	let len = read_varint(stream)?;
+	if !(1..=MAX_PACKET).contains(&len) {
+		anyhow::bail!("invalid packet length {len}");
+	}
+	let mut body = vec![0u8; len as usize];
+	stream.read_exact(&mut body)?;
+	Ok(body)
+}
+
+fn write_varint(out: &mut Vec<u8>, value: i32) {
+	let mut v = value as u32;
+	loop {
+		if v & !0x7f == 0 {
+			out.push(v as u8);
+			return;
+		}
+		out.push((v & 0x7f) as u8 | 0x80);
+		v >>= 7;
+	}
+}
+
+fn read_varint(input: &mut impl Read) -> anyhow::Result<i32> {
+	let mut value = 0u32;
+	for i in 0..5 {
+		let mut byte = [0u8; 1];
+		input.read_exact(&mut byte)?;
+		value |= u32::from(byte[0] & 0x7f) << (7 * i);
+		if byte[0] & 0x80 == 0 {
+			return Ok(value as i32);
+		}
+	}
+	anyhow::bail!("VarInt too long")
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+	write_varint(out, s.len() as i32);
+	out.extend_from_slice(s.as_bytes());
+}
+
+fn read_string(input: &mut impl Read) -> anyhow::Result<String> {
+	let len = read_varint(input)?;
+	if !(0..MAX_PACKET).contains(&len) {
+		anyhow::bail!("invalid string length {len}");
+	}
+	let mut buf = vec![0u8; len as usize];
+	input.read_exact(&mut buf)?;
+	Ok(String::from_utf8(buf)?)
+}
+
+fn parse_status_json(json: &str) -> anyhow::Result<Status> {
+	let v: Value = serde_json::from_str(json)?;
+	let sample = v["players"]["sample"]
+		.as_array()
+		.map(|a| {
+			a.iter()
+				.filter_map(|p| p["name"].as_str())
+				.map(str::to_string)
+				.collect()
+		})
+		.unwrap_or_default();
+
+	Ok(Status {
+		version: v["version"]["name"].as_str().unwrap_or("?").to_string(),
+		protocol: v["version"]["protocol"].as_i64().unwrap_or(-1),
+		motd: rcon::strip_formatting(&chat_text(&v["description"])),
+		online: v["players"]["online"].as_i64().unwrap_or(0),
+		max: v["players"]["max"].as_i64().unwrap_or(0),
+		sample,
+		latency_ms: None,
+		legacy: false,
+	})
+}
+
+/// Flattens a chat component (string, `{text, extra}` or array) to text.
+fn chat_text(v: &Value) -> String {
+	match v {
+		Value::String(s) => s.clone(),
+		Value::Array(parts) => parts.iter().map(chat_text).collect(),
+		Value::Object(o) => {
+			let mut s = o.get("text").map(chat_text).unwrap_or_default();
+			if let Some(extra) = o.get("extra") {
+				s.push_str(&chat_text(extra));
+			}
+			s
+		}
+		_ => String::new(),
+	}
+}
+
+/// The 1.6 ping: `FE 01 FA` plus an `MC|PingHost` plugin message.
+fn ping_legacy(
+	addr: SocketAddr,
+	host: &str,
+	port: u16,
+) -> anyhow::Result<Status> {
+	let mut stream = connect(addr)?;
+
+	let host16: Vec<u16> = host.encode_utf16().collect();
+	let mut req = vec![0xfe, 0x01, 0xfa];
+	push_utf16(&mut req, &"MC|PingHost".encode_utf16().collect::<Vec<_>>());
+	req.extend_from_slice(&((7 + 2 * host16.len()) as u16).to_be_bytes());
+	req.push(74);
+	push_utf16(&mut req, &host16);
+	req.extend_from_slice(&i32::from(port).to_be_bytes());
+	stream.write_all(&req)?;
+
+	let mut reply = Vec::new();
+	stream.read_to_end(&mut reply)?;
+	parse_legacy(&reply)
+}
+
+fn push_utf16(out: &mut Vec<u8>, units: &[u16]) {
+	out.extend_from_slice(&(units.len() as u16).to_be_bytes());
+	for u in units {
+		out.extend_from_slice(&u.to_be_bytes());
+	}
+}
+
+/// `FF`, a length, then UTF-16BE `§1\0protocol\0version\0motd\0online\0max`.
+fn parse_legacy(reply: &[u8]) -> anyhow::Result<Status> {
+	if reply.len() < 3 || reply[0] != 0xff {
+		anyhow::bail!("not a legacy ping reply");
+	}
+	let units: Vec<u16> = reply[3..]
+		.chunks_exact(2)
+		.map(|c| u16::from_be_bytes([c[0], c[1]]))
+		.collect();
+	let text = String::from_utf16_lossy(&units);
+	let fields: Vec<&str> = text.split('\0').collect();
+	let [marker, protocol, version, motd, online, max] = fields[..] else {
+		anyhow::bail!("unexpected legacy reply: {text}");
+	};
+	if marker != "§1" {
+		anyhow::bail!("unexpected legacy reply: {text}");
+	}
+
+	Ok(Status {
+		version: version.to_string(),
+		protocol: protocol.parse().unwrap_or(-1),
+		motd: rcon::strip_formatting(motd),
+		online: online.parse().unwrap_or(0),
+		max: max.parse().unwrap_or(0),
+		sample: Vec::new(),
+		latency_ms: None,
+		legacy: true,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn varint_round_trip() {
+		for (value, bytes) in [
+			(0, vec![0x00]),
+			(1, vec![0x01]),
+			(127, vec![0x7f]),
+			(128, vec![0x80, 0x01]),
+			(25565, vec![0xdd, 0xc7, 0x01]),
+			(-1, vec![0xff, 0xff, 0xff, 0xff, 0x0f]),
+		] {
+			let mut out = Vec::new();
+			write_varint(&mut out, value);
+			assert_eq!(out, bytes, "encode {value}");
+			assert_eq!(read_varint(&mut bytes.as_slice()).unwrap(), value);
+		}
+		assert!(read_varint(&mut [0xff; 6].as_slice()).is_err());
+	}
+
+	#[test]
+	fn parses_status_json() {
+		let json = r#"{
+			"version": {"name": "Paper 1.21.4", "protocol": 769},
+			"players": {"max": 20, "online": 2,
+				"sample": [{"name": "Notch", "id": "x"}, {"name": "jeb_", "id": "y"}]},
+			"description": {"text": "§aHello", "extra": [{"text": " world"}]}
+		}"#;
+		let status = parse_status_json(json).unwrap();
+		assert_eq!(status.version, "Paper 1.21.4");
+		assert_eq!(status.protocol, 769);
+		assert_eq!(status.motd, "Hello world");
+		assert_eq!((status.online, status.max), (2, 20));
+		assert_eq!(status.sample, ["Notch", "jeb_"]);
+
+		let plain = r#"{"version":{"name":"1.8","protocol":47},"players":{"max":1,"online":0},"description":"hi"}"#;
+		assert_eq!(parse_status_json(plain).unwrap().motd, "hi");
+	}
+
+	#[test]
+	fn parses_legacy_reply() {
+		let text = "§1\u{0}74\u{0}1.6.4\u{0}A server\u{0}3\u{0}10";
+		let units: Vec<u16> = text.encode_utf16().collect();
+		let mut reply = vec![0xff];
+		reply.extend_from_slice(&(units.len() as u16).to_be_bytes());
+		for u in units {
+			reply.extend_from_slice(&u.to_be_bytes());
+		}
+
+		let status = parse_legacy(&reply).unwrap();
+		assert_eq!(status.version, "1.6.4");
+		assert_eq!(status.protocol, 74);
+		assert_eq!(status.motd, "A server");
+		assert_eq!((status.online, status.max), (3, 10));
+		assert!(status.legacy);
+	}
+
+	#[test]
+	fn host_port_targets_skip_local_servers() {
+		let target = resolve_target(Some("127.0.0.1:1"), |_| {
+			unreachable!("not a local server")
+		});
+		assert_eq!(target.unwrap(), ("127.0.0.1".into(), 1));
+	}
+
+	#[test]
+	fn parses_targets() {
+		let p = |s| parse_host_port(s).unwrap();
+		assert_eq!(p("mc.example.com"), ("mc.example.com".into(), 25565));
+		assert_eq!(p("mc.example.com:22222"), ("mc.example.com".into(), 22222));
+		assert_eq!(p("[::1]:25566"), ("::1".into(), 25566));
+		assert_eq!(p("::1"), ("::1".into(), 25565));
+		assert!(parse_host_port("host:abc").is_err());
+	}
+}
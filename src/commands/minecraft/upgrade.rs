@@ -0,0 +1,306 @@
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, Command};
+
+use super::fs;
+use super::install::{self, Pin};
+use super::java;
+use super::manifest::{self, Launch, ServerManifest, ServerType};
+
+/// Folder inside the server holding what the last upgrade replaced.
+const BACKUP_DIR: &str = ".eagle-upgrade";
+
+pub(super) fn build_command() -> Command {
+	Command::new("upgrade")
+		.about("Move a server to a newer build, loader or version")
+		.arg(
+			Arg::new("server")
+				.help("Server folder name (prompts if omitted)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("version")
+				.long("version")
+				.short('v')
+				.help("Target Minecraft version (default: current)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("build")
+				.long("build")
+				.help("Paper/Fill or Purpur build id (default: newest stable)")
+				.value_parser(clap::value_parser!(u64))
+				.required(false),
+		)
+		.arg(
+			Arg::new("loader")
+				.long("loader")
+				.help("Loader version (Fabric/Quilt/Forge/NeoForge)")
+				.required(false),
+		)
+		.arg(
+			Arg::new("backup_world")
+				.long("backup-world")
+				.help("Also copy the world folders before upgrading")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("rollback")
+				.long("rollback")
+				.help(
+					"Restore the jar (and world) replaced by the last upgrade",
+				)
+				.action(clap::ArgAction::SetTrue)
+				.conflicts_with_all(["version", "build", "loader"]),
+		)
+		.arg(
+			Arg::new("force")
+				.long("force")
+				.help(
+					"Upgrade even if the server seems to be running; re-download \
+					 when already on the target build",
+				)
+				.action(clap::ArgAction::SetTrue),
+		)
+}
+
+pub(super) fn run_upgrade(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server to upgrade",
+	)?;
+
+	if !matches.get_flag("force") && fs::is_running(&server)? {
+		anyhow::bail!(
+			"{} appears to be running (port {} is open); stop it first or pass --force",
+			server.name(),
+			fs::server_port(&server)?
+		);
+	}
+
+	if matches.get_flag("rollback") {
+		return rollback(&server.path);
+	}
+
+	let current = server.manifest.clone().ok_or_else(|| {
+		anyhow::anyhow!(
+			"{} has no {} (run `eagle minecraft adopt {}` first)",
+			server.name(),
+			manifest::MANIFEST_FILE,
+			server.name()
+		)
+	})?;
+
+	let server_type = current.server_type;
+	let version = match matches.get_one::<String>("version") {
+		Some(v) => install::resolve_version(server_type, v)?,
+		None => current.version.clone(),
+	};
+
+	let pin = Pin {
+		build: matches.get_one::<u64>("build").copied(),
+		loader: matches.get_one::<String>("loader").cloned(),
+	};
+	let build = install::resolve_build(server_type, &version, &pin)?;
+
+	let mut next =
+		target_manifest(&current, &version, &build, java::required_major);
+
+	if current.version == version
+		&& build.is_installed(&current)
+		&& !matches.get_flag("force")
+	{
+		println!("Already on {}", current.describe());
+		return Ok(());
+	}
+
+	backup_current(&server.path, &current, matches.get_flag("backup_world"))?;
+	match install::install(server_type, &version, &build, &server.path) {
+		Ok(launch) => next.launch = launch,
+		Err(err) => {
+			eprintln!("Upgrade failed, restoring previous files...");
+			restore(&server.path, false)?;
+			return Err(err);
+		}
+	}
+	manifest::save(&server.path, &next)?;
+
+	println!(
+		"Upgraded {}: {} -> {}",
+		server.name(),
+		current.describe(),
+		next.describe()
+	);
+	println!(
+		"Undo with: eagle minecraft upgrade {} --rollback",
+		server.name()
+	);
+	Ok(())
+}
+
+/// `current` moved to `version` and `build`. A new Minecraft version can
+/// need a different Java, so the cached major is looked up again.
+fn target_manifest(
+	current: &ServerManifest,
+	version: &str,
+	build: &install::Build,
+	required_major: impl FnOnce(ServerType, &str) -> u32,
+) -> ServerManifest {
+	let mut next = current.clone();
+	next.version = version.to_string();
+	build.clone().apply(&mut next);
+	if current.version != version && current.java_major.is_some() {
+		next.java_major = Some(required_major(next.server_type, version));
+	}
+	next
+}
+
+/// Copies the current launch files (and optionally the world) aside.
+///
+/// Jars are only ever replaced via an atomic rename, so the copy is what a
+/// failed or unwanted upgrade is rolled back to. Forge-style library folders
+/// are versioned and left in place.
+fn backup_current(
+	server_dir: &Path,
+	current: &ServerManifest,
+	with_world: bool,
+) -> anyhow::Result<()> {
+	let backup_dir = server_dir.join(BACKUP_DIR);
+	if backup_dir.exists() {
+		std::fs::remove_dir_all(&backup_dir)?;
+	}
+	std::fs::create_dir_all(&backup_dir)?;
+
+	let mut files = vec!["server.jar"];
+	if let Launch::Jar { jar } = &current.launch {
+		files.push(jar);
+	}
+	for file in files {
+		let path = server_dir.join(file);
+		if path.is_file() {
+			std::fs::copy(&path, backup_dir.join(file))?;
+		}
+	}
+	manifest::save(&backup_dir, current)?;
+
+	if with_world {
+		for world in fs::world_dirs(server_dir)? {
+			let Some(name) = world.file_name() else {
+				continue;
+			};
+			println!("Backing up {}...", world.display());
+			fs::copy_dir(&world, &backup_dir.join(name))?;
+		}
+	}
+
+	Ok(())
+}
+
+fn rollback(server_dir: &Path) -> anyhow::Result<()> {
+	let backup_dir = server_dir.join(BACKUP_DIR);
+	if !backup_dir.join(manifest::MANIFEST_FILE).exists() {
+		anyhow::bail!("Nothing to roll back in {}", server_dir.display());
+	}
+
+	let restored = restore(server_dir, true)?;
+	std::fs::remove_dir_all(&backup_dir)?;
+	println!("Rolled back to {}", restored.describe());
+	Ok(())
+}
+
+/// Puts the files from the backup folder back in place and returns the
+/// manifest they belong to.
+fn restore(
+	server_dir: &Path,
+	with_world: bool,
+) -> anyhow::Result<ServerManifest> {
+	let backup_dir = server_dir.join(BACKUP_DIR);
+	let restored = manifest::load(&backup_dir)?.ok_or_else(|| {
+		anyhow::anyhow!("Backup in {} has no manifest", backup_dir.display())
+	})?;
+
+	for entry in std::fs::read_dir(&backup_dir)? {
+		let entry = entry?;
+		let name = entry.file_name();
+		let target = server_dir.join(&name);
+
+		if entry.file_type()?.is_dir() {
+			if !with_world {
+				continue;
+			}
+			println!("Restoring {}...", target.display());
+			if target.exists() {
+				std::fs::remove_dir_all(&target)?;
+			}
+			fs::copy_dir(&entry.path(), &target)?;
+		} else if name != manifest::MANIFEST_FILE {
+			let mut tmp_name = name.clone();
+			tmp_name.push(".restore");
+			let tmp = server_dir.join(tmp_name);
+			std::fs::copy(entry.path(), &tmp)?;
+			std::fs::rename(&tmp, &target)?;
+		}
+	}
+
+	manifest::save(server_dir, &restored)?;
+	Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn read(path: &Path) -> String {
+		std::fs::read_to_string(path).unwrap()
+	}
+
+	#[test]
+	fn new_version_refreshes_the_java_major() {
+		let mut current =
+			ServerManifest::new(ServerType::Fabric, "1.20.1", 25565);
+		current.java_major = Some(17);
+		let build = install::Build::default();
+		let major =
+			|_: ServerType, v: &str| if v == "1.21.4" { 21 } else { 17 };
+
+		let next = target_manifest(&current, "1.21.4", &build, major);
+		assert_eq!(
+			(next.version.as_str(), next.java_major),
+			("1.21.4", Some(21))
+		);
+
+		let same = target_manifest(&current, "1.20.1", &build, |_, _| {
+			unreachable!("same version keeps the cached major")
+		});
+		assert_eq!(same.java_major, Some(17));
+	}
+
+	#[test]
+	fn failed_upgrade_restores_jar_and_rollback_restores_world() {
+		let dir = fs::TestDir::new("upgrade");
+		std::fs::create_dir_all(dir.join("world")).unwrap();
+		std::fs::write(dir.join("server.jar"), "old").unwrap();
+		std::fs::write(dir.join("world/level.dat"), "w1").unwrap();
+		let current = ServerManifest::new(ServerType::Paper, "1.21.4", 25565);
+		manifest::save(&dir, &current).unwrap();
+
+		backup_current(&dir, &current, true).unwrap();
+		std::fs::write(dir.join("server.jar"), "new").unwrap();
+		std::fs::write(dir.join("world/level.dat"), "w2").unwrap();
+
+		// What a failed install does: the jar only, the world is untouched.
+		let restored = restore(&dir, false).unwrap();
+		assert_eq!(restored, current);
+		assert_eq!(read(&dir.join("server.jar")), "old");
+		assert_eq!(read(&dir.join("world/level.dat")), "w2");
+
+		std::fs::write(dir.join("server.jar"), "new").unwrap();
+		rollback(&dir).unwrap();
+		assert_eq!(read(&dir.join("server.jar")), "old");
+		assert_eq!(read(&dir.join("world/level.dat")), "w1");
+		assert!(!dir.join(BACKUP_DIR).exists());
+		assert!(rollback(&dir).is_err());
+	}
+}
@@ -0,0 +1,186 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::fill;
+use crate::net;
+
+const VERSION_MANIFEST_URL: &str =
+	"https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// Minimal shape of Mojang's `version_manifest_v2.json`.
+#[derive(Debug, Clone, Deserialize)]
+struct VersionManifest {
+	latest: Latest,
+	versions: Vec<VersionEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Latest {
+	release: String,
+	snapshot: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionEntry {
+	id: String,
+	#[serde(rename = "type")]
+	kind: String,
+	url: String,
+}
+
+/// Minimal shape of a per-version JSON (`versions[].url`).
+#[derive(Debug, Clone, Deserialize)]
+struct VersionDetails {
+	downloads: VersionDownloads,
+	#[serde(rename = "javaVersion")]
+	java_version: Option<JavaVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct JavaVersion {
+	#[serde(rename = "majorVersion")]
+	major_version: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionDownloads {
+	server: Option<VersionDownload>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionDownload {
+	sha1: String,
+	url: String,
+}
+
+/// Resolves `latest`, `latest-snapshot`, family keys (`1.21`) and exact ids
+/// against Mojang's version manifest.
+pub(super) fn resolve_vanilla_version(version: &str) -> anyhow::Result<String> {
+	let manifest = net::get_json::<VersionManifest>(VERSION_MANIFEST_URL)?;
+	pick_version(&manifest, version)
+		.map(str::to_string)
+		.ok_or_else(|| anyhow::anyhow!("Unknown Minecraft version: {version}"))
+}
+
+fn pick_version<'a>(
+	manifest: &'a VersionManifest,
+	input: &str,
+) -> Option<&'a str> {
+	let input = input.trim();
+	match input {
+		"latest" => return Some(&manifest.latest.release),
+		"latest-snapshot" => return Some(&manifest.latest.snapshot),
+		_ => {}
+	}
+
+	if fill::looks_like_family_key(input) {
+		let prefix = format!("{input}.");
+		// The manifest lists newest first.
+		return manifest
+			.versions
+			.iter()
+			.filter(|v| v.kind == "release")
+			.find(|v| v.id == input || v.id.starts_with(&prefix))
+			.map(|v| v.id.as_str());
+	}
+
+	manifest
+		.versions
+		.iter()
+		.find(|v| v.id == input)
+		.map(|v| v.id.as_str())
+}
+
+/// Downloads the vanilla server jar for an exact version id, verified against
+/// the SHA-1 Mojang publishes for it.
+pub(super) fn download_vanilla_server(
+	version: &str,
+	jar_path: &Path,
+) -> anyhow::Result<()> {
+	println!("Downloading Vanilla {version}...");
+
+	let details = fetch_details(version)?;
+	let server = details.downloads.server.ok_or_else(|| {
+		anyhow::anyhow!("Minecraft {version} has no server download")
+	})?;
+
+	net::download_verified(
+		&server.url,
+		jar_path,
+		Some(net::Checksum::Sha1(&server.sha1)),
+	)?;
+	Ok(())
+}
+
+/// Java major version Mojang declares for `version` (`None` for very old
+/// versions that predate the field).
+pub(super) fn java_major(version: &str) -> anyhow::Result<Option<u32>> {
+	let details = fetch_details(version)?;
+	Ok(details.java_version.map(|j| j.major_version))
+}
+
+fn fetch_details(version: &str) -> anyhow::Result<VersionDetails> {
+	let manifest = net::get_json::<VersionManifest>(VERSION_MANIFEST_URL)?;
+	let entry = manifest
+		.versions
+		.iter()
+		.find(|v| v.id == version)
+		.ok_or_else(|| {
+			anyhow::anyhow!("Unknown Minecraft version: {version}")
+		})?;
+
+	net::get_json::<VersionDetails>(&entry.url)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(id: &str, kind: &str) -> VersionEntry {
+		VersionEntry {
+			id: id.to_string(),
+			kind: kind.to_string(),
+			url: String::new(),
+		}
+	}
+
+	fn manifest() -> VersionManifest {
+		VersionManifest {
+			latest: Latest {
+				release: "1.21.4".to_string(),
+				snapshot: "25w02a".to_string(),
+			},
+			versions: vec![
+				entry("25w02a", "snapshot"),
+				entry("1.21.4", "release"),
+				entry("1.21.4-rc1", "snapshot"),
+				entry("1.21.3", "release"),
+				entry("1.21", "release"),
+				entry("1.20.6", "release"),
+			],
+		}
+	}
+
+	#[test]
+	fn resolves_latest_aliases() {
+		let m = manifest();
+		assert_eq!(pick_version(&m, "latest"), Some("1.21.4"));
+		assert_eq!(pick_version(&m, "latest-snapshot"), Some("25w02a"));
+	}
+
+	#[test]
+	fn resolves_family_to_newest_release() {
+		let m = manifest();
+		assert_eq!(pick_version(&m, "1.21"), Some("1.21.4"));
+		assert_eq!(pick_version(&m, "1.20"), Some("1.20.6"));
+		assert_eq!(pick_version(&m, "1.19"), None);
+	}
+
+	#[test]
+	fn resolves_exact_ids_only_when_listed() {
+		let m = manifest();
+		assert_eq!(pick_version(&m, "1.21.4-rc1"), Some("1.21.4-rc1"));
+		assert_eq!(pick_version(&m, "1.21.99"), None);
+	}
+}
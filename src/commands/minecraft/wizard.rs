@@ -0,0 +1,206 @@
+//! Guided `minecraft create`: prompts for every option, using the flags that
+//! were given as defaults, and confirms a summary before writing anything.
+
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use dialoguer::{Confirm, Input, Select};
+
+use super::create::{self, Options};
+use super::fs;
+use super::manifest::ServerType;
+use super::ports;
+use super::properties;
+
+/// `None` when the summary was declined.
+pub(super) fn run(matches: &ArgMatches) -> anyhow::Result<Option<Options>> {
+	let mut options = Options {
+		name: String::new(),
+		server_type: ServerType::ALL[0],
+		version_input: String::new(),
+		port: *matches.get_one::<u16>("port").unwrap_or(&22222),
+		auto_port: matches.value_source("port")
+			!= Some(ValueSource::CommandLine),
+		motd: matches
+			.get_one::<String>("motd")
+			.cloned()
+			.unwrap_or_else(|| "eagle minecraft server".to_string()),
+		ram_mb: matches.get_one::<u32>("ram_mb").copied(),
+		rcon: matches.get_flag("rcon"),
+		query: matches.get_flag("query"),
+		properties: create::property_overrides(matches)?,
+		pack: None,
+		world: matches.get_one::<PathBuf>("world").cloned(),
+	}
+	.with_dedicated_properties();
+
+	let mut name = Input::<String>::new()
+		.with_prompt("Server name")
+		.validate_with(|s: &String| {
+			create::validate_server_name(s).map_err(|e| e.to_string())
+		});
+	if let Some(default) = matches.get_one::<String>("name") {
+		name = name.default(default.clone());
+	}
+	options.name = name.interact_text()?;
+
+	let type_default = matches
+		.get_one::<String>("type")
+		.and_then(|t| ServerType::ALL.iter().position(|s| s.as_str() == t))
+		.unwrap_or(0);
+	options.server_type = ServerType::ALL[Select::new()
+		.with_prompt("Server type")
+		.items(&ServerType::names())
+		.default(type_default)
+		.interact()?];
+	// Before any more prompts, not after the summary was confirmed.
+	options.check_proxy_options()?;
+
+	options.version_input = Input::<String>::new()
+		.with_prompt("Version (e.g. 1.21.11, 1.21 or latest)")
+		.default(
+			matches
+				.get_one::<String>("version")
+				.cloned()
+				.unwrap_or_else(|| "latest".to_string()),
+		)
+		.interact_text()?;
+
+	if options.auto_port {
+		let allocations = ports::allocations(&fs::servers_roots()?)?;
+		let dir = fs::servers_root()?.join(&options.name);
+		options.port = ports::suggest(&allocations, &dir, options.port);
+	}
+	options.port = Input::<u16>::new()
+		.with_prompt("Port")
+		.default(options.port)
+		.interact_text()?;
+	// Picked by hand now; `create` only warns if it is taken.
+	options.auto_port = false;
+	options.motd = Input::<String>::new()
+		.with_prompt("MOTD")
+		.default(options.motd.clone())
+		.interact_text()?;
+
+	let ram = Input::<String>::new()
+		.with_prompt("RAM in MB (empty for the start default)")
+		.default(options.ram_mb.map(|r| r.to_string()).unwrap_or_default())
+		.allow_empty(true)
+		.validate_with(|s: &String| match s.trim() {
+			"" => Ok(()),
+			s => s.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()),
+		})
+		.interact_text()?;
+	options.ram_mb = ram.trim().parse().ok();
+
+	if !options.server_type.is_proxy() {
+		game_settings(&mut options)?;
+		options.rcon = confirm("Enable RCON?", options.rcon)?;
+	}
+	options.query = confirm("Enable Query?", options.query)?;
+
+	print_summary(&options)?;
+	if !confirm("Create this server?", true)? {
+		return Ok(None);
+	}
+	Ok(Some(options))
+}
+
+fn game_settings(options: &mut Options) -> anyhow::Result<()> {
+	for (key, label, names, default) in [
+		("gamemode", "Game mode", properties::GAMEMODES, "survival"),
+		("difficulty", "Difficulty", properties::DIFFICULTIES, "easy"),
+	] {
+		let current = options.property(key).unwrap_or(default);
+		let index = names.iter().position(|n| *n == current).unwrap_or(0);
+		let pick = Select::new()
+			.with_prompt(label)
+			.items(names)
+			.default(index)
+			.interact()?;
+		options.set_property(key, names[pick].to_string());
+	}
+
+	for (key, label, default) in [
+		("max-players", "Max players", "20"),
+		("view-distance", "View distance", "10"),
+	] {
+		let value = Input::<String>::new()
+			.with_prompt(label)
+			.default(options.property(key).unwrap_or(default).to_string())
+			.validate_with(|s: &String| {
+				properties::normalize(key, s)
+					.map(|_| ())
+					.map_err(|e| e.to_string())
+			})
+			.interact_text()?;
+		options.set_property(key, properties::normalize(key, &value)?);
+	}
+
+	let seed = Input::<String>::new()
+		.with_prompt("Seed (empty for random)")
+		.default(options.property("level-seed").unwrap_or("").to_string())
+		.allow_empty(true)
+		.interact_text()?;
+	options.set_property("level-seed", seed);
+
+	for (key, label, default) in [
+		("online-mode", "Online mode (Mojang account check)?", true),
+		("white-list", "Whitelist?", false),
+		("pvp", "PvP?", true),
+	] {
+		let current = options
+			.property(key)
+			.map(|v| v == "true")
+			.unwrap_or(default);
+		let value = confirm(label, current)?;
+		options.set_property(key, value.to_string());
+		if key == "white-list" {
+			options.set_property("enforce-whitelist", value.to_string());
+		}
+	}
+	Ok(())
+}
+
+fn confirm(prompt: &str, default: bool) -> anyhow::Result<bool> {
+	Ok(Confirm::new()
+		.with_prompt(prompt)
+		.default(default)
+		.interact()?)
+}
+
+fn print_summary(options: &Options) -> anyhow::Result<()> {
+	let yes_no = |b: bool| if b { "yes" } else { "no" };
+	let folder = fs::servers_root()?.join(&options.name);
+
+	println!();
+	println!("Summary");
+	println!("  Folder:   {}", folder.display());
+	if folder.exists() {
+		println!("            (exists; needs --force)");
+	}
+	println!("  Type:     {}", options.server_type.as_str());
+	println!("  Version:  {}", options.version_input);
+	println!("  Port:     {}", options.port);
+	println!("  MOTD:     {}", options.motd);
+	match options.ram_mb {
+		Some(ram) => println!("  RAM:      {ram} MB"),
+		None => println!("  RAM:      default"),
+	}
+	if !options.server_type.is_proxy() {
+		println!("  RCON:     {}", yes_no(options.rcon));
+	}
+	println!("  Query:    {}", yes_no(options.query));
+	if let Some(world) = &options.world {
+		println!("  World:    {}", world.display());
+	}
+	if !options.properties.is_empty() {
+		println!("  server.properties:");
+		for (key, value) in &options.properties {
+			println!("    {key}={value}");
+		}
+	}
+	println!();
+	Ok(())
+}
@@ -0,0 +1,414 @@
+//! Worlds in and out of servers, and generation presets. Singleplayer saves
+//! (and vanilla-style servers) keep the Nether and End inside the world
+//! folder as `DIM-1`/`DIM1`; Bukkit-based servers split them into
+//! `<level>_nether/DIM-1` and `<level>_the_end/DIM1`.
+
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgMatches, Command};
+
+use super::archive;
+use super::fs;
+use super::manifest::ServerType;
+
+const IMPORT_TMP: &str = ".eagle-world.part";
+const LEVEL_DAT: &str = "level.dat";
+/// (dimension folder, split world suffix).
+const DIMENSIONS: [(&str, &str); 2] =
+	[("DIM-1", "_nether"), ("DIM1", "_the_end")];
+
+/// Named bundles of generation settings for reproducible test worlds.
+pub(super) struct Preset {
+	pub(super) name: &'static str,
+	pub(super) properties: &'static [(&'static str, &'static str)],
+}
+
+pub(super) const PRESETS: [Preset; 4] = [
+	Preset {
+		name: "flat",
+		properties: &[
+			("level-type", "minecraft:flat"),
+			(
+				"generator-settings",
+				r#"{"layers":[{"block":"minecraft:bedrock","height":1},{"block":"minecraft:dirt","height":2},{"block":"minecraft:grass_block","height":1}],"biome":"minecraft:plains"}"#,
+			),
+		],
+	},
+	Preset {
+		name: "void",
+		properties: &[
+			("level-type", "minecraft:flat"),
+			(
+				"generator-settings",
+				r#"{"layers":[{"block":"minecraft:air","height":1}],"biome":"minecraft:the_void"}"#,
+			),
+			("generate-structures", "false"),
+		],
+	},
+	Preset {
+		name: "amplified",
+		properties: &[("level-type", "minecraft:amplified")],
+	},
+	Preset {
+		name: "large-biomes",
+		properties: &[("level-type", "minecraft:large_biomes")],
+	},
+];
+
+pub(super) fn preset_names() -> Vec<&'static str> {
+	PRESETS.iter().map(|p| p.name).collect()
+}
+
+pub(super) fn preset(name: &str) -> anyhow::Result<&'static Preset> {
+	PRESETS
+		.iter()
+		.find(|p| p.name == name)
+		.ok_or_else(|| anyhow::anyhow!("Unknown world preset: {name}"))
+}
+
+/// `generator-settings` JSON from a superflat code as the game's preset
+/// screen writes it, bottom layer first: `bedrock,2*dirt,grass_block;plains`.
+pub(super) fn superflat_settings(code: &str) -> anyhow::Result<String> {
+	let id = |s: &str| match s.trim() {
+		s if s.contains(':') => s.to_string(),
+		s => format!("minecraft:{s}"),
+	};
+	let (layers, biome) = code.split_once(';').unwrap_or((code, "plains"));
+
+	let mut json = Vec::new();
+	for layer in layers.split(',').filter(|l| !l.trim().is_empty()) {
+		let (height, block) = match layer.split_once('*') {
+			Some((n, block)) => (
+				n.trim().parse::<u32>().map_err(|_| {
+					anyhow::anyhow!("Invalid layer height in {layer:?}")
+				})?,
+				block,
+			),
+			None => (1, layer),
+		};
+		json.push(serde_json::json!({ "block": id(block), "height": height }));
+	}
+	if json.is_empty() {
+		anyhow::bail!("Superflat code has no layers: {code}");
+	}
+	Ok(serde_json::json!({ "layers": json, "biome": id(biome) }).to_string())
+}
+
+/// Bukkit-based servers keep each dimension in its own world folder.
+pub(super) fn splits_dimensions(server_type: ServerType) -> bool {
+	matches!(
+		server_type,
+		ServerType::Paper | ServerType::Purpur | ServerType::Folia
+	)
+}
+
+/// Where each dimension of a world lives on disk.
+#[derive(Debug, PartialEq, Eq)]
+struct Layout {
+	overworld: PathBuf,
+	/// The `DIM-1` / `DIM1` folders themselves.
+	nether: Option<PathBuf>,
+	end: Option<PathBuf>,
+}
+
+impl Layout {
+	/// Finds the dimensions inside `level_dir` or in split siblings.
+	fn detect(level_dir: &Path) -> Self {
+		let name = level_dir
+			.file_name()
+			.map(|n| n.to_string_lossy().into_owned())
+			.unwrap_or_default();
+		let [nether, end] = DIMENSIONS.map(|(dim, suffix)| {
+			let inner = level_dir.join(dim);
+			let split = level_dir
+				.with_file_name(format!("{name}{suffix}"))
+				.join(dim);
+			[inner, split].into_iter().find(|p| p.is_dir())
+		});
+		Self {
+			overworld: level_dir.to_path_buf(),
+			nether,
+			end,
+		}
+	}
+
+	fn is_split(&self) -> bool {
+		[&self.nether, &self.end]
+			.into_iter()
+			.flatten()
+			.any(|d| !d.starts_with(&self.overworld))
+	}
+
+	fn describe(&self) -> &'static str {
+		match self.is_split() {
+			true => "split dimension folders",
+			false => "singleplayer layout",
+		}
+	}
+}
+
+/// Copies a world folder, `.zip` or `.tar.zst` into `server_dir` as `level`,
+/// converting it to the layout `split` asks for.
+pub(super) fn import(
+	source: &Path,
+	server_dir: &Path,
+	level: &str,
+	split: bool,
+) -> anyhow::Result<()> {
+	if server_dir.join(level).exists() {
+		anyhow::bail!("World folder already exists: {level}");
+	}
+	if source.is_dir() {
+		return place(
+			&Layout::detect(&find_level(source)?),
+			server_dir,
+			level,
+			split,
+		);
+	}
+
+	let tmp = server_dir.join(IMPORT_TMP);
+	let result = archive::extract(source, &tmp).and_then(|()| {
+		place(
+			&Layout::detect(&find_level(&tmp)?),
+			server_dir,
+			level,
+			split,
+		)
+	});
+	let _ = std::fs::remove_dir_all(&tmp);
+	result
+}
+
+/// `dir` itself when it holds `level.dat`, else its one child that does
+/// (split `_nether`/`_the_end` siblings aside).
+fn find_level(dir: &Path) -> anyhow::Result<PathBuf> {
+	if dir.join(LEVEL_DAT).is_file() {
+		return Ok(dir.to_path_buf());
+	}
+	let mut found = Vec::new();
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		let name = path.file_name().unwrap_or_default().to_string_lossy();
+		let dimension = DIMENSIONS.iter().any(|(_, s)| name.ends_with(s));
+		if !dimension && path.join(LEVEL_DAT).is_file() {
+			found.push(path);
+		}
+	}
+	match found.len() {
+		1 => Ok(found.swap_remove(0)),
+		0 => anyhow::bail!("No {LEVEL_DAT} found in {}", dir.display()),
+		_ => anyhow::bail!("{} holds more than one world", dir.display()),
+	}
+}
+
+fn place(
+	layout: &Layout,
+	server_dir: &Path,
+	level: &str,
+	split: bool,
+) -> anyhow::Result<()> {
+	let target = match split {
+		true => "split dimension folders",
+		false => "singleplayer layout",
+	};
+	println!("Importing world ({} -> {target})", layout.describe());
+
+	let main = server_dir.join(level);
+	std::fs::create_dir_all(&main)?;
+	for entry in std::fs::read_dir(&layout.overworld)? {
+		let entry = entry?;
+		let name = entry.file_name();
+		if DIMENSIONS.iter().any(|(dim, _)| name == *dim)
+			|| name == "session.lock"
+		{
+			continue;
+		}
+		match entry.file_type()?.is_dir() {
+			true => fs::copy_dir(&entry.path(), &main.join(&name))?,
+			false => {
+				std::fs::copy(entry.path(), main.join(&name))?;
+			}
+		}
+	}
+
+	for (src, (dim, suffix)) in
+		[&layout.nether, &layout.end].into_iter().zip(DIMENSIONS)
+	{
+		let Some(src) = src else {
+			continue;
+		};
+		let dest = match split {
+			true => {
+				let dir = server_dir.join(format!("{level}{suffix}"));
+				std::fs::create_dir_all(&dir)?;
+				std::fs::copy(main.join(LEVEL_DAT), dir.join(LEVEL_DAT))?;
+				dir.join(dim)
+			}
+			false => main.join(dim),
+		};
+		fs::copy_dir(src, &dest)?;
+	}
+	Ok(())
+}
+
+pub(super) fn build_command() -> Command {
+	Command::new("world")
+		.about("Move worlds between servers and singleplayer")
+		.subcommand_required(true)
+		.subcommand(
+			Command::new("export")
+				.about("Zip a server's world so singleplayer can load it")
+				.arg(
+					Arg::new("server")
+						.help("Server folder name (prompts if omitted)")
+						.required(false),
+				)
+				.arg(
+					Arg::new("output")
+						.long("output")
+						.short('o')
+						.help("Zip to write (default: <server>.zip here)")
+						.value_parser(clap::value_parser!(PathBuf)),
+				)
+				.arg(
+					Arg::new("force")
+						.long("force")
+						.help("Export even if the server seems to be running")
+						.action(clap::ArgAction::SetTrue),
+				),
+		)
+}
+
+pub(super) fn run_world(matches: &ArgMatches) -> anyhow::Result<()> {
+	match matches.subcommand() {
+		Some(("export", sub)) => run_export(sub),
+		_ => unreachable!("subcommand_required"),
+	}
+}
+
+fn run_export(matches: &ArgMatches) -> anyhow::Result<()> {
+	let roots = fs::servers_roots()?;
+	let server = fs::select_server(
+		&roots,
+		matches.get_one::<String>("server").map(|s| s.as_str()),
+		"Select a server to export",
+	)?;
+	if !matches.get_flag("force") && fs::is_running(&server)? {
+		anyhow::bail!(
+			"{} appears to be running (port {} is open); stop it first or pass --force",
+			server.name(),
+			fs::server_port(&server)?
+		);
+	}
+
+	let props = fs::read_properties(&server.path)?;
+	let level = props
+		.get("level-name")
+		.filter(|s| !s.is_empty())
+		.map(String::as_str)
+		.unwrap_or("world");
+	let level_dir = server.path.join(level);
+	if !level_dir.join(LEVEL_DAT).is_file() {
+		anyhow::bail!("No world to export in {}", level_dir.display());
+	}
+
+	let dest = matches
+		.get_one::<PathBuf>("output")
+		.cloned()
+		.unwrap_or_else(|| PathBuf::from(format!("{}.zip", server.name())));
+	if dest.exists() {
+		anyhow::bail!("{} already exists", dest.display());
+	}
+
+	let files = export_files(&Layout::detect(&level_dir), server.name())?;
+	archive::create_zip_from(&files, &dest)?;
+	println!(
+		"Exported {} ({} files) to {}; unzip it into .minecraft/saves",
+		server.name(),
+		files.len(),
+		dest.display()
+	);
+	Ok(())
+}
+
+/// Every file of `layout` with its name in a singleplayer save `top`.
+fn export_files(
+	layout: &Layout,
+	top: &str,
+) -> anyhow::Result<Vec<(PathBuf, String)>> {
+	let mut out = Vec::new();
+	for file in archive::walk(&layout.overworld)? {
+		let name = archive::entry_name(&layout.overworld, &file)?;
+		let dimension = DIMENSIONS
+			.iter()
+			.any(|(dim, _)| name.starts_with(&format!("{dim}/")));
+		if !dimension {
+			out.push((file, format!("{top}/{name}")));
+		}
+	}
+	for (src, (dim, _)) in
+		[&layout.nether, &layout.end].into_iter().zip(DIMENSIONS)
+	{
+		let Some(src) = src else {
+			continue;
+		};
+		for file in archive::walk(src)? {
+			let name = archive::entry_name(src, &file)?;
+			out.push((file, format!("{top}/{dim}/{name}")));
+		}
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_superflat_codes() {
+		assert_eq!(
+			superflat_settings("bedrock,2*dirt,grass_block").unwrap(),
+			r#"{"biome":"minecraft:plains","layers":[{"block":"minecraft:bedrock","height":1},{"block":"minecraft:dirt","height":2},{"block":"minecraft:grass_block","height":1}]}"#
+		);
+		assert!(
+			superflat_settings("minecraft:stone;minecraft:desert")
+				.unwrap()
+				.contains(r#""biome":"minecraft:desert""#)
+		);
+		assert!(superflat_settings("x*stone").is_err());
+		assert!(superflat_settings(";plains").is_err());
+	}
+
+	#[test]
+	fn converts_between_layouts() {
+		let tmp = fs::TestDir::new("world");
+		let save = tmp.join("saves").join("My World");
+		std::fs::create_dir_all(save.join("DIM-1/region")).unwrap();
+		std::fs::create_dir_all(save.join("region")).unwrap();
+		std::fs::write(save.join(LEVEL_DAT), b"level").unwrap();
+		std::fs::write(save.join("DIM-1/region/r.0.0.mca"), b"n").unwrap();
+
+		let paper = tmp.join("paper");
+		import(&tmp.join("saves"), &paper, "world", true).unwrap();
+		assert!(paper.join("world/level.dat").is_file());
+		assert!(!paper.join("world/DIM-1").exists());
+		assert!(paper.join("world_nether/DIM-1/region/r.0.0.mca").is_file());
+		assert!(!paper.join("world_the_end").exists());
+
+		let layout = Layout::detect(&paper.join("world"));
+		assert!(layout.is_split());
+		let mut names: Vec<String> = export_files(&layout, "w")
+			.unwrap()
+			.into_iter()
+			.map(|(_, name)| name)
+			.collect();
+		names.sort();
+		assert_eq!(names, ["w/DIM-1/region/r.0.0.mca", "w/level.dat"]);
+
+		let vanilla = tmp.join("vanilla");
+		import(&paper.join("world"), &vanilla, "world", false).unwrap();
+		assert!(vanilla.join("world/DIM-1/region/r.0.0.mca").is_file());
+		assert!(!Layout::detect(&vanilla.join("world")).is_split());
+	}
+}
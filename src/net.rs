@@ -6,17 +6,21 @@
 //! - helpers are pure where possible and tested
 
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use serde::de::DeserializeOwned;
 
+/// Some APIs (Modrinth) ask clients to identify themselves.
+const USER_AGENT: &str =
+	concat!("prodbyeagle/eagle/", env!("CARGO_PKG_VERSION"));
+
 /// Performs a blocking HTTP GET and deserializes the response body as JSON.
 ///
 /// Errors if the server response is not `200 OK` or if the body cannot be
 /// deserialized.
 pub fn get_json<T: DeserializeOwned>(url: &str) -> anyhow::Result<T> {
-	let resp = ureq::get(url).call()?;
+	let resp = ureq::get(url).header("User-Agent", USER_AGENT).call()?;
 	let status = resp.status();
 	if status != 200 {
 		anyhow::bail!("HTTP {status} for {url}");
@@ -30,12 +34,123 @@ pub fn get_json<T: DeserializeOwned>(url: &str) -> anyhow::Result<T> {
 	Ok(json)
 }
 
+/// Performs a blocking HTTP GET and returns the body as trimmed UTF-8 text.
+pub fn get_text(url: &str) -> anyhow::Result<String> {
+	let resp = ureq::get(url).header("User-Agent", USER_AGENT).call()?;
+	let status = resp.status();
+	if status != 200 {
+		anyhow::bail!("HTTP {status} for {url}");
+	}
+
+	let mut body = String::new();
+	resp.into_body().into_reader().read_to_string(&mut body)?;
+	Ok(body.trim().to_string())
+}
+
+/// Digest a download is expected to match (lowercase or uppercase hex).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum<'a> {
+	Md5(&'a str),
+	Sha1(&'a str),
+	Sha256(&'a str),
+	Sha512(&'a str),
+}
+
+impl Checksum<'_> {
+	fn algorithm(&self) -> &'static str {
+		match self {
+			Self::Md5(_) => "md5",
+			Self::Sha1(_) => "sha1",
+			Self::Sha256(_) => "sha256",
+			Self::Sha512(_) => "sha512",
+		}
+	}
+
+	fn expected(&self) -> &str {
+		match self {
+			Self::Md5(hex)
+			| Self::Sha1(hex)
+			| Self::Sha256(hex)
+			| Self::Sha512(hex) => hex,
+		}
+	}
+
+	fn hasher(&self) -> Hasher {
+		match self {
+			Self::Md5(_) => Hasher::Md5(md5::Md5::default()),
+			Self::Sha1(_) => Hasher::Sha1(sha1::Sha1::default()),
+			Self::Sha256(_) => Hasher::Sha256(sha2::Sha256::default()),
+			Self::Sha512(_) => Hasher::Sha512(sha2::Sha512::default()),
+		}
+	}
+}
+
+enum Hasher {
+	Md5(md5::Md5),
+	Sha1(sha1::Sha1),
+	Sha256(sha2::Sha256),
+	Sha512(sha2::Sha512),
+}
+
+impl Hasher {
+	fn update(&mut self, data: &[u8]) {
+		use sha2::Digest;
+
+		match self {
+			Self::Md5(h) => h.update(data),
+			Self::Sha1(h) => h.update(data),
+			Self::Sha256(h) => h.update(data),
+			Self::Sha512(h) => h.update(data),
+		}
+	}
+
+	fn finish_hex(self) -> String {
+		use sha2::Digest;
+
+		match self {
+			Self::Md5(h) => to_hex(&h.finalize()),
+			Self::Sha1(h) => to_hex(&h.finalize()),
+			Self::Sha256(h) => to_hex(&h.finalize()),
+			Self::Sha512(h) => to_hex(&h.finalize()),
+		}
+	}
+}
+
 /// Downloads a URL to a file, streaming to disk and showing a simple progress
 /// bar when `Content-Length` is available.
+///
+/// Data is written to `<out_path>.part` and only renamed into place once the
+/// download completed, so an interrupted transfer never replaces a good file.
 pub fn download_to_file(url: &str, out_path: &Path) -> anyhow::Result<()> {
+	download_verified(url, out_path, None)
+}
+
+/// Like [`download_to_file`], but hashes the stream and refuses to move the
+/// file into place unless it matches `expected`.
+pub fn download_verified(
+	url: &str,
+	out_path: &Path,
+	expected: Option<Checksum<'_>>,
+) -> anyhow::Result<()> {
+	let part_path = part_path(out_path);
+	let result = download_part(url, &part_path, expected);
+	if result.is_err() {
+		let _ = std::fs::remove_file(&part_path);
+	}
+	result?;
+
+	std::fs::rename(&part_path, out_path)?;
+	Ok(())
+}
+
+fn download_part(
+	url: &str,
+	part_path: &Path,
+	expected: Option<Checksum<'_>>,
+) -> anyhow::Result<()> {
 	use std::io::Write;
 
-	let resp = ureq::get(url).call()?;
+	let resp = ureq::get(url).header("User-Agent", USER_AGENT).call()?;
 	let status = resp.status();
 	if status != 200 {
 		anyhow::bail!("Download failed (HTTP {status})");
@@ -48,7 +163,8 @@ pub fn download_to_file(url: &str, out_path: &Path) -> anyhow::Result<()> {
 		.and_then(|s| s.parse::<u64>().ok());
 
 	let mut reader = resp.into_body().into_reader();
-	let mut file = std::fs::File::create(out_path)?;
+	let mut file = std::fs::File::create(part_path)?;
+	let mut hasher = expected.map(|c| c.hasher());
 
 	let mut downloaded: u64 = 0;
 	let mut buf = vec![0_u8; 64 * 1024];
@@ -64,6 +180,9 @@ pub fn download_to_file(url: &str, out_path: &Path) -> anyhow::Result<()> {
 		}
 
 		file.write_all(&buf[..n])?;
+		if let Some(hasher) = hasher.as_mut() {
+			hasher.update(&buf[..n]);
+		}
 		downloaded += n as u64;
 
 		if last_draw.elapsed() >= Duration::from_millis(120) {
@@ -75,10 +194,67 @@ pub fn download_to_file(url: &str, out_path: &Path) -> anyhow::Result<()> {
 	draw_progress(downloaded, total_bytes)?;
 	println!();
 	file.flush()?;
+	file.sync_all()?;
+
+	if let Some(total) = total_bytes
+		&& downloaded != total
+	{
+		anyhow::bail!("Download truncated: got {downloaded} of {total} bytes");
+	}
+
+	if let (Some(expected), Some(hasher)) = (expected, hasher) {
+		verify_digest(&expected, &hasher.finish_hex())?;
+	}
 
 	Ok(())
 }
 
+/// Hashes an existing file and errors unless it matches `expected`.
+pub fn verify_file(path: &Path, expected: Checksum<'_>) -> anyhow::Result<()> {
+	let mut file = std::fs::File::open(path)?;
+	let mut hasher = expected.hasher();
+	let mut buf = vec![0_u8; 64 * 1024];
+	loop {
+		let n = file.read(&mut buf)?;
+		if n == 0 {
+			break;
+		}
+		hasher.update(&buf[..n]);
+	}
+	verify_digest(&expected, &hasher.finish_hex())
+}
+
+/// `<file>.part` next to `out_path`; written first, then renamed into place.
+pub fn part_path(out_path: &Path) -> PathBuf {
+	let mut name = out_path
+		.file_name()
+		.map(|n| n.to_os_string())
+		.unwrap_or_default();
+	name.push(".part");
+	out_path.with_file_name(name)
+}
+
+fn verify_digest(expected: &Checksum<'_>, actual: &str) -> anyhow::Result<()> {
+	if expected.expected().trim().eq_ignore_ascii_case(actual) {
+		return Ok(());
+	}
+
+	anyhow::bail!(
+		"Checksum mismatch ({}): expected {}, got {actual}",
+		expected.algorithm(),
+		expected.expected().trim().to_lowercase()
+	)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+	use std::fmt::Write;
+
+	bytes.iter().fold(String::new(), |mut s, b| {
+		let _ = write!(s, "{b:02x}");
+		s
+	})
+}
+
 fn draw_progress(downloaded: u64, total: Option<u64>) -> anyhow::Result<()> {
 	use std::io::Write;
 
@@ -110,7 +286,7 @@ fn draw_progress(downloaded: u64, total: Option<u64>) -> anyhow::Result<()> {
 	Ok(())
 }
 
-fn format_bytes(n: u64) -> String {
+pub fn format_bytes(n: u64) -> String {
 	const KIB: f64 = 1024.0;
 	const MIB: f64 = KIB * 1024.0;
 	const GIB: f64 = MIB * 1024.0;
@@ -152,4 +328,53 @@ mod tests {
 	fn format_bytes_gib() {
 		assert_eq!(format_bytes(1024 * 1024 * 1024), "1.0GiB");
 	}
+
+	#[test]
+	fn part_path_appends_suffix() {
+		assert_eq!(
+			part_path(Path::new("srv/server.jar")),
+			PathBuf::from("srv/server.jar.part")
+		);
+	}
+
+	#[test]
+	fn sha256_of_abc() {
+		let mut h = Checksum::Sha256("").hasher();
+		h.update(b"abc");
+		assert_eq!(
+			h.finish_hex(),
+			"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+		);
+	}
+
+	#[test]
+	fn md5_of_abc() {
+		let mut h = Checksum::Md5("").hasher();
+		h.update(b"abc");
+		assert_eq!(h.finish_hex(), "900150983cd24fb0d6963f7d28e17f72");
+	}
+
+	#[test]
+	fn sha1_of_abc() {
+		let mut h = Checksum::Sha1("").hasher();
+		h.update(b"abc");
+		assert_eq!(h.finish_hex(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+	}
+
+	#[test]
+	fn sha512_of_abc() {
+		let mut h = Checksum::Sha512("").hasher();
+		h.update(b"abc");
+		assert!(h.finish_hex().starts_with("ddaf35a193617aba"));
+	}
+
+	#[test]
+	fn verify_digest_is_case_insensitive() {
+		assert!(verify_digest(&Checksum::Sha256("ABCD"), "abcd").is_ok());
+
+		let err = verify_digest(&Checksum::Sha256("abcd"), "ef01")
+			.unwrap_err()
+			.to_string();
+		assert!(err.contains("abcd") && err.contains("ef01"));
+	}
 }